@@ -22,6 +22,7 @@ use std::collections::HashMap;
 use crypto::PublicKey;
 use node::{ExternalMessage, ApiSender};
 use blockchain::{Service, Blockchain, SharedNodeState};
+use storage;
 use api::{Api, ApiError};
 use messages::{TEST_NETWORK_ID, PROTOCOL_MAJOR_VERSION};
 
@@ -211,6 +212,39 @@ impl SystemApi {
         );
     }
 
+    fn handle_indexes(self, router: &mut Router) {
+        let self_ = self.clone();
+        let indexes = move |_: &mut Request| -> IronResult<Response> {
+            let indexes = storage::list_indexes(&*self_.blockchain.snapshot());
+            self_.ok_response(&serde_json::to_value(indexes).unwrap())
+        };
+
+        let index = move |request: &mut Request| -> IronResult<Response> {
+            let name: String = self.url_fragment(request, "name")?;
+            match storage::index_details(&*self.blockchain.snapshot(), &name) {
+                Some(info) => self.ok_response(&serde_json::to_value(info).unwrap()),
+                None => self.not_found_response(&serde_json::to_value("Unknown index").unwrap()),
+            }
+        };
+
+        router.get("/v1/storage/indexes", indexes, "storage_indexes");
+        router.get("/v1/storage/indexes/:name", index, "storage_index");
+    }
+
+    fn handle_storage_stats(self, router: &mut Router) {
+        let storage_stats = move |_: &mut Request| -> IronResult<Response> {
+            match self.blockchain.storage_stats() {
+                Some(stats) => self.ok_response(&serde_json::to_value(stats).unwrap()),
+                None => {
+                    let message = "Storage statistics are not collected";
+                    self.not_found_response(&serde_json::to_value(message).unwrap())
+                }
+            }
+        };
+
+        router.get("/v1/storage/stats", storage_stats, "storage_stats");
+    }
+
     fn handle_shutdown(self, router: &mut Router) {
         let shutdown = move |_: &mut Request| -> IronResult<Response> {
             self.node_channel
@@ -230,6 +264,8 @@ impl Api for SystemApi {
         self.clone().handle_network(router);
         self.clone().handle_is_consensus_enabled(router);
         self.clone().handle_set_consensus_enabled(router);
+        self.clone().handle_indexes(router);
+        self.clone().handle_storage_stats(router);
         self.clone().handle_shutdown(router);
     }
 }
@@ -16,18 +16,24 @@ use router::Router;
 use serde_json::Value as JsonValue;
 use iron::prelude::*;
 
+use std::collections::BTreeMap;
 use std::ops::Range;
 use std::cmp;
+use std::time::Duration;
 
-use api::{Api, ApiError};
-use blockchain::{Block, Blockchain, TxLocation, Schema, TransactionErrorType, TransactionResult};
+use api::{Api, ApiError, HexField};
+use blockchain::{Block, Blockchain, EventRecord, TxLocation, Schema, Simulation,
+                 SimulationError, SimulationOptions, TransactionErrorType, TransactionResult};
 use crypto::Hash;
+use encoding::serialize::encode_hex;
 use helpers::Height;
 use node::state::TxPool;
-use messages::Precommit;
-use storage::ListProof;
+use messages::{Precommit, RawMessage, HEADER_LENGTH};
+use storage::{self, ListProof};
 
 const MAX_BLOCKS_PER_REQUEST: u64 = 1000;
+const MAX_SIMULATION_OPERATIONS: u64 = 100_000;
+const MAX_SIMULATION_DURATION_MS: u64 = 1000;
 
 /// Block information.
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
@@ -51,10 +57,33 @@ pub struct TxInfo {
     pub location_proof: ListProof<Hash>,
     /// Status of the transaction execution.
     pub status: TxStatus,
+    /// Events emitted by the transaction.
+    pub events: Vec<EventInfo>,
+}
+
+/// Event emitted by a transaction. `JSON` representation of `EventRecord`.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct EventInfo {
+    /// Identifier of the service, which the event belongs to.
+    pub service_id: u16,
+    /// Name of the event.
+    pub name: String,
+    /// Contents of the event.
+    pub payload: JsonValue,
+}
+
+impl<'a> From<&'a EventRecord> for EventInfo {
+    fn from(record: &'a EventRecord) -> Self {
+        EventInfo {
+            service_id: record.service_id(),
+            name: record.name().to_owned(),
+            payload: record.payload_json(),
+        }
+    }
 }
 
 /// Transaction execution status. Simplified version of `TransactionResult`.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "kebab-case")]
 pub enum TxStatus {
     /// Successful transaction execution.
@@ -70,9 +99,75 @@ pub enum TxStatus {
         code: u8,
         /// Error description.
         description: String,
+        /// Identifier of the service, which the error code belongs to.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        service_id: Option<u16>,
+        /// Machine-readable error details.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        payload: Option<JsonValue>,
     },
 }
 
+impl<'a> From<&'a TransactionResult> for TxStatus {
+    fn from(result: &'a TransactionResult) -> Self {
+        match *result {
+            Ok(()) => TxStatus::Success,
+            Err(ref e) => {
+                let description = e.description().unwrap_or_default().to_owned();
+                match e.error_type() {
+                    TransactionErrorType::Panic => TxStatus::Panic { description },
+                    TransactionErrorType::Code(code) => TxStatus::Error {
+                        code,
+                        description,
+                        service_id: e.service_id(),
+                        payload: e.payload(),
+                    },
+                }
+            }
+        }
+    }
+}
+
+/// Outcome of the simulated transaction execution. `JSON` representation of `Simulation`.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct SimulationInfo {
+    /// Status of the transaction execution.
+    pub status: TxStatus,
+    /// Hex-encoded keys changed by the transaction grouped by the index names.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub touched_keys: Option<BTreeMap<String, Vec<String>>>,
+}
+
+impl<'a> From<&'a Simulation> for SimulationInfo {
+    fn from(simulation: &'a Simulation) -> Self {
+        let touched_keys = simulation.touched_keys.as_ref().map(|touched_keys| {
+            touched_keys
+                .iter()
+                .map(|(name, keys)| {
+                    (name.clone(), keys.iter().map(encode_hex).collect())
+                })
+                .collect()
+        });
+        SimulationInfo {
+            status: TxStatus::from(&simulation.result),
+            touched_keys,
+        }
+    }
+}
+
+/// Request of the transaction simulation.
+#[derive(Debug, Clone, Deserialize)]
+struct SimulationRequest {
+    /// Hex-encoded signed transaction.
+    tx_body: HexField<Vec<u8>>,
+    /// Maximal number of the storage operations the transaction is allowed to perform.
+    #[serde(default)]
+    max_operations: Option<u64>,
+    /// Whether to return the keys changed by the transaction.
+    #[serde(default)]
+    touched_keys: bool,
+}
+
 /// Information on blocks coupled with the corresponding range in the blockchain.
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct BlocksRange {
@@ -145,6 +240,46 @@ impl ExplorerApi {
         }
     }
 
+    fn simulate_transaction(&self, request: SimulationRequest) -> Result<SimulationInfo, ApiError> {
+        let max_operations = request.max_operations.unwrap_or(MAX_SIMULATION_OPERATIONS);
+        if max_operations > MAX_SIMULATION_OPERATIONS {
+            return Err(ApiError::BadRequest(format!(
+                "Max storage operations per simulation exceeded ({})",
+                MAX_SIMULATION_OPERATIONS
+            )));
+        }
+
+        let HexField(tx_body) = request.tx_body;
+        if tx_body.len() < HEADER_LENGTH {
+            return Err(ApiError::BadRequest("Transaction is too short".into()));
+        }
+        let tx = self.blockchain
+            .tx_from_raw(RawMessage::from_vec(tx_body))
+            .ok_or_else(|| ApiError::BadRequest("Unable to parse transaction".into()))?;
+        if !tx.verify() {
+            return Err(ApiError::BadRequest("Transaction verification failed".into()));
+        }
+        let next_height = Schema::new(self.blockchain.snapshot()).height().next();
+        if tx.expiration().map_or(false, |e| e.is_expired(next_height)) {
+            return Err(ApiError::BadRequest("Transaction is expired".into()));
+        }
+
+        let options = SimulationOptions {
+            max_operations: Some(max_operations),
+            max_duration: Some(Duration::from_millis(MAX_SIMULATION_DURATION_MS)),
+            touched_keys: request.touched_keys,
+        };
+        let simulation = self.blockchain
+            .simulate_transaction(&*tx, &options)
+            .map_err(|e| match e {
+                SimulationError::Storage(message) => {
+                    ApiError::Storage(storage::Error::new(message))
+                }
+                e => ApiError::BadRequest(e.to_string()),
+            })?;
+        Ok(SimulationInfo::from(&simulation))
+    }
+
     fn set_blocks_response(self, router: &mut Router) {
         let blocks = move |req: &mut Request| -> IronResult<Response> {
             let count: u64 = self.required_param(req, "count")?;
@@ -181,6 +316,16 @@ impl ExplorerApi {
 
         router.get("/v1/transactions/:hash", transaction, "hash");
     }
+
+    fn set_simulate_transaction_response(self, router: &mut Router) {
+        let simulate = move |req: &mut Request| -> IronResult<Response> {
+            let request: SimulationRequest = self.parse_body(req)?;
+            let info = self.simulate_transaction(request)?;
+            self.ok_response(&::serde_json::to_value(info).unwrap())
+        };
+
+        router.post("/v1/transactions/simulate", simulate, "simulate_transaction");
+    }
 }
 
 impl Api for ExplorerApi {
@@ -188,6 +333,7 @@ impl Api for ExplorerApi {
         self.clone().set_blocks_response(router);
         self.clone().set_block_response(router);
         self.clone().set_transaction_info_response(router);
+        self.clone().set_simulate_transaction_response(router);
     }
 }
 
@@ -233,22 +379,20 @@ impl<'a> BlockchainExplorer<'a> {
         );
 
         // Unwrap is OK here, because we already know that transaction is committed.
-        let status = match schema.transaction_results().get(tx_hash).unwrap() {
-            Ok(()) => TxStatus::Success,
-            Err(e) => {
-                let description = e.description().unwrap_or_default().to_owned();
-                match e.error_type() {
-                    TransactionErrorType::Panic => TxStatus::Panic { description },
-                    TransactionErrorType::Code(code) => TxStatus::Error { code, description },
-                }
-            }
-        };
+        let status = TxStatus::from(&schema.transaction_results().get(tx_hash).unwrap());
+
+        let events = schema
+            .tx_events(tx_hash)
+            .iter()
+            .map(|record| EventInfo::from(&record))
+            .collect();
 
         Ok(Some(TxInfo {
             content,
             location,
             location_proof,
             status,
+            events,
         }))
     }
 
@@ -0,0 +1,140 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Events emitted by the transactions during the execution.
+
+use std::cell::RefCell;
+use std::mem;
+
+use serde::Serialize;
+use serde_json::{self, Value as JsonValue};
+
+/// Typed event, which can be emitted by a transaction with the
+/// [`Schema::emit_event`](struct.Schema.html#method.emit_event) method.
+///
+/// Events of a transaction are stored only if the transaction is executed successfully.
+/// An event is attributed to the service whose transaction has emitted it.
+/// The event contents are serialized to JSON with the keys of the objects sorted, so the stored
+/// record does not depend on the iteration order of the maps within the event.
+pub trait Event: Serialize {
+    /// Name of the event, which is unique within the service.
+    fn event_name(&self) -> &str;
+}
+
+encoding_struct! (
+    /// Event emitted by a transaction as it is stored in the blockchain.
+    struct EventRecord {
+        /// Identifier of the service, which the event belongs to.
+        service_id: u16,
+        /// Name of the event.
+        name: &str,
+        /// Contents of the event serialized to JSON with the sorted object keys.
+        payload: &str,
+    }
+);
+
+impl EventRecord {
+    /// Creates a record of the given event emitted by the service with the given identifier.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the event cannot be serialized to `JSON`.
+    pub fn from_event<E: Event>(service_id: u16, event: &E) -> Self {
+        // The conversion to `JsonValue` sorts the object keys, since the record is a part
+        // of the blockchain state and must be the same on all the nodes.
+        let payload = serde_json::to_value(event).expect("Unable to serialize event");
+        EventRecord::new(service_id, event.event_name(), &payload.to_string())
+    }
+
+    /// Returns the contents of the event as JSON value.
+    pub fn payload_json(&self) -> JsonValue {
+        serde_json::from_str(self.payload()).expect("Invalid event payload")
+    }
+}
+
+// Events emitted by the transaction, which is executed on the current thread.
+struct EventContext {
+    service_id: u16,
+    events: Vec<EventRecord>,
+}
+
+thread_local! {
+    static CONTEXT: RefCell<Option<EventContext>> = RefCell::new(None);
+}
+
+// Restores the previous context when the execution is finished, even if it panics.
+struct ContextGuard {
+    previous: Option<EventContext>,
+    active: bool,
+}
+
+impl ContextGuard {
+    fn enter(service_id: u16) -> Self {
+        let context = EventContext {
+            service_id,
+            events: Vec::new(),
+        };
+        let previous =
+            CONTEXT.with(|current| mem::replace(&mut *current.borrow_mut(), Some(context)));
+        ContextGuard {
+            previous,
+            active: true,
+        }
+    }
+
+    // Restores the previous context and returns the context of the finished execution.
+    fn exit(&mut self) -> Option<EventContext> {
+        if !self.active {
+            return None;
+        }
+        self.active = false;
+        let previous = self.previous.take();
+        CONTEXT.with(|current| mem::replace(&mut *current.borrow_mut(), previous))
+    }
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        self.exit();
+    }
+}
+
+/// Executes the transaction of the service with the given identifier and returns the events
+/// emitted by it with [`emit`] together with the result of the execution.
+///
+/// [`emit`]: fn.emit.html
+pub(crate) fn collect_events<F, R>(service_id: u16, execute: F) -> (R, Vec<EventRecord>)
+where
+    F: FnOnce() -> R,
+{
+    let mut guard = ContextGuard::enter(service_id);
+    let result = execute();
+    let events = guard.exit().map_or_else(Vec::new, |context| context.events);
+    (result, events)
+}
+
+/// Records the event of the transaction executed with [`collect_events`] on the current thread.
+/// Returns `false` if no transaction is executed, in which case the event is discarded.
+///
+/// [`collect_events`]: fn.collect_events.html
+pub(crate) fn emit<E: Event>(event: &E) -> bool {
+    CONTEXT.with(|current| match *current.borrow_mut() {
+        Some(ref mut context) => {
+            let record = EventRecord::from_event(context.service_id, event);
+            context.events.push(record);
+            true
+        }
+        None => false,
+    })
+}
@@ -0,0 +1,129 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Migrations of the service data between the versions of the service schema.
+//!
+//! Each service declares the version of the data schema its code works with
+//! ([`Service::schema_version`]) and the steps migrating the data from the previous
+//! versions ([`Service::migrations`]). The schema versions of the service data are stored
+//! in the core schema ([`Schema::service_schema_versions`]). When the blockchain
+//! is [initialized][initialize] with an existing storage, the missing steps are applied to
+//! the data of every service in the ascending order of versions. If a step is missing,
+//! the initialization fails and the data is left intact.
+//!
+//! Migrations change the state of the services, so the entries of the
+//! `state_hash_aggregator` and the `state_hash` of the latest block no longer correspond
+//! to the migrated data. The new state is committed with the next block. Until then,
+//! the state hash before and after each migration can be found in the
+//! [`Schema::migrations`] log.
+//!
+//! [`Service::schema_version`]: ../trait.Service.html#method.schema_version
+//! [`Service::migrations`]: ../trait.Service.html#method.migrations
+//! [`Schema::service_schema_versions`]: ../struct.Schema.html#method.service_schema_versions
+//! [`Schema::migrations`]: ../struct.Schema.html#method.migrations
+//! [initialize]: ../struct.Blockchain.html#method.initialize
+
+use std::fmt;
+
+use crypto::Hash;
+use helpers::Height;
+use storage::Fork;
+
+/// A step migrating the data of a service from the previous schema version
+/// to the given one.
+///
+/// # Examples
+///
+/// ```
+/// use exonum::blockchain::migration::Migration;
+/// use exonum::crypto::PublicKey;
+/// use exonum::storage::{Fork, MapIndex};
+///
+/// // Version 1 stores balances as `u64` instead of `u32`.
+/// let migration = Migration::new(1, "Widen balances", |fork: &mut Fork| {
+///     let balances: Vec<_> = {
+///         let index: MapIndex<_, PublicKey, u32> = MapIndex::new("wallets.balances", &*fork);
+///         index.iter().collect()
+///     };
+///     fork.drop_index("wallets.balances");
+///     let mut index = MapIndex::new("wallets.balances", fork);
+///     for (owner, balance) in balances {
+///         index.put(&owner, u64::from(balance));
+///     }
+/// });
+/// assert_eq!(migration.version(), 1);
+/// ```
+pub struct Migration {
+    version: u32,
+    description: String,
+    migrate: Box<Fn(&mut Fork) + Send + Sync>,
+}
+
+impl Migration {
+    /// Creates a step migrating the data from `version - 1` to `version`.
+    pub fn new<S, F>(version: u32, description: S, migrate: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn(&mut Fork) + Send + Sync + 'static,
+    {
+        Migration {
+            version,
+            description: description.into(),
+            migrate: Box::new(migrate),
+        }
+    }
+
+    /// Returns the schema version the data is migrated to.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns the human-readable description of the migration.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Applies the migration to the data in the fork.
+    pub fn apply(&self, fork: &mut Fork) {
+        (self.migrate)(fork)
+    }
+}
+
+impl fmt::Debug for Migration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Migration")
+            .field("version", &self.version)
+            .field("description", &self.description)
+            .finish()
+    }
+}
+
+encoding_struct! (
+    /// Record about the migration of the service data, which is stored in the core schema.
+    struct MigrationRecord {
+        /// Identifier of the migrated service.
+        service_id: u16,
+        /// Schema version of the data before the migration.
+        from_version: u32,
+        /// Schema version of the data after the migration.
+        to_version: u32,
+        /// Height of the latest committed block at the moment of the migration.
+        height: Height,
+        /// State hash of the blockchain before the migration.
+        state_hash_before: &Hash,
+        /// State hash of the blockchain after the migration, which is committed
+        /// with the next block.
+        state_hash_after: &Hash,
+    }
+);
@@ -39,7 +39,9 @@ use std::mem;
 use std::fmt;
 use std::iter;
 use std::panic;
+use std::thread;
 use std::net::SocketAddr;
+use std::io::{Read, Write};
 
 use vec_map::VecMap;
 use byteorder::{ByteOrder, LittleEndian};
@@ -47,28 +49,36 @@ use mount::Mount;
 
 use crypto::{self, CryptoHash, Hash, PublicKey, SecretKey};
 use messages::{CONSENSUS as CORE_SERVICE, Connect, Precommit, RawMessage};
-use storage::{Database, Error, Fork, Patch, Snapshot};
+use storage::{archive, history, Database, Error, Fork, MemoryDB, OperationsLimitExceeded, Patch,
+              ProofMapIndex, Snapshot, StorageStats, TimeLimitExceeded};
 use helpers::{Height, Round, ValidatorId};
 use node::ApiSender;
 
 pub use self::block::{Block, BlockProof, SCHEMA_MAJOR_VERSION};
 pub use self::schema::{Schema, TxLocation};
 pub use self::genesis::GenesisConfig;
+pub use self::event::{Event, EventRecord};
 pub use self::config::{ConsensusConfig, StoredConfiguration, TimeoutAdjusterConfig, ValidatorKeys};
+pub use self::migration::{Migration, MigrationRecord};
 pub use self::service::{ApiContext, Service, ServiceContext, SharedNodeState};
+pub use self::simulation::{Simulation, SimulationError, SimulationOptions};
 pub use self::transaction::{ExecutionError, ExecutionResult, Transaction, TransactionError,
-                            TransactionErrorType, TransactionResult, TransactionSet};
+                            TransactionErrorType, TransactionResult, TransactionSet,
+                            TxExpiration};
 
 mod block;
 mod schema;
 mod genesis;
 mod service;
+mod event;
+mod simulation;
 #[macro_use]
 mod transaction;
 #[cfg(test)]
 mod tests;
 
 pub mod config;
+pub mod migration;
 
 /// Exonum blockchain instance with the concrete services set and data storage.
 /// Only blockchains with the identical set of services and genesis block can be combined
@@ -147,12 +157,140 @@ impl Blockchain {
         })
     }
 
+    /// Executes the transaction against a fork of the latest snapshot and discards
+    /// the changes. The transaction is executed as if it were the only transaction
+    /// of the next block; the `before_commit` handlers of the services are not invoked.
+    ///
+    /// Returns an error if the transaction exceeds the execution budget given
+    /// in the options, or if the storage fails during the execution.
+    pub fn simulate_transaction(
+        &self,
+        tx: &Transaction,
+        options: &SimulationOptions,
+    ) -> Result<Simulation, SimulationError> {
+        let mut fork = self.fork();
+        fork.set_operations_limit(options.max_operations);
+        fork.set_time_limit(options.max_duration);
+        let run_result = run_transaction(tx, &mut fork);
+        fork.set_operations_limit(None);
+        fork.set_time_limit(None);
+
+        let result = match run_result {
+            Ok((result, _)) => result,
+            Err(err) => {
+                return Err(if err.is::<OperationsLimitExceeded>() {
+                    let max_operations = options.max_operations.unwrap_or_default();
+                    SimulationError::BudgetExceeded(max_operations)
+                } else if err.is::<TimeLimitExceeded>() {
+                    SimulationError::TimeLimitExceeded
+                } else {
+                    // Besides the budget, only the storage errors are not turned into
+                    // the transaction result.
+                    let description = err.downcast_ref::<Error>().map_or_else(
+                        || "Unknown error".to_owned(),
+                        |e| e.to_string(),
+                    );
+                    SimulationError::Storage(description)
+                });
+            }
+        };
+        if let Err(ref e) = result {
+            // Simulated transactions are not a part of the blockchain, so their failures
+            // are logged with a lower level.
+            debug!("Simulated {:?} transaction failed: {:?}", tx.hash(), e);
+        }
+        let touched_keys = if options.touched_keys {
+            let keys = fork.patch()
+                .iter()
+                .filter(|&(_, changes)| changes.iter().next().is_some())
+                .map(|(name, changes)| {
+                    (name.clone(), changes.iter().map(|(key, _)| key.clone()).collect())
+                })
+                .collect();
+            Some(keys)
+        } else {
+            None
+        };
+
+        Ok(Simulation {
+            result,
+            touched_keys,
+        })
+    }
+
+    /// Creates a readonly snapshot of the storage state as of the specified height.
+    ///
+    /// Returns `None` if the height is greater than the current height, or the storage
+    /// history is disabled, or the height is outside of the history retention window.
+    /// See [`history`](../storage/history/index.html) for details.
+    pub fn snapshot_at(&self, height: Height) -> Option<Box<Snapshot>> {
+        let blocks_count = Schema::new(&self.snapshot()).block_hashes_by_height().len();
+        if height.0 >= blocks_count {
+            return None;
+        }
+        history::snapshot_at(&*self.db, height.0)
+    }
+
+    /// Enables the storage history with the given retention window in blocks,
+    /// or disables it if `retention` is `None`.
+    /// See [`history`](../storage/history/index.html) for details.
+    pub fn set_history_retention(&mut self, retention: Option<u64>) -> Result<(), Error> {
+        history::set_retention(&*self.db, retention)
+    }
+
+    /// Exports the current storage state into an archive written to `writer`
+    /// and returns the writer back.
+    /// See [`archive`](../storage/archive/index.html) for details.
+    pub fn export_archive<W: Write>(&self, writer: W) -> Result<W, Error> {
+        archive::export(&*self.snapshot(), writer)
+    }
+
+    /// Imports the storage state from an archive into the empty blockchain storage.
+    ///
+    /// Before the imported state is committed, the entries of the `state_hash_aggregator`
+    /// are recomputed with the core schema and the services of this blockchain, and
+    /// the resulting Merkle root is compared with the `state_hash` of the latest block.
+    /// Thus, the blockchain should be created with the same set of services as the one
+    /// the archive was exported from.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the storage is not empty, the archive is malformed or
+    /// the imported state does not match the latest block. No changes are applied
+    /// to the storage in this case.
+    pub fn import_archive<R: Read>(&mut self, reader: R) -> Result<(), Error> {
+        archive::check_empty(&*self.snapshot())?;
+        let mut fork = self.fork();
+        fork.merge(archive::decode(reader)?);
+        self.verify_state_hash(&fork)?;
+        self.db.merge(fork.into_patch())
+    }
+
     /// Commits changes from the patch to the blockchain storage.
     /// See [`Fork`](../storage/struct.Fork.html) for details.
+    ///
+    /// If the storage history is enabled, the previous values of the changed keys are
+    /// recorded as a part of the next block height.
     pub fn merge(&mut self, patch: Patch) -> Result<(), Error> {
+        let patch = if history::retention(&*self.db.snapshot()).is_some() {
+            let mut fork = self.db.fork();
+            let version = Schema::new(&fork).block_hashes_by_height().len();
+            history::record(&mut fork, &patch, version);
+            fork.merge(patch);
+            fork.into_patch()
+        } else {
+            patch
+        };
         self.db.merge(patch)
     }
 
+    /// Returns the statistics of the storage operations per index accumulated since
+    /// the node start, or `None` if the storage does not collect them.
+    /// See [`MeteredDB`](../storage/struct.MeteredDB.html) for details.
+    pub fn storage_stats(&self) -> Option<StorageStats> {
+        self.db.storage_stats()
+    }
+
     /// Returns the hash of latest committed block.
     ///
     /// # Panics
@@ -176,16 +314,100 @@ impl Blockchain {
 
     /// Creates and commits the genesis block for the given genesis configuration
     /// if the blockchain was not initialized.
+    ///
+    /// If the blockchain was initialized, migrates the data of the services to their
+    /// current schema versions. See the [`migration`](migration/index.html) module for details.
     pub fn initialize(&mut self, cfg: GenesisConfig) -> Result<(), Error> {
         let has_genesis_block = !Schema::new(&self.snapshot())
             .block_hashes_by_height()
             .is_empty();
-        if !has_genesis_block {
+        if has_genesis_block {
+            self.migrate()?;
+        } else {
             self.create_genesis_block(cfg)?;
         }
         Ok(())
     }
 
+    /// Applies the missing migrations of the service data and commits the result.
+    ///
+    /// The migrations of all the services are checked before applying any of them,
+    /// so the data is left intact if some migration is missing.
+    fn migrate(&mut self) -> Result<(), Error> {
+        let mut pending = Vec::new();
+        {
+            let snapshot = self.snapshot();
+            let schema = Schema::new(&snapshot);
+            let versions = schema.service_schema_versions();
+            for service in self.service_map.values() {
+                let name = service.service_name();
+                let current = versions.get(&service.service_id()).unwrap_or(0);
+                let target = service.schema_version();
+                if current > target {
+                    return Err(Error::new(format!(
+                        "Schema version {} of service '{}' data is newer than the supported \
+                         version {}",
+                        current,
+                        name,
+                        target
+                    )));
+                }
+
+                let mut migrations = service.migrations();
+                migrations.retain(|migration| {
+                    migration.version() > current && migration.version() <= target
+                });
+                migrations.sort_by_key(Migration::version);
+                for (index, version) in (current + 1..target + 1).enumerate() {
+                    if migrations.get(index).map(Migration::version) != Some(version) {
+                        return Err(Error::new(format!(
+                            "Missing migration of service '{}' to schema version {}",
+                            name,
+                            version
+                        )));
+                    }
+                }
+                if !migrations.is_empty() {
+                    pending.push((service.service_id(), current, migrations));
+                }
+            }
+        }
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut fork = self.fork();
+        let height = Schema::new(&fork).height();
+        for (service_id, mut from_version, migrations) in pending {
+            for migration in migrations {
+                let state_hash_before = self.aggregate_state_hash(&fork);
+                migration.apply(&mut fork);
+                let state_hash_after = self.aggregate_state_hash(&fork);
+                info!(
+                    "Migrated data of service {} to schema version {}: {}",
+                    service_id,
+                    migration.version(),
+                    migration.description()
+                );
+
+                let mut schema = Schema::new(&mut fork);
+                schema
+                    .service_schema_versions_mut()
+                    .put(&service_id, migration.version());
+                schema.migrations_mut().push(MigrationRecord::new(
+                    service_id,
+                    from_version,
+                    migration.version(),
+                    height,
+                    &state_hash_before,
+                    &state_hash_after,
+                ));
+                from_version = migration.version();
+            }
+        }
+        self.merge(fork.into_patch())
+    }
+
     /// Creates and commits the genesis block for the given genesis configuration.
     fn create_genesis_block(&mut self, cfg: GenesisConfig) -> Result<(), Error> {
         let mut config_propose = StoredConfiguration {
@@ -219,6 +441,10 @@ impl Blockchain {
                     return Ok(());
                 }
                 schema.commit_configuration(config_propose);
+                let mut versions = schema.service_schema_versions_mut();
+                for service in self.service_map.values() {
+                    versions.put(&service.service_id(), service.schema_version());
+                }
             };
             self.merge(fork.into_patch())?;
             self.create_patch(ValidatorId::zero(), Height::zero(), &[], &BTreeMap::new())
@@ -248,9 +474,87 @@ impl Blockchain {
         crypto::hash(&vec)
     }
 
+    // Computes the `state_hash_aggregator` entries for the core tables and the services.
+    fn state_hashes(&self, view: &Snapshot) -> Vec<(Hash, Hash)> {
+        let vec_core_state = Schema::new(view).core_state_hash();
+        let mut state_hashes = Vec::new();
+
+        for (idx, core_table_hash) in vec_core_state.into_iter().enumerate() {
+            let key = Blockchain::service_table_unique_key(CORE_SERVICE, idx);
+            state_hashes.push((key, core_table_hash));
+        }
+
+        for service in self.service_map.values() {
+            let service_id = service.service_id();
+            let vec_service_state = service.state_hash(view);
+            for (idx, service_table_hash) in vec_service_state.into_iter().enumerate() {
+                let key = Blockchain::service_table_unique_key(service_id, idx);
+                state_hashes.push((key, service_table_hash));
+            }
+        }
+
+        state_hashes
+    }
+
+    // Computes the `state_hash` of a block that would be committed on top of the given view.
+    fn aggregate_state_hash(&self, view: &Snapshot) -> Hash {
+        let mut fork = MemoryDB::new().fork();
+        let mut sum_table = ProofMapIndex::new("state_hash_aggregator", &mut fork);
+        for (key, hash) in self.state_hashes(view) {
+            sum_table.put(&key, hash);
+        }
+        sum_table.merkle_root()
+    }
+
+    // Checks that the state of the given view corresponds to the `state_hash` of the latest block
+    // or, if the service data was migrated after the latest block, to the state hash
+    // after the migration.
+    fn verify_state_hash(&self, view: &Snapshot) -> Result<(), Error> {
+        let schema = Schema::new(view);
+        if schema.block_hashes_by_height().is_empty() {
+            return Ok(());
+        }
+
+        let last_block = schema.last_block();
+        let expected_hash = match schema.migrations().last() {
+            Some(ref record) if record.height() == last_block.height() => {
+                *record.state_hash_after()
+            }
+            _ => {
+                let mut state_hashes = self.state_hashes(view);
+                state_hashes.sort();
+                let stored_hashes = schema.state_hash_aggregator().iter().collect::<Vec<_>>();
+                if state_hashes != stored_hashes {
+                    return Err(Error::new(
+                        "Recomputed state hashes do not match the `state_hash_aggregator` entries",
+                    ));
+                }
+                *last_block.state_hash()
+            }
+        };
+
+        let state_hash = self.aggregate_state_hash(view);
+        if state_hash != expected_hash {
+            return Err(Error::new(format!(
+                "Recomputed state hash {:?} does not match the state hash of the block \
+                 at height {}: {:?}",
+                state_hash,
+                last_block.height(),
+                expected_hash
+            )));
+        }
+        Ok(())
+    }
+
     /// Executes the given transactions from pool.
     /// Then it collects the resulting changes from the current storage state and returns them
     /// with the hash of resulting block.
+    ///
+    /// The result of each transaction is saved right after its execution, so it is visible
+    /// to the subsequent transactions of the block. The hashes of the `state_hash_aggregator`
+    /// are recomputed once per block (see [`ProofMapIndex::deferred`]).
+    ///
+    /// [`ProofMapIndex::deferred`]: ../storage/struct.ProofMapIndex.html#method.deferred
     pub fn create_patch(
         &self,
         proposer_id: ValidatorId,
@@ -273,35 +577,19 @@ impl Blockchain {
                 execute_transaction(tx.as_ref(), height, index, &mut fork);
             }
 
+            for service in self.service_map.values() {
+                before_commit(service.as_ref(), &mut fork);
+            }
+
             // Get tx & state hash
             let (tx_hash, state_hash) = {
-                let state_hashes = {
-                    let schema = Schema::new(&fork);
-
-                    let vec_core_state = schema.core_state_hash();
-                    let mut state_hashes = Vec::new();
-
-                    for (idx, core_table_hash) in vec_core_state.into_iter().enumerate() {
-                        let key = Blockchain::service_table_unique_key(CORE_SERVICE, idx);
-                        state_hashes.push((key, core_table_hash));
-                    }
-
-                    for service in self.service_map.values() {
-                        let service_id = service.service_id();
-                        let vec_service_state = service.state_hash(&fork);
-                        for (idx, service_table_hash) in vec_service_state.into_iter().enumerate() {
-                            let key = Blockchain::service_table_unique_key(service_id, idx);
-                            state_hashes.push((key, service_table_hash));
-                        }
-                    }
-
-                    state_hashes
-                };
+                let state_hashes = self.state_hashes(&fork);
 
                 let mut schema = Schema::new(&mut fork);
 
                 let state_hash = {
                     let mut sum_table = schema.state_hash_aggregator_mut();
+                    let mut sum_table = sum_table.deferred();
                     for (key, hash) in state_hashes {
                         sum_table.put(&key, hash)
                     }
@@ -500,37 +788,88 @@ impl Clone for Blockchain {
     }
 }
 
-fn execute_transaction(tx: &Transaction, height: Height, index: usize, fork: &mut Fork) {
-    fork.checkpoint();
+fn before_commit(service: &Service, fork: &mut Fork) {
+    if let Err(err) = fork.catch_unwind(|fork| service.before_commit(fork)) {
+        if err.is::<Error>() {
+            // Continue panic unwind if the reason is StorageError.
+            panic::resume_unwind(err);
+        }
+        error!(
+            "{} service before_commit panicked: {:?}",
+            service.service_name(),
+            err
+        );
+    }
+}
 
-    let catch_result = panic::catch_unwind(panic::AssertUnwindSafe(|| tx.execute(fork)));
+// Executes the transaction and rolls back its changes if it fails. Returns the result
+// of the transaction together with its events, which are empty if the transaction fails.
+// The panics, which must not be turned into the transaction result, are returned as errors.
+// The failures are not logged here, since the callers log them with different levels.
+fn run_transaction(
+    tx: &Transaction,
+    fork: &mut Fork,
+) -> thread::Result<(TransactionResult, Vec<EventRecord>)> {
+    let savepoint = fork.checkpoint();
+
+    let (catch_result, events) = event::collect_events(tx.raw().service_id(), || {
+        fork.catch_unwind(|fork| tx.execute(fork))
+    });
+    // The events emitted by a failed transaction are discarded together with its changes.
+    let events = match catch_result {
+        Ok(Ok(())) => {
+            fork.release(savepoint);
+            events
+        }
+        _ => {
+            fork.rollback_to(savepoint);
+            Vec::new()
+        }
+    };
 
-    let tx_hash = tx.hash();
     let tx_result = match catch_result {
-        Ok(execution_result) => {
-            match execution_result {
-                Ok(()) => fork.commit(),
-                Err(ref e) => {
-                    // Unlike panic, transaction failure isn't that rare, so logging the
-                    // whole transaction body is an overkill: it can be relatively big.
-                    info!("{:?} transaction execution failed: {:?}", tx_hash, e);
-                    fork.rollback();
-                }
-            }
-            execution_result.map_err(TransactionError::from)
-        }
+        Ok(execution_result) => execution_result.map_err(|e| {
+            TransactionError::from_execution_error(e, tx.raw().service_id())
+        }),
         Err(err) => {
-            if err.is::<Error>() {
-                // Continue panic unwind if the reason is StorageError.
-                panic::resume_unwind(err);
+            if err.is::<Error>() || err.is::<OperationsLimitExceeded>() ||
+                err.is::<TimeLimitExceeded>()
+            {
+                return Err(err);
             }
-            fork.rollback();
-            error!("{:?} transaction execution panicked: {:?}", tx, err);
             Err(TransactionError::from_panic(&err))
         }
     };
+    Ok((tx_result, events))
+}
 
+fn execute_transaction(tx: &Transaction, height: Height, index: usize, fork: &mut Fork) {
+    // Continue panic unwind if the reason is StorageError.
+    let (tx_result, events) =
+        run_transaction(tx, fork).unwrap_or_else(|err| panic::resume_unwind(err));
+
+    let tx_hash = tx.hash();
+    if let Err(ref e) = tx_result {
+        match e.error_type() {
+            TransactionErrorType::Panic => {
+                error!("{:?} transaction execution panicked: {:?}", tx, e);
+            }
+            // Unlike panic, transaction failure isn't that rare, so logging the
+            // whole transaction body is an overkill: it can be relatively big.
+            TransactionErrorType::Code(_) => {
+                info!("{:?} transaction execution failed: {:?}", tx_hash, e);
+            }
+        }
+    }
     let mut schema = Schema::new(fork);
+    if !events.is_empty() {
+        let events_hash = {
+            let mut tx_events = schema.tx_events_mut(&tx_hash);
+            tx_events.extend(events);
+            tx_events.merkle_root()
+        };
+        schema.tx_events_hashes_mut().put(&tx_hash, events_hash);
+    }
     schema.transactions_mut().put(&tx_hash, tx.raw().clone());
     schema.transaction_results_mut().put(&tx_hash, tx_result);
     schema.block_txs_mut(height).push(tx_hash);
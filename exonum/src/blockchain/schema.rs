@@ -17,7 +17,9 @@ use messages::{Precommit, RawMessage, Connect};
 use storage::{Entry, Fork, ListIndex, MapIndex, MapProof, ProofListIndex, ProofMapIndex, Snapshot};
 use helpers::{Height, Round};
 use super::{Block, BlockProof, Blockchain, TransactionResult};
+use super::event::{self, Event, EventRecord};
 use super::config::StoredConfiguration;
+use super::migration::MigrationRecord;
 
 /// Defines `&str` constants with given name and value.
 macro_rules! define_names {
@@ -34,6 +36,8 @@ define_names!(
     TRANSACTIONS => "transactions";
     TRANSACTION_RESULTS => "transaction_results";
     TX_LOCATION_BY_TX_HASH => "tx_location_by_tx_hash";
+    TX_EVENTS => "tx_events";
+    TX_EVENTS_HASHES => "tx_events_hashes";
     BLOCKS => "blocks";
     BLOCK_HASHES_BY_HEIGHT => "block_hashes_by_height";
     BLOCK_TXS => "block_txs";
@@ -44,6 +48,8 @@ define_names!(
     PEERS_CACHE => "peers_cache";
     CONSENSUS_MESSAGES_CACHE => "consensus_messages_cache";
     CONSENSUS_ROUND => "consensus_round";
+    SERVICE_SCHEMA_VERSIONS => "service_schema_versions";
+    MIGRATIONS => "migrations";
 );
 
 encoding_struct! (
@@ -91,6 +97,20 @@ where
         ProofMapIndex::new(TRANSACTION_RESULTS, &self.view)
     }
 
+    /// Returns table that keeps a list of events emitted by the transaction with the given hash.
+    pub fn tx_events(&self, tx_hash: &Hash) -> ProofListIndex<&T, EventRecord> {
+        ProofListIndex::new_in_family(TX_EVENTS, tx_hash, &self.view)
+    }
+
+    /// Returns table that represents a map from transaction hash into the root hash of
+    /// the [`tx_events`][1] list. Only the transactions, which have emitted events,
+    /// are present in the table.
+    ///
+    /// [1]: struct.Schema.html#method.tx_events
+    pub fn tx_events_hashes(&self) -> ProofMapIndex<&T, Hash, Hash> {
+        ProofMapIndex::new(TX_EVENTS_HASHES, &self.view)
+    }
+
     /// Returns table that keeps the block height and tx position inside block for every
     /// transaction hash.
     pub fn tx_location_by_tx_hash(&self) -> MapIndex<&T, Hash, TxLocation> {
@@ -149,6 +169,21 @@ where
         ProofMapIndex::new(STATE_HASH_AGGREGATOR, &self.view)
     }
 
+    /// Returns a table that represents a map with a key-value pair of a
+    /// service identifier and the schema version of the service data.
+    ///
+    /// Services missing from the table have the schema version `0`.
+    pub fn service_schema_versions(&self) -> MapIndex<&T, u16, u32> {
+        MapIndex::new(SERVICE_SCHEMA_VERSIONS, &self.view)
+    }
+
+    /// Returns a table that keeps the log of the applied migrations of the service data.
+    ///
+    /// See the [`migration`](migration/index.html) module for details.
+    pub fn migrations(&self) -> ListIndex<&T, MigrationRecord> {
+        ListIndex::new(MIGRATIONS, &self.view)
+    }
+
     /// Returns peers that have to be recovered in case of process' restart
     /// after abnormal termination.
     pub(crate) fn peers_cache(&self) -> MapIndex<&T, PublicKey, Connect> {
@@ -282,7 +317,11 @@ where
 
     /// Returns the `state_hash` table for core tables.
     pub fn core_state_hash(&self) -> Vec<Hash> {
-        vec![self.configs().merkle_root(), self.transaction_results().merkle_root()]
+        vec![
+            self.configs().merkle_root(),
+            self.transaction_results().merkle_root(),
+            self.tx_events_hashes().merkle_root(),
+        ]
     }
 
     /// Constructs a proof of inclusion of root hash of a specific service
@@ -350,6 +389,44 @@ impl<'a> Schema<&'a mut Fork> {
         ProofMapIndex::new(TRANSACTION_RESULTS, self.view)
     }
 
+    /// Mutable reference to the [`tx_events`][1] index.
+    ///
+    /// [1]: struct.Schema.html#method.tx_events
+    pub(crate) fn tx_events_mut(
+        &mut self,
+        tx_hash: &Hash,
+    ) -> ProofListIndex<&mut Fork, EventRecord> {
+        ProofListIndex::new_in_family(TX_EVENTS, tx_hash, self.view)
+    }
+
+    /// Mutable reference to the [`tx_events_hashes`][1] index.
+    ///
+    /// [1]: struct.Schema.html#method.tx_events_hashes
+    pub(crate) fn tx_events_hashes_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, Hash> {
+        ProofMapIndex::new(TX_EVENTS_HASHES, self.view)
+    }
+
+    /// Emits an event of the currently executed transaction. The event is attributed
+    /// to the service of the transaction.
+    ///
+    /// The events are kept in memory and are stored in the [`tx_events`][1] list after
+    /// the transaction is executed successfully. If the transaction returns an error or panics,
+    /// its events are discarded together with the other changes. Note that the events are
+    /// not a part of the fork, so rolling back a part of the transaction to a checkpoint
+    /// does not discard the events emitted by that part. Events emitted outside of
+    /// the transaction execution, for example, in the `before_commit` handlers of the services,
+    /// are discarded as well.
+    ///
+    /// [1]: struct.Schema.html#method.tx_events
+    pub fn emit_event<E: Event>(&mut self, event: &E) {
+        if !event::emit(event) {
+            warn!(
+                "Event {:?} emitted outside of a transaction is discarded",
+                event.event_name()
+            );
+        }
+    }
+
     /// Mutable reference to the [`tx_location_by_tx_hash`][1] index.
     ///
     /// [1]: struct.Schema.html#method.tx_location_by_tx_hash
@@ -407,6 +484,20 @@ impl<'a> Schema<&'a mut Fork> {
         ProofMapIndex::new(STATE_HASH_AGGREGATOR, self.view)
     }
 
+    /// Mutable reference to the [`service_schema_versions`][1] index.
+    ///
+    /// [1]: struct.Schema.html#method.service_schema_versions
+    pub(crate) fn service_schema_versions_mut(&mut self) -> MapIndex<&mut Fork, u16, u32> {
+        MapIndex::new(SERVICE_SCHEMA_VERSIONS, self.view)
+    }
+
+    /// Mutable reference to the [`migrations`][1] index.
+    ///
+    /// [1]: struct.Schema.html#method.migrations
+    pub(crate) fn migrations_mut(&mut self) -> ListIndex<&mut Fork, MigrationRecord> {
+        ListIndex::new(MIGRATIONS, self.view)
+    }
+
     /// Mutable reference to the [`peers_cache`][1] index.
     ///
     /// [1]: struct.Schema.html#method.peers_cache
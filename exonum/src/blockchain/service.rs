@@ -31,6 +31,7 @@ use node::{ApiSender, Node, State, TransactionSend};
 use blockchain::{Blockchain, ConsensusConfig, Schema, StoredConfiguration, ValidatorKeys};
 use helpers::{Height, Milliseconds, ValidatorId};
 use super::transaction::Transaction;
+use super::migration::Migration;
 
 
 /// A trait that describes business logic of a concrete service.
@@ -173,6 +174,36 @@ pub trait Service: Send + Sync + 'static {
         Value::Null
     }
 
+    /// Returns the version of the data schema the service code works with.
+    ///
+    /// The version is recorded on genesis block creation. If the version stored in
+    /// the blockchain is lower, the data is migrated with the [`migrations`][1] of the
+    /// service when the node starts.
+    ///
+    /// [1]: #method.migrations
+    fn schema_version(&self) -> u32 {
+        0
+    }
+
+    /// Returns the steps migrating the service data between the schema versions.
+    /// The step with version `v` migrates the data from version `v - 1` to `v`.
+    ///
+    /// See the [`migration`](migration/index.html) module for details.
+    fn migrations(&self) -> Vec<Migration> {
+        Vec::new()
+    }
+
+    /// Performs the service work that is a part of each block, for example, updates
+    /// of the service data that depend only on the height. This handler is invoked
+    /// for each service in order of their identifiers after the transactions of the block,
+    /// including the genesis block, are executed and before the state hash is calculated,
+    /// so the changes are a part of the block.
+    ///
+    /// The handler is executed on every node, so its result must be deterministic.
+    /// If the handler panics, its changes are discarded and the block is created
+    /// without them.
+    fn before_commit(&self, fork: &mut Fork) {}
+
     /// Handles block commit. This handler is invoked for each service after commit of the block.
     /// For example, a service can create one or more transactions if a specific condition
     /// has occurred.
@@ -0,0 +1,63 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Dry-run execution of the transactions.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use super::TransactionResult;
+
+/// Options of the transaction simulation, see
+/// [`Blockchain::simulate_transaction`](../struct.Blockchain.html#method.simulate_transaction).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimulationOptions {
+    /// Maximal number of the storage operations the transaction is allowed to perform
+    /// (see [`Fork::set_operations_limit`] for the details). The number is unlimited
+    /// if `None` is given.
+    ///
+    /// [`Fork::set_operations_limit`]: ../../storage/struct.Fork.html#method.set_operations_limit
+    pub max_operations: Option<u64>,
+    /// Maximal time, during which the transaction is allowed to perform the storage operations
+    /// (see [`Fork::set_time_limit`] for the details). The time is unlimited if `None` is given.
+    ///
+    /// [`Fork::set_time_limit`]: ../../storage/struct.Fork.html#method.set_time_limit
+    pub max_duration: Option<Duration>,
+    /// Whether to collect the keys changed by the transaction.
+    pub touched_keys: bool,
+}
+
+/// Outcome of the simulated transaction execution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Simulation {
+    /// Result of the transaction execution.
+    pub result: TransactionResult,
+    /// Keys changed by the transaction grouped by the index names, if they were requested.
+    /// The changes of the failed transactions are discarded, so the map is empty for them.
+    pub touched_keys: Option<BTreeMap<String, Vec<Vec<u8>>>>,
+}
+
+/// The error returned if the transaction simulation is aborted.
+#[derive(Fail, Debug, Clone, PartialEq, Eq)]
+pub enum SimulationError {
+    /// The transaction has exceeded the given number of the storage operations.
+    #[fail(display = "Execution budget of {} storage operations is exceeded", _0)]
+    BudgetExceeded(u64),
+    /// The transaction has exceeded the given execution time.
+    #[fail(display = "Execution time limit is exceeded")]
+    TimeLimitExceeded,
+    /// The storage has failed during the execution.
+    #[fail(display = "Storage error: {}", _0)]
+    Storage(String),
+}
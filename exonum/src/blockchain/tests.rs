@@ -15,14 +15,23 @@
 #![allow(dead_code)]
 
 use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
 
 use rand::{thread_rng, Rng};
 use serde_json;
 
-use blockchain::{Blockchain, Schema, Transaction, ExecutionResult};
+use futures::sync::mpsc;
+use serde_json::Value;
+
+use blockchain::{Blockchain, Event, EventRecord, ExecutionError, GenesisConfig, Migration,
+                 Schema, Service, SimulationError, SimulationOptions, Transaction,
+                 TransactionErrorType, ExecutionResult, ValidatorKeys};
 use crypto::{gen_keypair, CryptoHash, Hash};
-use storage::{Database, Fork, Error, ListIndex};
-use messages::Message;
+use encoding::Error as MessageError;
+use storage::{Database, Fork, Error, ListIndex, MeteredDB, ProofListIndex, Snapshot};
+use messages::{Message, RawTransaction};
+use node::ApiSender;
 use helpers::{Height, ValidatorId};
 
 const IDX_NAME: &'static str = "idx_name";
@@ -98,6 +107,34 @@ fn test_correct_encoding_struct() {
     assert_eq!(struct_.second(), &[2u8]);
 }
 
+#[test]
+fn test_event_record_sorted_keys() {
+    use std::collections::HashMap;
+
+    #[derive(Serialize)]
+    struct MapEvent {
+        values: HashMap<String, u8>,
+        key: u8,
+    }
+
+    impl Event for MapEvent {
+        fn event_name(&self) -> &str {
+            "map"
+        }
+    }
+
+    let values = (0..16).map(|i| (format!("key{:02}", i), i)).collect();
+    let record = EventRecord::from_event(1, &MapEvent { values, key: 0 });
+    let expected_values = (0..16)
+        .map(|i| format!(r#""key{:02}":{}"#, i, i))
+        .collect::<Vec<_>>()
+        .join(",");
+    assert_eq!(
+        record.payload(),
+        format!(r#"{{"key":0,"values":{{{}}}}}"#, expected_values)
+    );
+}
+
 #[test]
 #[should_panic(expected = "OverlappingSegment")]
 fn test_overlap_segments() {
@@ -273,6 +310,296 @@ fn handling_tx_panic_storage_error(blockchain: &Blockchain) {
     );
 }
 
+fn snapshot_at_height(blockchain: &mut Blockchain) {
+    // No blocks are committed yet.
+    assert!(blockchain.snapshot_at(Height(0)).is_none());
+    blockchain.set_history_retention(Some(2)).unwrap();
+    assert!(blockchain.snapshot_at(Height(0)).is_none());
+
+    for value in 0..4u64 {
+        let mut fork = blockchain.fork();
+        Schema::new(&mut fork).block_hashes_by_height_mut().push(
+            value.hash(),
+        );
+        ListIndex::new(IDX_NAME, &mut fork).push(value);
+        blockchain.merge(fork.into_patch()).unwrap();
+    }
+
+    let values_at = |blockchain: &Blockchain, height: u64| {
+        blockchain.snapshot_at(Height(height)).map(|snapshot| {
+            let index: ListIndex<_, u64> = ListIndex::new(IDX_NAME, &*snapshot);
+            index.iter().collect::<Vec<_>>()
+        })
+    };
+
+    assert_eq!(values_at(blockchain, 0), None);
+    assert_eq!(values_at(blockchain, 1), Some(vec![0, 1]));
+    assert_eq!(values_at(blockchain, 2), Some(vec![0, 1, 2]));
+    assert_eq!(values_at(blockchain, 3), Some(vec![0, 1, 2, 3]));
+    assert_eq!(values_at(blockchain, 4), None);
+
+    blockchain.set_history_retention(None).unwrap();
+    assert_eq!(values_at(blockchain, 3), None);
+}
+
+fn storage_stats(db: Box<Database>) {
+    let service_keypair = gen_keypair();
+    let api_channel = mpsc::channel(1);
+    let mut blockchain = Blockchain::new(
+        MeteredDB::new(db),
+        Vec::new(),
+        service_keypair.0,
+        service_keypair.1,
+        ApiSender::new(api_channel.0),
+    );
+    assert_eq!(blockchain.storage_stats().unwrap().patches, 0);
+
+    for value in 0..2u64 {
+        let mut fork = blockchain.fork();
+        {
+            let mut index = ListIndex::new(IDX_NAME, &mut fork);
+            index.push(value);
+            index.get(0);
+        }
+        blockchain.merge(fork.into_patch()).unwrap();
+    }
+
+    // Statistics are shared between the clones of the blockchain.
+    let stats = blockchain.clone().storage_stats().unwrap();
+    assert_eq!(stats.patches, 2);
+    let last = *stats.last_patch.get(IDX_NAME).unwrap();
+    assert!(last.reads >= 1);
+    assert_eq!(last.writes, 2);
+    assert_eq!(last.deletes, 0);
+    assert!(last.bytes_written > 0);
+    let total = *stats.total.get(IDX_NAME).unwrap();
+    assert_eq!(total.writes, 4);
+    assert!(total.reads > last.reads);
+}
+
+fn tx_events(blockchain: &mut Blockchain) {
+    #[derive(Serialize)]
+    struct ValueEvent {
+        value: u64,
+    }
+
+    impl Event for ValueEvent {
+        fn event_name(&self) -> &str {
+            "value"
+        }
+    }
+
+    messages! {
+        const SERVICE_ID = 3;
+        struct Tx {
+            value: u64,
+        }
+    }
+
+    impl Transaction for Tx {
+        fn verify(&self) -> bool {
+            true
+        }
+
+        fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+            Schema::new(&mut *fork).emit_event(&ValueEvent { value: self.value() });
+            match self.value() {
+                0 => Err(ExecutionError::new(0)),
+                1 => panic!("Transaction panicked"),
+                value => {
+                    Schema::new(fork).emit_event(&ValueEvent { value: value + 1 });
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    let (_, sec_key) = gen_keypair();
+    let txs = vec![
+        Tx::new(2, &sec_key),
+        Tx::new(0, &sec_key),
+        Tx::new(1, &sec_key),
+        Tx::new(5, &sec_key),
+    ];
+    let pool = txs.iter()
+        .map(|tx| (tx.hash(), Box::new(tx.clone()) as Box<Transaction>))
+        .collect::<BTreeMap<_, _>>();
+    let tx_hashes = txs.iter().map(|tx| tx.hash()).collect::<Vec<_>>();
+
+    let (_, patch) = blockchain.create_patch(
+        ValidatorId::zero(),
+        Height::zero(),
+        &tx_hashes,
+        &pool,
+    );
+    blockchain.merge(patch).unwrap();
+
+    let snapshot = blockchain.snapshot();
+    let schema = Schema::new(&snapshot);
+    let events = |tx: &Tx| {
+        schema
+            .tx_events(&tx.hash())
+            .iter()
+            .map(|record| (record.service_id(), record.payload().to_owned()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        events(&txs[0]),
+        vec![
+            (3, r#"{"value":2}"#.to_owned()),
+            (3, r#"{"value":3}"#.to_owned()),
+        ]
+    );
+    // Events of the failed transactions are discarded.
+    assert!(events(&txs[1]).is_empty());
+    assert!(events(&txs[2]).is_empty());
+
+    let events_hashes = schema.tx_events_hashes();
+    assert_eq!(events_hashes.keys().count(), 2);
+    assert_eq!(
+        events_hashes.get(&txs[0].hash()),
+        Some(schema.tx_events(&txs[0].hash()).merkle_root())
+    );
+    assert_eq!(events_hashes.get(&txs[1].hash()), None);
+    let record = schema.tx_events(&txs[3].hash()).get(1).unwrap();
+    assert_eq!(record, EventRecord::new(3, "value", r#"{"value":6}"#));
+    assert_eq!(record.payload_json()["value"], 6);
+    // Events are a part of the core state.
+    assert_eq!(
+        schema.state_hash_aggregator().get(
+            &Blockchain::service_table_unique_key(0, 2),
+        ),
+        Some(events_hashes.merkle_root())
+    );
+
+    // Events emitted outside of transactions are discarded and do not change the storage.
+    let mut fork = blockchain.fork();
+    Schema::new(&mut fork).emit_event(&ValueEvent { value: 1 });
+    assert!(fork.patch().iter().next().is_none());
+}
+
+fn simulate_transaction(blockchain: &Blockchain) {
+    messages! {
+        const SERVICE_ID = 1;
+        struct Tx {
+            value: u64,
+        }
+    }
+
+    impl Transaction for Tx {
+        fn verify(&self) -> bool {
+            true
+        }
+
+        fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+            if self.value() == u64::max_value() {
+                panic!(Error::new("Storage failure"));
+            }
+            let mut index = ListIndex::new(IDX_NAME, fork);
+            for value in 0..self.value() {
+                index.push(value);
+            }
+            if self.value() == 0 {
+                Err(ExecutionError::new(1))?;
+            }
+            Ok(())
+        }
+    }
+
+    let (_, sec_key) = gen_keypair();
+    let options = SimulationOptions {
+        max_operations: None,
+        max_duration: None,
+        touched_keys: true,
+    };
+
+    let simulation = blockchain
+        .simulate_transaction(&Tx::new(2, &sec_key), &options)
+        .unwrap();
+    assert_eq!(simulation.result, Ok(()));
+    let touched_keys = simulation.touched_keys.unwrap();
+    assert_eq!(touched_keys[IDX_NAME].len(), 3);
+    // The changes are discarded.
+    let snapshot = blockchain.snapshot();
+    assert!(snapshot.iter(IDX_NAME, &[]).next().is_none());
+
+    let simulation = blockchain
+        .simulate_transaction(&Tx::new(0, &sec_key), &options)
+        .unwrap();
+    let error = simulation.result.unwrap_err();
+    assert_eq!(error.error_type(), TransactionErrorType::Code(1));
+    assert_eq!(error.service_id(), Some(1));
+    assert!(simulation.touched_keys.unwrap().is_empty());
+
+    let simulation = blockchain
+        .simulate_transaction(&Tx::new(1, &sec_key), &SimulationOptions::default())
+        .unwrap();
+    assert_eq!(simulation.touched_keys, None);
+
+    let options = SimulationOptions {
+        max_operations: Some(10),
+        ..SimulationOptions::default()
+    };
+    let result = blockchain.simulate_transaction(&Tx::new(100, &sec_key), &options);
+    assert_eq!(result, Err(SimulationError::BudgetExceeded(10)));
+
+    let options = SimulationOptions {
+        max_duration: Some(Duration::from_secs(0)),
+        ..SimulationOptions::default()
+    };
+    let result = blockchain.simulate_transaction(&Tx::new(100, &sec_key), &options);
+    assert_eq!(result, Err(SimulationError::TimeLimitExceeded));
+
+    // Storage failures are returned as errors instead of unwinding.
+    let tx = Tx::new(u64::max_value(), &sec_key);
+    let result = blockchain.simulate_transaction(&tx, &SimulationOptions::default());
+    assert_eq!(
+        result,
+        Err(SimulationError::Storage("Storage failure".to_owned()))
+    );
+}
+
+fn export_import_archive(source: &mut Blockchain, target: &mut Blockchain) {
+    for height in 0..3 {
+        let (_, patch) = source.create_patch(
+            ValidatorId::zero(),
+            Height(height),
+            &[],
+            &BTreeMap::new(),
+        );
+        source.merge(patch).unwrap();
+    }
+
+    let archive = source.export_archive(Vec::new()).unwrap();
+    target.import_archive(&archive[..]).unwrap();
+    assert_eq!(target.last_block(), source.last_block());
+    assert_eq!(target.last_hash(), source.last_hash());
+    // The storage is not empty anymore.
+    assert!(target.import_archive(&archive[..]).is_err());
+}
+
+fn import_archive_with_invalid_state(source: &mut Blockchain, target: &mut Blockchain) {
+    let (_, patch) = source.create_patch(
+        ValidatorId::zero(),
+        Height::zero(),
+        &[],
+        &BTreeMap::new(),
+    );
+    source.merge(patch).unwrap();
+
+    let mut fork = source.fork();
+    Schema::new(&mut fork).transaction_results_mut().put(
+        &Hash::zero(),
+        Ok(()),
+    );
+    source.merge(fork.into_patch()).unwrap();
+
+    let archive = source.export_archive(Vec::new()).unwrap();
+    let err = target.import_archive(&archive[..]).unwrap_err();
+    assert!(err.to_string().contains("state hash"));
+    assert!(target.snapshot().iter("blocks", &[]).next().is_none());
+}
+
 mod transactions_tests {
     use blockchain::{Transaction, TransactionSet, ExecutionResult};
     use storage::Fork;
@@ -367,9 +694,252 @@ mod transactions_tests {
     }
 }
 
+const VERSIONED_VALUES: &str = "versioned.values";
+
+struct VersionedService {
+    version: u32,
+}
+
+impl Service for VersionedService {
+    fn service_id(&self) -> u16 {
+        1
+    }
+
+    fn service_name(&self) -> &str {
+        "versioned"
+    }
+
+    fn state_hash(&self, snapshot: &Snapshot) -> Vec<Hash> {
+        let values: ProofListIndex<_, u64> = ProofListIndex::new(VERSIONED_VALUES, snapshot);
+        vec![values.merkle_root()]
+    }
+
+    fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<Transaction>, MessageError> {
+        Err(MessageError::IncorrectMessageType {
+            message_type: raw.message_type(),
+        })
+    }
+
+    fn initialize(&self, fork: &mut Fork) -> Value {
+        ProofListIndex::new(VERSIONED_VALUES, fork).extend(vec![1_u64, 2]);
+        Value::Null
+    }
+
+    fn schema_version(&self) -> u32 {
+        self.version
+    }
+
+    fn migrations(&self) -> Vec<Migration> {
+        vec![
+            Migration::new(2, "Append the sum of values", |fork: &mut Fork| {
+                let mut values: ProofListIndex<_, u64> =
+                    ProofListIndex::new(VERSIONED_VALUES, fork);
+                let sum = values.iter().sum();
+                values.push(sum);
+            }),
+            Migration::new(1, "Double values", |fork: &mut Fork| {
+                let mut values: ProofListIndex<_, u64> =
+                    ProofListIndex::new(VERSIONED_VALUES, fork);
+                for index in 0..values.len() {
+                    let value = values.get(index).unwrap();
+                    values.set(index, value * 2);
+                }
+            }),
+        ]
+    }
+}
+
+const HEIGHTS: &str = "heights.values";
+
+// Records the height of each block.
+struct HeightsService;
+
+impl Service for HeightsService {
+    fn service_id(&self) -> u16 {
+        2
+    }
+
+    fn service_name(&self) -> &str {
+        "heights"
+    }
+
+    fn state_hash(&self, snapshot: &Snapshot) -> Vec<Hash> {
+        let heights: ProofListIndex<_, u64> = ProofListIndex::new(HEIGHTS, snapshot);
+        vec![heights.merkle_root()]
+    }
+
+    fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<Transaction>, MessageError> {
+        Err(MessageError::IncorrectMessageType {
+            message_type: raw.message_type(),
+        })
+    }
+
+    fn before_commit(&self, fork: &mut Fork) {
+        let height = Schema::new(&*fork).block_hashes_by_height().len();
+        ProofListIndex::new(HEIGHTS, fork).push(height);
+    }
+}
+
+struct PanickingService;
+
+impl Service for PanickingService {
+    fn service_id(&self) -> u16 {
+        3
+    }
+
+    fn service_name(&self) -> &str {
+        "panicking"
+    }
+
+    fn state_hash(&self, _: &Snapshot) -> Vec<Hash> {
+        Vec::new()
+    }
+
+    fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<Transaction>, MessageError> {
+        Err(MessageError::IncorrectMessageType {
+            message_type: raw.message_type(),
+        })
+    }
+
+    fn before_commit(&self, fork: &mut Fork) {
+        ListIndex::new(IDX_NAME, fork).push(1_u64);
+        panic!("before_commit failed");
+    }
+}
+
+fn before_commit(db: Arc<Database>) {
+    let service_keypair = gen_keypair();
+    let api_channel = mpsc::channel(1);
+    let mut blockchain = Blockchain::new(
+        db,
+        vec![
+            Box::new(PanickingService) as Box<Service>,
+            Box::new(HeightsService) as Box<Service>,
+        ],
+        service_keypair.0,
+        service_keypair.1,
+        ApiSender::new(api_channel.0),
+    );
+    let validator_keys = ValidatorKeys {
+        consensus_key: gen_keypair().0,
+        service_key: gen_keypair().0,
+    };
+    blockchain
+        .initialize(GenesisConfig::new(vec![validator_keys].into_iter()))
+        .unwrap();
+    let (_, patch) = blockchain.create_patch(
+        ValidatorId::zero(),
+        Height(1),
+        &[],
+        &BTreeMap::new(),
+    );
+    blockchain.merge(patch).unwrap();
+
+    let snapshot = blockchain.snapshot();
+    let heights: ProofListIndex<_, u64> = ProofListIndex::new(HEIGHTS, &snapshot);
+    assert_eq!(heights.iter().collect::<Vec<_>>(), vec![0, 1]);
+    // The changes are a part of the block state.
+    let schema = Schema::new(&snapshot);
+    assert_eq!(
+        schema.state_hash_aggregator().get(
+            &Blockchain::service_table_unique_key(2, 0),
+        ),
+        Some(heights.merkle_root())
+    );
+    assert_eq!(
+        *blockchain.last_block().state_hash(),
+        schema.state_hash_aggregator().merkle_root()
+    );
+    // The changes of the panicking service are discarded.
+    let values: ListIndex<_, u64> = ListIndex::new(IDX_NAME, &snapshot);
+    assert!(values.is_empty());
+}
+
+fn migrate_service_data(db: Arc<Database>) {
+    let init = |version: u32| {
+        let service_keypair = gen_keypair();
+        let api_channel = mpsc::channel(1);
+        let mut blockchain = Blockchain::new(
+            Arc::clone(&db),
+            vec![Box::new(VersionedService { version }) as Box<Service>],
+            service_keypair.0,
+            service_keypair.1,
+            ApiSender::new(api_channel.0),
+        );
+        let validator_keys = ValidatorKeys {
+            consensus_key: gen_keypair().0,
+            service_key: gen_keypair().0,
+        };
+        let genesis = GenesisConfig::new(vec![validator_keys].into_iter());
+        blockchain.initialize(genesis).map(|_| blockchain)
+    };
+    let values = |blockchain: &Blockchain| {
+        let snapshot = blockchain.snapshot();
+        let values: ProofListIndex<_, u64> = ProofListIndex::new(VERSIONED_VALUES, &snapshot);
+        values.iter().collect::<Vec<_>>()
+    };
+    let version = |blockchain: &Blockchain| {
+        Schema::new(&blockchain.snapshot())
+            .service_schema_versions()
+            .get(&1)
+    };
+
+    let blockchain = init(0).unwrap();
+    assert_eq!(version(&blockchain), Some(0));
+    let genesis_state_hash = *blockchain.last_block().state_hash();
+
+    // The migration to version 3 is missing, so the data is left intact.
+    let err = init(3).unwrap_err();
+    assert!(err.to_string().contains("Missing migration"));
+    assert_eq!(version(&blockchain), Some(0));
+    assert_eq!(values(&blockchain), vec![1, 2]);
+
+    let mut blockchain = init(2).unwrap();
+    assert_eq!(version(&blockchain), Some(2));
+    assert_eq!(values(&blockchain), vec![2, 4, 6]);
+    let records = Schema::new(&blockchain.snapshot())
+        .migrations()
+        .iter()
+        .collect::<Vec<_>>();
+    assert_eq!(records.len(), 2);
+    assert_eq!(
+        (records[0].from_version(), records[0].to_version()),
+        (0, 1)
+    );
+    assert_eq!(
+        (records[1].from_version(), records[1].to_version()),
+        (1, 2)
+    );
+    assert_eq!(records[0].height(), Height::zero());
+    assert_eq!(*records[0].state_hash_before(), genesis_state_hash);
+    assert_eq!(records[1].state_hash_before(), records[0].state_hash_after());
+
+    // The migrated state is committed with the next block.
+    let (_, patch) = blockchain.create_patch(
+        ValidatorId::zero(),
+        Height(1),
+        &[],
+        &BTreeMap::new(),
+    );
+    blockchain.merge(patch).unwrap();
+    assert_eq!(
+        blockchain.last_block().state_hash(),
+        records[1].state_hash_after()
+    );
+
+    // The data is already migrated.
+    let blockchain = init(2).unwrap();
+    assert_eq!(Schema::new(&blockchain.snapshot()).migrations().len(), 2);
+
+    // The stored schema version is newer than the one supported by the service.
+    let err = init(1).unwrap_err();
+    assert!(err.to_string().contains("newer"));
+}
+
 mod memorydb_tests {
     use futures::sync::mpsc;
     use std::path::Path;
+    use std::sync::Arc;
     use tempdir::TempDir;
     use storage::{Database, MemoryDB};
     use blockchain::Blockchain;
@@ -411,11 +981,70 @@ mod memorydb_tests {
         let blockchain = create_blockchain(path);
         super::handling_tx_panic_storage_error(&blockchain);
     }
+
+    #[test]
+    fn test_snapshot_at_height() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let path = dir.path();
+        let mut blockchain = create_blockchain(path);
+        super::snapshot_at_height(&mut blockchain);
+    }
+
+    #[test]
+    fn test_storage_stats() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        super::storage_stats(create_database(dir.path()));
+    }
+
+    #[test]
+    fn test_tx_events() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let mut blockchain = create_blockchain(dir.path());
+        super::tx_events(&mut blockchain);
+    }
+
+    #[test]
+    fn test_simulate_transaction() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let blockchain = create_blockchain(dir.path());
+        super::simulate_transaction(&blockchain);
+    }
+
+    #[test]
+    fn test_export_import_archive() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let mut source = create_blockchain(&dir.path().join("source"));
+        let mut target = create_blockchain(&dir.path().join("target"));
+        super::export_import_archive(&mut source, &mut target);
+    }
+
+    #[test]
+    fn test_import_archive_with_invalid_state() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let mut source = create_blockchain(&dir.path().join("source"));
+        let mut target = create_blockchain(&dir.path().join("target"));
+        super::import_archive_with_invalid_state(&mut source, &mut target);
+    }
+
+    #[test]
+    fn test_migrate_service_data() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let db = create_database(dir.path());
+        super::migrate_service_data(Arc::from(db));
+    }
+
+    #[test]
+    fn test_before_commit() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let db = create_database(dir.path());
+        super::before_commit(Arc::from(db));
+    }
 }
 
 mod rocksdb_tests {
     use futures::sync::mpsc;
     use std::path::Path;
+    use std::sync::Arc;
     use tempdir::TempDir;
     use storage::{Database, RocksDB, DbOptions};
     use blockchain::Blockchain;
@@ -459,4 +1088,55 @@ mod rocksdb_tests {
         let blockchain = create_blockchain(path);
         super::handling_tx_panic_storage_error(&blockchain);
     }
+
+    #[test]
+    fn test_snapshot_at_height() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let path = dir.path();
+        let mut blockchain = create_blockchain(path);
+        super::snapshot_at_height(&mut blockchain);
+    }
+
+    #[test]
+    fn test_storage_stats() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        super::storage_stats(create_database(dir.path()));
+    }
+
+    #[test]
+    fn test_tx_events() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let mut blockchain = create_blockchain(dir.path());
+        super::tx_events(&mut blockchain);
+    }
+
+    #[test]
+    fn test_export_import_archive() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let mut source = create_blockchain(&dir.path().join("source"));
+        let mut target = create_blockchain(&dir.path().join("target"));
+        super::export_import_archive(&mut source, &mut target);
+    }
+
+    #[test]
+    fn test_import_archive_with_invalid_state() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let mut source = create_blockchain(&dir.path().join("source"));
+        let mut target = create_blockchain(&dir.path().join("target"));
+        super::import_archive_with_invalid_state(&mut source, &mut target);
+    }
+
+    #[test]
+    fn test_migrate_service_data() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let db = create_database(dir.path());
+        super::migrate_service_data(Arc::from(db));
+    }
+
+    #[test]
+    fn test_before_commit() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let db = create_database(dir.path());
+        super::before_commit(Arc::from(db));
+    }
 }
@@ -22,12 +22,14 @@ use std::u8;
 
 use serde::Serialize;
 use serde::de::DeserializeOwned;
+use serde_json::{self, Value as JsonValue};
 
 use messages::{Message, RawTransaction};
 use storage::{Fork, StorageValue};
-use crypto::{Hash, CryptoHash};
+use crypto::{self, Hash, CryptoHash};
 use encoding;
 use encoding::serialize::json::ExonumJson;
+use helpers::Height;
 
 //  User-defined error codes (`TransactionErrorType::Code(u8)`) have a `0...255` range.
 #[cfg_attr(feature = "cargo-clippy", allow(cast_lossless))]
@@ -36,6 +38,10 @@ const MAX_ERROR_CODE: u16 = u8::MAX as u16;
 const TRANSACTION_STATUS_OK: u16 = MAX_ERROR_CODE + 1;
 // `Err(TransactionErrorType::Panic)`.
 const TRANSACTION_STATUS_PANIC: u16 = TRANSACTION_STATUS_OK + 1;
+// Flags of the optional parts of the stored `TransactionError`.
+const DESCRIPTION_FLAG: u8 = 1;
+const SERVICE_ID_FLAG: u8 = 2;
+const PAYLOAD_FLAG: u8 = 4;
 
 /// Return value of the `Transaction`'s `execute' method. Changes made by the transaction are
 /// discarded if `Err` is returned, see `Transaction` documentation for the details.
@@ -144,6 +150,91 @@ pub trait Transaction: Message + ExonumJson + 'static {
     /// }
     /// # fn main() {}
     fn execute(&self, fork: &mut Fork) -> ExecutionResult;
+
+    /// Returns the validity window of the transaction, or `None` if the transaction never
+    /// expires, which is the default.
+    ///
+    /// Expired transactions are not added to the pool of unconfirmed transactions,
+    /// and block proposals containing them are considered incorrect. Transactions expiring
+    /// while in the pool are removed from it when the next block is committed. Together with
+    /// the check for already committed transactions, this prevents the transaction from being
+    /// replayed after the window has passed.
+    ///
+    /// *Like `verify`, this method must depend only on the transaction contents.*
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate exonum;
+    /// #
+    /// use exonum::blockchain::{Transaction, TxExpiration};
+    /// use exonum::crypto::PublicKey;
+    /// use exonum::helpers::Height;
+    /// # use exonum::blockchain::ExecutionResult;
+    /// # use exonum::storage::Fork;
+    ///
+    /// transactions! {
+    ///     MyTransactions {
+    ///         const SERVICE_ID = 1;
+    ///
+    ///         struct MyTransaction {
+    ///             public_key: &PublicKey,
+    ///             // Height of the latest block known to the author.
+    ///             last_height: Height,
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// impl Transaction for MyTransaction {
+    ///     fn expiration(&self) -> Option<TxExpiration> {
+    ///         Some(TxExpiration::TimeToLive {
+    ///             height: self.last_height(),
+    ///             blocks: 100,
+    ///         })
+    ///     }
+    ///
+    ///     // Other methods...
+    ///     // ...
+    /// #   fn verify(&self) -> bool { true }
+    /// #   fn execute(&self, _: &mut Fork) -> ExecutionResult { Ok(()) }
+    /// }
+    /// # fn main() {}
+    /// ```
+    fn expiration(&self) -> Option<TxExpiration> {
+        None
+    }
+}
+
+/// Validity window of a transaction, see
+/// [`Transaction::expiration`](trait.Transaction.html#method.expiration).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxExpiration {
+    /// The transaction can be included into the blocks up to the given height inclusive.
+    MaxHeight(Height),
+    /// The transaction can be included into the given number of blocks following the block
+    /// at the reference height, usually the latest block known to the transaction author.
+    TimeToLive {
+        /// Reference height.
+        height: Height,
+        /// Number of blocks.
+        blocks: u64,
+    },
+}
+
+impl TxExpiration {
+    /// Returns the maximal height of the block, which the transaction can be included into.
+    pub fn max_height(&self) -> Height {
+        match *self {
+            TxExpiration::MaxHeight(height) => height,
+            TxExpiration::TimeToLive { height, blocks } => Height(height.0.saturating_add(blocks)),
+        }
+    }
+
+    /// Returns `true` if the transaction cannot be included into the block
+    /// at the given height.
+    pub fn is_expired(&self, height: Height) -> bool {
+        height > self.max_height()
+    }
 }
 
 /// Result of unsuccessful transaction execution.
@@ -154,6 +245,8 @@ pub struct ExecutionError {
     code: u8,
     /// Optional error description.
     description: Option<String>,
+    /// Optional error payload serialized to the canonical `JSON`.
+    payload: Option<String>,
 }
 
 impl ExecutionError {
@@ -162,6 +255,7 @@ impl ExecutionError {
         Self {
             code,
             description: None,
+            payload: None,
         }
     }
 
@@ -170,8 +264,52 @@ impl ExecutionError {
         Self {
             code,
             description: Some(description),
+            payload: None,
         }
     }
+
+    /// Constructs a new `ExecutionError` instance with the given error code and
+    /// machine-readable payload.
+    ///
+    /// Unlike the description, the payload is a part of the transaction result hash,
+    /// hence it must not depend on anything but the transaction and the blockchain state.
+    /// The payload is serialized to `JSON` with the keys of the objects sorted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate serde_derive;
+    /// # extern crate exonum;
+    /// use exonum::blockchain::ExecutionError;
+    ///
+    /// #[derive(Serialize)]
+    /// struct InsufficientFunds {
+    ///     have: u64,
+    ///     need: u64,
+    /// }
+    ///
+    /// # fn main() {
+    /// let error = ExecutionError::with_payload(1, &InsufficientFunds { have: 5, need: 10 });
+    /// # drop(error);
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the payload cannot be serialized to `JSON`.
+    pub fn with_payload<P: Serialize>(code: u8, payload: &P) -> Self {
+        let payload = serde_json::to_value(payload).expect("Unable to serialize error payload");
+        Self {
+            code,
+            description: None,
+            payload: Some(payload.to_string()),
+        }
+    }
+
+    /// Sets the description of the error.
+    pub fn set_description(&mut self, description: String) {
+        self.description = Some(description);
+    }
 }
 
 /// Type of the transaction error.
@@ -191,6 +329,9 @@ pub enum TransactionErrorType {
 ///
 /// - Content of `description`' field is excluded from hash calculation (see `StorageValue`
 ///   implementation for the details).
+/// - The identifier of the service, which the error code belongs to, is set by the framework
+///   to the service of the transaction.
+/// - Error payload is included into the hash together with the service identifier.
 /// - `TransactionErrorType::Panic` is set by the framework if panic is raised during transaction
 ///   execution.
 /// - `TransactionError` implements `Display` which can be used for obtaining a simple error
@@ -224,6 +365,10 @@ pub struct TransactionError {
     error_type: TransactionErrorType,
     /// Optional error description.
     description: Option<String>,
+    /// Identifier of the service, which the error code belongs to.
+    service_id: Option<u16>,
+    /// Optional error payload serialized to the canonical `JSON`.
+    payload: Option<String>,
 }
 
 impl TransactionError {
@@ -232,6 +377,8 @@ impl TransactionError {
         Self {
             error_type,
             description,
+            service_id: None,
+            payload: None,
         }
     }
 
@@ -250,6 +397,15 @@ impl TransactionError {
         Self::panic(panic_description(panic))
     }
 
+    /// Creates a new `TransactionError` instance from the error returned by a transaction
+    /// of the service with the given identifier.
+    pub(crate) fn from_execution_error(error: ExecutionError, service_id: u16) -> Self {
+        Self {
+            service_id: Some(service_id),
+            ..Self::from(error)
+        }
+    }
+
     /// Returns error type of this `TransactionError` instance.
     pub fn error_type(&self) -> TransactionErrorType {
         self.error_type
@@ -259,6 +415,18 @@ impl TransactionError {
     pub fn description(&self) -> Option<&str> {
         self.description.as_ref().map(String::as_ref)
     }
+
+    /// Returns the identifier of the service, which the error code belongs to.
+    pub fn service_id(&self) -> Option<u16> {
+        self.service_id
+    }
+
+    /// Returns an optional error payload.
+    pub fn payload(&self) -> Option<JsonValue> {
+        self.payload.as_ref().map(|payload| {
+            serde_json::from_str(payload).expect("Invalid error payload")
+        })
+    }
 }
 
 impl<'a, T: Transaction> From<T> for Box<Transaction + 'a> {
@@ -278,16 +446,24 @@ impl fmt::Display for TransactionError {
             write!(f, " description: {}", description)?;
         }
 
+        if let Some(ref payload) = self.payload {
+            write!(f, " payload: {}", payload)?;
+        }
+
         Ok(())
     }
 }
 
 // String content (`TransactionError::Description`) is intentionally excluded from the hash
 // calculation because user can be tempted to use error description from a third-party libraries
-// which aren't stable across the versions.
+// which aren't stable across the versions. The errors without a payload are hashed by the status
+// only, so that their hashes are the same as before the payloads were introduced.
 impl CryptoHash for TransactionResult {
     fn hash(&self) -> Hash {
-        u16::hash(&status_as_u16(self))
+        match *self {
+            Err(ref e) if e.payload.is_some() => crypto::hash(&encode_result(self, false)),
+            _ => u16::hash(&status_as_u16(self)),
+        }
     }
 }
 
@@ -296,39 +472,93 @@ impl From<ExecutionError> for TransactionError {
         Self {
             error_type: TransactionErrorType::Code(error.code),
             description: error.description,
+            service_id: None,
+            payload: error.payload,
         }
     }
 }
 
-// `TransactionResult` is stored as `u16` plus `bool` (`true` means that optional part is present)
-// with optional string part needed only for string error description.
+// `TransactionResult` is stored as `u16` status plus `u8` flags of the optional parts, which
+// follow in the order: `u16` service identifier, `u32` length and `JSON` of the payload and
+// the string error description till the end.
 impl StorageValue for TransactionResult {
     fn into_bytes(self) -> Vec<u8> {
-        let mut res = u16::into_bytes(status_as_u16(&self));
-        if let Some(description) = self.err().and_then(|e| e.description) {
-            res.extend(bool::into_bytes(true));
-            res.extend(String::into_bytes(description));
-        } else {
-            res.extend(bool::into_bytes(false));
-        }
-        res
+        encode_result(&self, true)
     }
 
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
         let main_part = u16::from_bytes(Cow::Borrowed(&bytes));
-        let description = if bool::from_bytes(Cow::Borrowed(&bytes[2..3])) {
-            Some(String::from_bytes(Cow::Borrowed(&bytes[3..])))
+        let flags = bytes[2];
+        let mut offset = 3;
+        let service_id = if flags & SERVICE_ID_FLAG != 0 {
+            offset += 2;
+            Some(u16::from_bytes(Cow::Borrowed(&bytes[offset - 2..offset])))
+        } else {
+            None
+        };
+        let payload = if flags & PAYLOAD_FLAG != 0 {
+            let len = u32::from_bytes(Cow::Borrowed(&bytes[offset..offset + 4])) as usize;
+            offset += 4 + len;
+            Some(String::from_bytes(Cow::Borrowed(&bytes[offset - len..offset])))
+        } else {
+            None
+        };
+        let description = if flags & DESCRIPTION_FLAG != 0 {
+            Some(String::from_bytes(Cow::Borrowed(&bytes[offset..])))
         } else {
             None
         };
 
-        match main_part {
-            value @ 0...MAX_ERROR_CODE => Err(TransactionError::code(value as u8, description)),
-            TRANSACTION_STATUS_OK => Ok(()),
-            TRANSACTION_STATUS_PANIC => Err(TransactionError::panic(description)),
+        let mut error = match main_part {
+            value @ 0...MAX_ERROR_CODE => TransactionError::code(value as u8, description),
+            TRANSACTION_STATUS_OK => return Ok(()),
+            TRANSACTION_STATUS_PANIC => TransactionError::panic(description),
             value => panic!("Invalid TransactionResult value: {}", value),
+        };
+        error.service_id = service_id;
+        error.payload = payload;
+        Err(error)
+    }
+}
+
+fn encode_result(result: &TransactionResult, with_description: bool) -> Vec<u8> {
+    let mut res = u16::into_bytes(status_as_u16(result));
+    let error = match *result {
+        Ok(()) => {
+            res.push(0);
+            return res;
         }
+        Err(ref e) => e,
+    };
+    let description = if with_description {
+        error.description.as_ref()
+    } else {
+        None
+    };
+
+    let mut flags = 0;
+    if description.is_some() {
+        flags |= DESCRIPTION_FLAG;
+    }
+    if error.service_id.is_some() {
+        flags |= SERVICE_ID_FLAG;
+    }
+    if error.payload.is_some() {
+        flags |= PAYLOAD_FLAG;
+    }
+    res.push(flags);
+
+    if let Some(service_id) = error.service_id {
+        res.extend(u16::into_bytes(service_id));
+    }
+    if let Some(ref payload) = error.payload {
+        res.extend(u32::into_bytes(payload.len() as u32));
+        res.extend_from_slice(payload.as_bytes());
+    }
+    if let Some(description) = description {
+        res.extend_from_slice(description.as_bytes());
     }
+    res
 }
 
 fn status_as_u16(status: &TransactionResult) -> u16 {
@@ -631,6 +861,28 @@ mod tests {
         static ref EXECUTION_STATUS: Mutex<ExecutionResult> = Mutex::new(Ok(()));
     }
 
+    #[test]
+    fn tx_expiration() {
+        let expiration = TxExpiration::MaxHeight(Height(5));
+        assert_eq!(expiration.max_height(), Height(5));
+        assert!(!expiration.is_expired(Height(5)));
+        assert!(expiration.is_expired(Height(6)));
+
+        let expiration = TxExpiration::TimeToLive {
+            height: Height(5),
+            blocks: 10,
+        };
+        assert_eq!(expiration.max_height(), Height(15));
+        assert!(!expiration.is_expired(Height(15)));
+        assert!(expiration.is_expired(Height(16)));
+
+        let expiration = TxExpiration::TimeToLive {
+            height: Height(5),
+            blocks: u64::max_value(),
+        };
+        assert!(!expiration.is_expired(Height(u64::max_value())));
+    }
+
     #[test]
     fn execution_error_new() {
         let codes = [0, 1, 100, 255];
@@ -717,6 +969,20 @@ mod tests {
                 255,
                 Some("(Not) really long error description".to_owned()),
             )),
+            Err(TransactionError::from_execution_error(ExecutionError::new(3), 0)),
+            Err(TransactionError::from_execution_error(
+                ExecutionError::with_payload(3, &vec![1, 2]),
+                128,
+            )),
+            Err(TransactionError::from_execution_error(
+                {
+                    let mut error = ExecutionError::with_payload(3, &"");
+                    error.set_description("Error with payload".to_owned());
+                    error
+                },
+                u16::max_value(),
+            )),
+            Err(TransactionError::from(ExecutionError::with_payload(7, &()))),
         ];
 
         for result in &results {
@@ -726,6 +992,64 @@ mod tests {
         }
     }
 
+    #[test]
+    fn transaction_results_legacy_encoding() {
+        let bytes = [1, 0, 1, b'a', b'b'];
+        let result = TransactionResult::from_bytes(Cow::Borrowed(&bytes));
+        assert_eq!(result, Err(TransactionError::code(1, Some("ab".to_owned()))));
+        assert_eq!(result.into_bytes(), bytes.to_vec());
+
+        let result: TransactionResult = Ok(());
+        assert_eq!(result.into_bytes(), vec![0, 1, 0]);
+    }
+
+    #[test]
+    fn error_payload() {
+        #[derive(Serialize)]
+        struct InsufficientFunds {
+            need: u64,
+            have: u64,
+        }
+
+        let error = ExecutionError::with_payload(1, &InsufficientFunds { need: 10, have: 5 });
+        let error = TransactionError::from_execution_error(error, 128);
+        assert_eq!(error.service_id(), Some(128));
+        assert_eq!(error.payload().unwrap()["have"], 5);
+        // Keys of the payload are sorted.
+        assert_eq!(error.payload.as_ref().unwrap(), r#"{"have":5,"need":10}"#);
+        assert_eq!(
+            error.to_string(),
+            r#"Error code: 1 payload: {"have":5,"need":10}"#
+        );
+    }
+
+    #[test]
+    fn transaction_results_hash() {
+        let with_payload = |payload: u8, service_id: u16| -> TransactionResult {
+            Err(TransactionError::from_execution_error(
+                ExecutionError::with_payload(1, &payload),
+                service_id,
+            ))
+        };
+
+        // Errors without payload are hashed by the status only.
+        let error = TransactionError::from_execution_error(
+            ExecutionError::with_description(1, "description".to_owned()),
+            128,
+        );
+        assert_eq!(Err(error).hash(), u16::hash(&1));
+        assert_eq!(Ok(()).hash(), u16::hash(&TRANSACTION_STATUS_OK));
+
+        assert_ne!(with_payload(0, 1).hash(), u16::hash(&1));
+        assert_ne!(with_payload(0, 1).hash(), with_payload(1, 1).hash());
+        assert_ne!(with_payload(0, 1).hash(), with_payload(0, 2).hash());
+        // The description is excluded from the hash.
+        let mut error = ExecutionError::with_payload(1, &0);
+        error.set_description("description".to_owned());
+        let result: TransactionResult = Err(TransactionError::from_execution_error(error, 1));
+        assert_eq!(result.hash(), with_payload(0, 1).hash());
+    }
+
     #[test]
     fn error_discards_transaction_changes() {
         let statuses = [
@@ -29,7 +29,7 @@ use blockchain::config::ValidatorKeys;
 use helpers::generate_testnet_config;
 use helpers::config::ConfigFile;
 use node::{NodeApiConfig, NodeConfig};
-use storage::{Database, RocksDB, DbOptions};
+use storage::{CachedDB, Database, MeteredDB, ReadOnlyRocksDB, RocksDB, DbOptions};
 use crypto;
 use super::internal::{CollectedCommand, Command, Feedback};
 use super::{Argument, CommandName, Context};
@@ -52,12 +52,29 @@ impl Run {
     }
 
     /// Returns created database instance.
+    ///
+    /// If the `read_only` option is set, the database is opened in the read-only mode,
+    /// which allows to read the data of a running node.
     pub fn db_helper(ctx: &Context, options: &DbOptions) -> Box<Database> {
         let path = ctx.arg::<String>(DATABASE_PATH).expect(&format!(
             "{} not found.",
             DATABASE_PATH
         ));
-        Box::new(RocksDB::open(Path::new(&path), options).unwrap())
+        let db: Box<Database> = if options.read_only {
+            Box::new(ReadOnlyRocksDB::open(Path::new(&path), options).unwrap())
+        } else {
+            Box::new(RocksDB::open(Path::new(&path), options).unwrap())
+        };
+        let db: Box<Database> = match options.cache_size {
+            Some(size) => Box::new(CachedDB::new(db, size)),
+            None => db,
+        };
+        // Reads are counted before they reach the cache.
+        if options.storage_stats {
+            Box::new(MeteredDB::new(db))
+        } else {
+            db
+        }
     }
 
     fn node_config(ctx: &Context) -> NodeConfig {
@@ -24,6 +24,7 @@
 
 extern crate exonum_sodiumoxide as sodiumoxide;
 extern crate exonum_rocksdb as rocksdb;
+extern crate exonum_librocksdb_sys as rocksdb_sys;
 #[macro_use]
 extern crate exonum_profiler;
 #[macro_use]
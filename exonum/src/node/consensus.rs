@@ -230,6 +230,10 @@ impl NodeHandler {
                             return;
                         }
                     });
+                    if self.state.is_expired(&*tx) {
+                        error!("Expired transaction in block detected, block={:?}", msg);
+                        return;
+                    }
                     self.state.add_transaction(hash, tx, true);
                     tx_hashes.push(hash);
                 } else {
@@ -355,6 +359,15 @@ impl NodeHandler {
             return;
         }
 
+        // Propose with an expired transaction cannot be executed.
+        if !self.state.propose(propose_hash).unwrap().is_valid() {
+            error!(
+                "Received majority precommits for invalid propose, propose={:?}",
+                propose_hash
+            );
+            return;
+        }
+
         // Request transactions if needed.
         let proposer = {
             let propose_state = self.state.propose(propose_hash).unwrap();
@@ -598,6 +611,11 @@ impl NodeHandler {
             return;
         }
 
+        if self.state.is_expired(&*msg) {
+            trace!("Ignoring expired transaction: {:?}", hash);
+            return;
+        }
+
         // Broadcast transaction to validators
         trace!("Broadcast transactions: {:?}", msg.raw());
         self.broadcast(msg.raw());
@@ -840,6 +858,10 @@ impl NodeHandler {
     pub fn request_propose_or_txs(&mut self, propose_hash: &Hash, key: PublicKey) -> bool {
         let requested_data = match self.state.propose(propose_hash) {
             Some(state) => {
+                // Expired transactions of an invalid propose are not requested.
+                if !state.is_valid() {
+                    return false;
+                }
                 // Request transactions
                 if state.has_unknown_txs() {
                     Some(RequestData::Transactions(*propose_hash))
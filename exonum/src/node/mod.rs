@@ -786,6 +786,9 @@ impl Node {
             node_cfg.service_secret_key.clone(),
             ApiSender::new(channel.api_requests.0.clone()),
         );
+        blockchain
+            .set_history_retention(node_cfg.database.and_then(|opts| opts.history_retention))
+            .unwrap();
         blockchain.initialize(node_cfg.genesis.clone()).unwrap();
 
         let config = Configuration {
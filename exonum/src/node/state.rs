@@ -137,6 +137,8 @@ pub struct ProposeState {
     block_hash: Option<Hash>,
     // Whether the message has been saved to the consensus messages' cache or not.
     is_saved: bool,
+    // Whether the propose can be executed. A propose with an expired transaction cannot.
+    is_valid: bool,
 }
 
 /// State of a block.
@@ -326,6 +328,12 @@ impl ProposeState {
     pub fn set_saved(&mut self, saved: bool) {
         self.is_saved = saved;
     }
+
+    /// Returns `false` if the propose contains an expired transaction, so it cannot
+    /// be executed.
+    pub fn is_valid(&self) -> bool {
+        self.is_valid
+    }
 }
 
 impl BlockState {
@@ -726,11 +734,18 @@ impl State {
         {
             // Commit transactions if needed
             let txs = self.block(block_hash).unwrap().txs.clone();
+            let mut transactions = self.transactions.write().expect("Expected write lock");
             for hash in txs {
-                self.transactions
-                    .write()
-                    .expect("Expected write lock")
-                    .remove(&hash);
+                transactions.remove(&hash);
+            }
+            // Remove expired transactions
+            let expired = transactions
+                .iter()
+                .filter(|&(_, tx)| tx_is_expired(&**tx, self.height))
+                .map(|(hash, _)| *hash)
+                .collect::<Vec<_>>();
+            for hash in expired {
+                transactions.remove(&hash);
             }
         }
         // TODO: destruct/construct structure HeightState instead of call clear (ECR-171)
@@ -762,6 +777,11 @@ impl State {
         &self.transactions
     }
 
+    /// Returns `true` if the transaction cannot be included into a block at the current height.
+    pub fn is_expired(&self, tx: &Transaction) -> bool {
+        tx_is_expired(tx, self.height)
+    }
+
     /// Adds a transaction to the pool and returns list of proposes that don't contain unknown
     /// transactions now.
     ///
@@ -770,6 +790,10 @@ impl State {
     /// - transactions pool size is exceeded
     /// - transaction isn't contained in unknown transaction list of any propose
     /// - transaction isn't a part of block
+    ///
+    /// Expired transactions are always ignored. The proposes containing such a transaction
+    /// are marked invalid, so they are never executed, and the transaction is no longer
+    /// requested for them.
     pub fn add_transaction(
         &mut self,
         tx_hash: Hash,
@@ -778,10 +802,30 @@ impl State {
         // we should add it, or we could become stuck in some state
         mut high_priority_tx: bool,
     ) -> Vec<(Hash, Round)> {
+        if self.is_expired(&*msg) {
+            let mut invalid_proposes = Vec::new();
+            for (propose_hash, propose_state) in &mut self.proposes {
+                if propose_state.unknown_txs.remove(&tx_hash) {
+                    error!(
+                        "Received propose with expired transaction, propose={:?}, tx={:?}",
+                        propose_hash,
+                        tx_hash
+                    );
+                    propose_state.is_valid = false;
+                    invalid_proposes.push(*propose_hash);
+                }
+            }
+            self.unknown_txs.remove(&tx_hash);
+            for propose_hash in invalid_proposes {
+                self.remove_request(&RequestData::Transactions(propose_hash));
+            }
+            return Vec::new();
+        }
+
         let mut full_proposes = Vec::new();
         for (propose_hash, propose_state) in &mut self.proposes {
             high_priority_tx |= propose_state.unknown_txs.remove(&tx_hash);
-            if propose_state.unknown_txs.is_empty() {
+            if propose_state.is_valid && propose_state.unknown_txs.is_empty() {
                 full_proposes.push((*propose_hash, propose_state.message().round()));
             }
         }
@@ -849,6 +893,7 @@ impl State {
                 // TODO:: for the moment it's true because this code gets called immediately after
                 // saving a propose to the cache. Think about making this approach less error-prone
                 is_saved: true,
+                is_valid: true,
             },
         );
 
@@ -877,6 +922,7 @@ impl State {
                     unknown_txs,
                     block_hash: None,
                     is_saved: false,
+                    is_valid: true,
                 }))
             }
         }
@@ -1092,6 +1138,10 @@ impl State {
     }
 }
 
+fn tx_is_expired(tx: &Transaction, height: Height) -> bool {
+    tx.expiration().map_or(false, |expiration| expiration.is_expired(height))
+}
+
 fn make_timeout_adjuster(config: &ConsensusConfig) -> Box<TimeoutAdjuster> {
     match config.timeout_adjuster {
         TimeoutAdjusterConfig::Constant { timeout } => Box::new(Constant::new(timeout)),
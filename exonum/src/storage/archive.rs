@@ -0,0 +1,349 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Export of the whole database state into a single archive and import of it
+//! into an empty database.
+//!
+//! An archive contains every index registered in the indexes metadata together with its
+//! type, as well as the internal storage tables (the indexes metadata itself and the
+//! [storage history](../history/index.html)). Indexes that were written directly through
+//! `Fork::put` without being registered in the metadata are not exported.
+//!
+//! The archive has the following layout (all integers are little-endian):
+//!
+//! - Header: the magic bytes `EXNARCHV` followed by the format version (`u16`).
+//! - Records, each starting with a one-byte tag:
+//!   - `0x01`: start of an index; name length (`u32`), the UTF-8 name, the index type
+//!     (`u8`, `0xFF` for the internal tables) and the family flag (`u8`).
+//!   - `0x02`: an entry of the current index; key length (`u32`), key, value length (`u32`)
+//!     and value.
+//!   - `0x00`: end of the archive; followed by the SHA-256 hash of all the preceding bytes.
+//!
+//! The blockchain-level counterparts of the functions in this module, which also verify
+//! the state hash of the imported data, are [`Blockchain::export_archive`] and
+//! [`Blockchain::import_archive`].
+//!
+//! [`Blockchain::export_archive`]: ../../blockchain/struct.Blockchain.html#method.export_archive
+//! [`Blockchain::import_archive`]: ../../blockchain/struct.Blockchain.html#method.import_archive
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+use std::io::{Read, Write};
+
+use super::{BaseIndex, Change, Database, Error, Patch, Result, Snapshot, StorageValue};
+use super::history::HISTORY_TABLE_NAME;
+use super::indexes_metadata::{IndexMetadata, IndexType, INDEXES_METADATA_TABLE_NAME};
+use super::patch_encoding::{ChecksumReader, ChecksumWriter};
+
+/// Version of the archive format produced by `export`.
+pub const ARCHIVE_FORMAT_VERSION: u16 = 1;
+
+const ARCHIVE_MAGIC: &[u8; 8] = b"EXNARCHV";
+
+const TAG_END: u8 = 0;
+const TAG_INDEX: u8 = 1;
+const TAG_ENTRY: u8 = 2;
+
+const INTERNAL_TABLE: u8 = 0xFF;
+const INTERNAL_TABLES: [&str; 2] = [INDEXES_METADATA_TABLE_NAME, HISTORY_TABLE_NAME];
+
+/// Writes every index of the snapshot into `writer` and returns the writer back.
+///
+/// # Examples
+///
+/// ```
+/// use exonum::storage::{Database, MemoryDB, MapIndex};
+/// use exonum::storage::archive;
+///
+/// let db = MemoryDB::new();
+/// let mut fork = db.fork();
+/// MapIndex::new("index", &mut fork).put(&1u8, 10u64);
+/// db.merge(fork.into_patch()).unwrap();
+///
+/// let bytes = archive::export(&*db.snapshot(), Vec::new()).unwrap();
+/// let copy = MemoryDB::new();
+/// archive::import(&copy, &bytes[..]).unwrap();
+///
+/// let snapshot = copy.snapshot();
+/// let index: MapIndex<_, u8, u64> = MapIndex::new("index", &snapshot);
+/// assert_eq!(index.get(&1), Some(10));
+/// ```
+pub fn export<W: Write>(snapshot: &Snapshot, writer: W) -> Result<W> {
+    let mut writer = ChecksumWriter::new(writer, ARCHIVE_MAGIC, ARCHIVE_FORMAT_VERSION)
+        .map_err(write_error)?;
+
+    for name in &INTERNAL_TABLES {
+        write_index(snapshot, &mut writer, name, INTERNAL_TABLE, false)?;
+    }
+    let metadata = BaseIndex::indexes_metadata(snapshot);
+    for (name, metadata) in metadata.iter::<_, String, IndexMetadata>(&()) {
+        let index_type = metadata.index_type() as u8;
+        write_index(snapshot, &mut writer, &name, index_type, metadata.is_family())?;
+    }
+
+    writer.write_raw(&[TAG_END]).map_err(write_error)?;
+    writer.finish().map_err(write_error)
+}
+
+/// Reads an archive and returns its contents as a patch, which can be merged into
+/// an empty database.
+///
+/// # Errors
+///
+/// Returns an error if the archive is malformed, its checksum does not match or
+/// the types of the indexes do not match the indexes metadata stored in the archive.
+pub fn decode<R: Read>(reader: R) -> Result<Patch> {
+    let mut reader =
+        ChecksumReader::new(reader, ARCHIVE_MAGIC, ARCHIVE_FORMAT_VERSION, "archive")?;
+    let mut patch = Patch::new();
+    let mut indexes = BTreeMap::new();
+    let mut internal_tables = HashSet::new();
+    let mut current: Option<String> = None;
+
+    loop {
+        match reader.read_raw(1)?[0] {
+            TAG_INDEX => {
+                let name = String::from_utf8(reader.read_chunk()?).map_err(|_| {
+                    Error::new("Index name is not a valid UTF-8 string")
+                })?;
+                let header = reader.read_raw(2)?;
+                let is_duplicate = if header[0] == INTERNAL_TABLE {
+                    if !INTERNAL_TABLES.contains(&name.as_str()) {
+                        return Err(Error::new(format!("Unknown internal table {:?}", name)));
+                    }
+                    !internal_tables.insert(name.clone())
+                } else {
+                    let index_type = IndexType::from_u8(header[0]).ok_or_else(|| {
+                        Error::new(format!("Unknown type {} of index {:?}", header[0], name))
+                    })?;
+                    let metadata = IndexMetadata::new(index_type, header[1] != 0);
+                    indexes.insert(name.clone(), metadata).is_some()
+                };
+                if is_duplicate {
+                    return Err(Error::new(format!("Duplicate index {:?}", name)));
+                }
+                current = Some(name);
+            }
+            TAG_ENTRY => {
+                let name = current.clone().ok_or_else(|| {
+                    Error::new("An entry precedes any index")
+                })?;
+                let key = reader.read_chunk()?;
+                let value = reader.read_chunk()?;
+                patch.insert_change(name, key, Change::Put(value));
+            }
+            TAG_END => {
+                reader.verify_checksum()?;
+                break;
+            }
+            tag => return Err(Error::new(format!("Unknown record tag: {}", tag))),
+        }
+    }
+
+    check_metadata(&patch, &indexes)?;
+    Ok(patch)
+}
+
+/// Reads an archive and merges its contents into the database.
+///
+/// # Errors
+///
+/// Returns an error if the database is not empty or the archive cannot be decoded
+/// (see [`decode`](fn.decode.html)). No changes are applied to the database in this case.
+pub fn import<R: Read>(db: &Database, reader: R) -> Result<()> {
+    check_empty(&*db.snapshot())?;
+    db.merge(decode(reader)?)
+}
+
+/// Returns an error if the snapshot contains any registered index or internal table.
+pub fn check_empty(snapshot: &Snapshot) -> Result<()> {
+    for name in &INTERNAL_TABLES {
+        if snapshot.iter(name, &[]).next().is_some() {
+            return Err(Error::new("The database is not empty"));
+        }
+    }
+    Ok(())
+}
+
+fn write_index<W: Write>(
+    snapshot: &Snapshot,
+    writer: &mut ChecksumWriter<W>,
+    name: &str,
+    index_type: u8,
+    is_family: bool,
+) -> Result<()> {
+    writer.write_raw(&[TAG_INDEX]).map_err(write_error)?;
+    writer.write_chunk(name.as_bytes()).map_err(write_error)?;
+    writer.write_raw(&[index_type, is_family as u8]).map_err(
+        write_error,
+    )?;
+
+    let mut iter = snapshot.iter(name, &[]);
+    while let Some((key, value)) = iter.next() {
+        writer.write_raw(&[TAG_ENTRY]).map_err(write_error)?;
+        writer.write_chunk(key).map_err(write_error)?;
+        writer.write_chunk(value).map_err(write_error)?;
+    }
+    Ok(())
+}
+
+// Checks that the archived indexes are exactly the ones registered in the archived metadata.
+fn check_metadata(patch: &Patch, indexes: &BTreeMap<String, IndexMetadata>) -> Result<()> {
+    let mut stored = BTreeMap::new();
+    for (_, changes) in patch.iter().filter(|&(name, _)| {
+        name == INDEXES_METADATA_TABLE_NAME
+    })
+    {
+        for (key, change) in changes.iter() {
+            if let Change::Put(ref value) = *change {
+                let name = String::from_utf8(key.clone()).map_err(|_| {
+                    Error::new("Index name in the metadata is not a valid UTF-8 string")
+                })?;
+                stored.insert(name, value.clone());
+            }
+        }
+    }
+
+    for (name, metadata) in indexes {
+        match stored.remove(name) {
+            Some(ref value) if *value == metadata.clone().into_bytes() => {}
+            Some(value) => {
+                return Err(Error::new(format!(
+                    "Type of index {:?} does not match the indexes metadata: {:?}, {:?}",
+                    name,
+                    metadata,
+                    IndexMetadata::from_bytes(Cow::Owned(value))
+                )))
+            }
+            None => {
+                return Err(Error::new(
+                    format!("Index {:?} is missing from the indexes metadata", name),
+                ))
+            }
+        }
+    }
+    if let Some(name) = stored.keys().next() {
+        return Err(Error::new(format!("Index {:?} is missing from the archive", name)));
+    }
+    Ok(())
+}
+
+fn write_error(e: ::std::io::Error) -> Error {
+    Error::new(format!("Cannot write the archive: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use crypto::hash;
+    use storage::{Database, DbOptions, Entry, ListIndex, MapIndex, MemoryDB, ProofMapIndex,
+                  RocksDB, Snapshot};
+    use storage::history;
+    use super::{check_empty, decode, export, import, INDEXES_METADATA_TABLE_NAME};
+
+    fn fill_database(db: &Database) {
+        let mut fork = db.fork();
+        {
+            let mut list = ListIndex::new("list", &mut fork);
+            list.extend(0..100u64);
+            let mut map = ProofMapIndex::new("proof_map", &mut fork);
+            for i in 0..50u8 {
+                map.put(&hash(&[i]), vec![i; i as usize]);
+            }
+            let mut family = MapIndex::new_in_family("family", &3u8, &mut fork);
+            family.put(&"key".to_owned(), 5u64);
+            Entry::new("entry", &mut fork).set(1u16);
+            // An empty index.
+            ListIndex::<_, u8>::new("empty", &mut fork).push(0);
+            ListIndex::<_, u8>::new("empty", &mut fork).clear();
+        }
+        db.merge(fork.into_patch()).unwrap();
+    }
+
+    fn assert_same_contents(expected: &Snapshot, actual: &Snapshot) {
+        let names = ["list", "proof_map", "family", "entry", "empty", INDEXES_METADATA_TABLE_NAME];
+        for name in &names {
+            let (mut expected, mut actual) = (expected.iter(name, &[]), actual.iter(name, &[]));
+            loop {
+                let (x, y) = (expected.next(), actual.next());
+                assert_eq!(x, y);
+                if x.is_none() {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn export_import(source: &Database, target: &Database) {
+        fill_database(source);
+        let bytes = export(&*source.snapshot(), Vec::new()).unwrap();
+        import(target, &bytes[..]).unwrap();
+        assert_same_contents(&*source.snapshot(), &*target.snapshot());
+
+        let (source, target) = (source.snapshot(), target.snapshot());
+        let expected = ProofMapIndex::<_, ::crypto::Hash, Vec<u8>>::new("proof_map", &source);
+        let actual = ProofMapIndex::<_, ::crypto::Hash, Vec<u8>>::new("proof_map", &target);
+        assert_eq!(expected.merkle_root(), actual.merkle_root());
+    }
+
+    #[test]
+    fn test_export_import_memorydb() {
+        export_import(&MemoryDB::new(), &MemoryDB::new());
+    }
+
+    #[test]
+    fn test_export_import_rocksdb() {
+        let dir = TempDir::new("exonum_archive").unwrap();
+        let opts = DbOptions::default();
+        let source = RocksDB::open(dir.path().join("source"), &opts).unwrap();
+        let target = RocksDB::open(dir.path().join("target"), &opts).unwrap();
+        export_import(&source, &target);
+    }
+
+    #[test]
+    fn test_import_into_non_empty_database() {
+        let db = MemoryDB::new();
+        fill_database(&db);
+        let bytes = export(&*db.snapshot(), Vec::new()).unwrap();
+        assert!(check_empty(&*db.snapshot()).is_err());
+        assert!(import(&db, &bytes[..]).is_err());
+    }
+
+    #[test]
+    fn test_export_history() {
+        let db = MemoryDB::new();
+        history::set_retention(&db, Some(1)).unwrap();
+        fill_database(&db);
+
+        let bytes = export(&*db.snapshot(), Vec::new()).unwrap();
+        let target = MemoryDB::new();
+        import(&target, &bytes[..]).unwrap();
+        assert_eq!(history::retention(&*target.snapshot()), Some(1));
+    }
+
+    #[test]
+    fn test_corrupted_archive() {
+        let db = MemoryDB::new();
+        fill_database(&db);
+        let bytes = export(&*db.snapshot(), Vec::new()).unwrap();
+
+        let mut corrupted = bytes.clone();
+        let len = corrupted.len();
+        corrupted[len / 2] ^= 1;
+        assert!(decode(&corrupted[..]).is_err());
+        assert!(decode(&bytes[..len - 1]).is_err());
+        assert!(decode(&bytes[..]).is_ok());
+    }
+}
@@ -16,6 +16,7 @@
 // spell-checker:ignore subprefix
 
 use std::borrow::Cow;
+use std::collections::Bound::{Excluded, Unbounded};
 use std::marker::PhantomData;
 
 use super::{StorageKey, StorageValue, Snapshot, Fork, Iter};
@@ -44,11 +45,14 @@ pub struct BaseIndex<T> {
 
 /// An iterator over the entries of a `BaseIndex`.
 ///
-/// This struct is created by the [`iter`] or
-/// [`iter_from`] methods on [`BaseIndex`]. See its documentation for more.
+/// This struct is created by the [`iter`], [`iter_from`], [`iter_rev`], [`range`] or
+/// [`range_rev`] methods on [`BaseIndex`]. See its documentation for more.
 ///
 /// [`iter`]: struct.BaseIndex.html#method.iter
 /// [`iter_from`]: struct.BaseIndex.html#method.iter_from
+/// [`iter_rev`]: struct.BaseIndex.html#method.iter_rev
+/// [`range`]: struct.BaseIndex.html#method.range
+/// [`range_rev`]: struct.BaseIndex.html#method.range_rev
 /// [`BaseIndex`]: struct.BaseIndex.html
 pub struct BaseIndexIter<'a, K, V> {
     base_iter: Iter<'a>,
@@ -223,6 +227,76 @@ where
             _v: PhantomData,
         }
     }
+
+    /// Returns an iterator over the entries of the index in descending order. The iterator element
+    /// type is *any* key-value pair. An argument `subprefix` allows to specify a subset of keys
+    /// for iteration.
+    pub fn iter_rev<P, K, V>(&self, subprefix: &P) -> BaseIndexIter<K, V>
+    where
+        P: StorageKey,
+        K: StorageKey,
+        V: StorageValue,
+    {
+        let iter_prefix = self.prefixed_key(subprefix);
+        let base_iter = match next_prefix(&iter_prefix) {
+            Some(ref to) => self.view.as_ref().iter_rev(&self.name, Excluded(to)),
+            None => self.view.as_ref().iter_rev(&self.name, Unbounded),
+        };
+        BaseIndexIter {
+            base_iter,
+            base_prefix_len: self.index_id.as_ref().map_or(0, |p| p.len()),
+            index_id: iter_prefix,
+            ended: false,
+            _k: PhantomData,
+            _v: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the entries of the index with the keys in the range `[from, to)`
+    /// in ascending order. The iterator element type is *any* key-value pair. An argument
+    /// `subprefix` allows to specify a subset of iteration.
+    pub fn range<P, F, K, V>(&self, subprefix: &P, from: &F, to: &F) -> BaseIndexIter<K, V>
+    where
+        P: StorageKey,
+        F: StorageKey + ?Sized,
+        K: StorageKey,
+        V: StorageValue,
+    {
+        let iter_prefix = self.prefixed_key(subprefix);
+        let iter_from = self.prefixed_key(from);
+        let iter_to = self.prefixed_key(to);
+        BaseIndexIter {
+            base_iter: self.view.as_ref().range(&self.name, &iter_from, &iter_to),
+            base_prefix_len: self.index_id.as_ref().map_or(0, |p| p.len()),
+            index_id: iter_prefix,
+            ended: false,
+            _k: PhantomData,
+            _v: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the entries of the index with the keys in the range `[from, to)`
+    /// in descending order. The iterator element type is *any* key-value pair. An argument
+    /// `subprefix` allows to specify a subset of iteration.
+    pub fn range_rev<P, F, K, V>(&self, subprefix: &P, from: &F, to: &F) -> BaseIndexIter<K, V>
+    where
+        P: StorageKey,
+        F: StorageKey + ?Sized,
+        K: StorageKey,
+        V: StorageValue,
+    {
+        let iter_prefix = self.prefixed_key(subprefix);
+        let iter_from = self.prefixed_key(from);
+        let iter_to = self.prefixed_key(to);
+        BaseIndexIter {
+            base_iter: self.view.as_ref().range_rev(&self.name, &iter_from, &iter_to),
+            base_prefix_len: self.index_id.as_ref().map_or(0, |p| p.len()),
+            index_id: iter_prefix,
+            ended: false,
+            _k: PhantomData,
+            _v: PhantomData,
+        }
+    }
 }
 
 impl<'a> BaseIndex<&'a mut Fork> {
@@ -306,6 +380,15 @@ impl<'a, K, V> ::std::fmt::Debug for BaseIndexIter<'a, K, V> {
     }
 }
 
+/// Returns the least key that is greater than all keys starting with the specified prefix,
+/// or `None` if there is no such key.
+fn next_prefix(prefix: &[u8]) -> Option<Vec<u8>> {
+    let position = prefix.iter().rposition(|&byte| byte < u8::max_value())?;
+    let mut next = prefix[..position + 1].to_vec();
+    next[position] += 1;
+    Some(next)
+}
+
 /// A function that validates an index name. Allowable characters in name: ASCII characters, digits
 /// and underscores.
 fn is_valid_name<S: AsRef<str>>(name: S) -> bool {
@@ -316,7 +399,7 @@ fn is_valid_name<S: AsRef<str>>(name: S) -> bool {
 }
 
 /// Calls the `is_valid_name` function with the given name and panics if it returns `false`.
-fn assert_valid_name<S: AsRef<str>>(name: S) {
+pub(crate) fn assert_valid_name<S: AsRef<str>>(name: S) {
     if !is_valid_name(name) {
         panic!("Wrong characters using in name. Use: a-zA-Z0-9 and _");
     }
@@ -344,6 +427,14 @@ mod tests {
         assert!(!is_valid_name("1in!dex_Namez"));
     }
 
+    #[test]
+    fn test_next_prefix() {
+        assert_eq!(next_prefix(&[]), None);
+        assert_eq!(next_prefix(&[1, 2]), Some(vec![1, 3]));
+        assert_eq!(next_prefix(&[1, 255]), Some(vec![2]));
+        assert_eq!(next_prefix(&[255, 255]), None);
+    }
+
     #[test]
     fn check_valid_name() {
         assert_valid_name("valid_name");
@@ -0,0 +1,381 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A read-through cache on top of another database.
+
+use std::collections::{BTreeMap, HashMap};
+use std::collections::Bound;
+use std::fmt;
+use std::sync::{Arc, Mutex, RwLock};
+
+use super::{Database, Snapshot, Patch, Iter, Result, StorageStats};
+
+/// Statistics of the lookups in a `CachedDB`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Number of lookups answered from the cache.
+    pub hits: u64,
+    /// Number of lookups passed to the underlying database.
+    pub misses: u64,
+}
+
+/// A value stored in the cache together with the time of the last access to it.
+#[derive(Debug)]
+struct CacheEntry {
+    value: Option<Vec<u8>>,
+    tick: u64,
+}
+
+impl CacheEntry {
+    fn size(&self, key: &[u8]) -> usize {
+        key.len() + self.value.as_ref().map_or(0, Vec::len)
+    }
+}
+
+/// Bounded LRU cache of the database entries keyed by the index name and the key.
+#[derive(Debug)]
+struct Cache {
+    capacity: usize,
+    size: usize,
+    tick: u64,
+    // The cached values are only valid for the snapshots of this generation.
+    generation: u64,
+    entries: HashMap<String, HashMap<Vec<u8>, CacheEntry>>,
+    // Keys of the entries ordered by the time of the last access.
+    order: BTreeMap<u64, (String, Vec<u8>)>,
+    stats: CacheStats,
+}
+
+impl Cache {
+    fn new(capacity: usize) -> Self {
+        Cache {
+            capacity,
+            size: 0,
+            tick: 0,
+            generation: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            stats: CacheStats::default(),
+        }
+    }
+
+    fn get(&mut self, name: &str, key: &[u8]) -> Option<Option<Vec<u8>>> {
+        self.tick += 1;
+        let tick = self.tick;
+        let entry = self.entries.get_mut(name).and_then(|table| table.get_mut(key))?;
+        let full_key = self.order.remove(&entry.tick).unwrap();
+        self.order.insert(tick, full_key);
+        entry.tick = tick;
+        Some(entry.value.clone())
+    }
+
+    fn insert(&mut self, name: &str, key: &[u8], value: Option<Vec<u8>>) {
+        self.remove(name, key);
+        self.tick += 1;
+        let entry = CacheEntry {
+            value,
+            tick: self.tick,
+        };
+        let size = entry.size(key) + name.len();
+        if size > self.capacity {
+            return;
+        }
+        while self.size + size > self.capacity {
+            self.evict();
+        }
+
+        self.size += size;
+        self.order.insert(self.tick, (name.to_owned(), key.to_vec()));
+        self.entries
+            .entry(name.to_owned())
+            .or_insert_with(HashMap::new)
+            .insert(key.to_vec(), entry);
+    }
+
+    fn remove(&mut self, name: &str, key: &[u8]) {
+        let removed = match self.entries.get_mut(name) {
+            Some(table) => table.remove(key),
+            None => None,
+        };
+        if let Some(entry) = removed {
+            self.size -= entry.size(key) + name.len();
+            self.order.remove(&entry.tick);
+        }
+    }
+
+    fn evict(&mut self) {
+        let oldest = self.order.keys().next().cloned();
+        if let Some(tick) = oldest {
+            let (name, key) = self.order.remove(&tick).unwrap();
+            self.remove(&name, &key);
+        }
+    }
+
+    fn invalidate(&mut self, patch: &Patch) {
+        for (name, changes) in patch.iter() {
+            for (key, _) in changes.iter() {
+                self.remove(name, key);
+            }
+        }
+        self.generation += 1;
+    }
+}
+
+/// Database wrapper that caches the values read from the underlying database.
+///
+/// The cache is shared among all snapshots of the database. It is bounded by the total size
+/// of the cached keys and values, and the least recently used entries are evicted first.
+/// Both present and absent values are cached. Iterators are not cached and always read
+/// from the underlying database.
+///
+/// Merging a patch removes all the changed entries from the cache. The snapshots taken
+/// before the merge keep reading the data they were created with, but bypass the cache.
+///
+/// The cache is enabled for the nodes with the [`cache_size`] database option.
+///
+/// # Examples
+///
+/// ```
+/// use exonum::storage::{CachedDB, Database, MapIndex, MemoryDB};
+///
+/// let db = CachedDB::new(Box::new(MemoryDB::new()), 1024 * 1024);
+/// let mut fork = db.fork();
+/// MapIndex::new("index", &mut fork).put(&1_u64, 2_u64);
+/// db.merge(fork.into_patch()).unwrap();
+///
+/// let snapshot = db.snapshot();
+/// let index: MapIndex<_, u64, u64> = MapIndex::new("index", &snapshot);
+/// assert_eq!(index.get(&1), Some(2));
+/// let hits = db.stats().hits;
+/// // The repeated lookup is answered from the cache.
+/// assert_eq!(index.get(&1), Some(2));
+/// assert_eq!(db.stats().hits, hits + 1);
+/// ```
+///
+/// [`cache_size`]: struct.DbOptions.html#structfield.cache_size
+pub struct CachedDB {
+    db: Box<Database>,
+    cache: Arc<Mutex<Cache>>,
+    // Prevents taking snapshots while a patch is being merged.
+    merge_lock: RwLock<()>,
+}
+
+/// A snapshot of a `CachedDB`.
+struct CachedSnapshot {
+    snapshot: Box<Snapshot>,
+    generation: u64,
+    cache: Arc<Mutex<Cache>>,
+}
+
+impl CachedDB {
+    /// Creates a cache of the given capacity in bytes over the specified database.
+    pub fn new(db: Box<Database>, capacity: usize) -> Self {
+        CachedDB {
+            db,
+            cache: Arc::new(Mutex::new(Cache::new(capacity))),
+            merge_lock: RwLock::new(()),
+        }
+    }
+
+    /// Returns the numbers of the cache hits and misses since the creation of the database.
+    pub fn stats(&self) -> CacheStats {
+        self.cache.lock().unwrap().stats
+    }
+
+    fn do_merge(&self, patch: Patch, sync: bool) -> Result<()> {
+        let _guard = self.merge_lock.write().unwrap();
+        // The entries are invalidated before the merge, so the snapshots of the old generation
+        // cannot put outdated values into the cache.
+        let stats = {
+            let mut cache = self.cache.lock().unwrap();
+            cache.invalidate(&patch);
+            cache.stats
+        };
+        metric!("storage.cache_hits", stats.hits);
+        metric!("storage.cache_misses", stats.misses);
+        if sync {
+            self.db.merge_sync(patch)
+        } else {
+            self.db.merge(patch)
+        }
+    }
+}
+
+impl Database for CachedDB {
+    fn snapshot(&self) -> Box<Snapshot> {
+        let _guard = self.merge_lock.read().unwrap();
+        let generation = self.cache.lock().unwrap().generation;
+        Box::new(CachedSnapshot {
+            snapshot: self.db.snapshot(),
+            generation,
+            cache: Arc::clone(&self.cache),
+        })
+    }
+
+    fn merge(&self, patch: Patch) -> Result<()> {
+        self.do_merge(patch, false)
+    }
+
+    fn merge_sync(&self, patch: Patch) -> Result<()> {
+        self.do_merge(patch, true)
+    }
+
+    fn storage_stats(&self) -> Option<StorageStats> {
+        self.db.storage_stats()
+    }
+}
+
+impl Snapshot for CachedSnapshot {
+    fn get(&self, name: &str, key: &[u8]) -> Option<Vec<u8>> {
+        {
+            let mut cache = self.cache.lock().unwrap();
+            if cache.generation == self.generation {
+                if let Some(value) = cache.get(name, key) {
+                    cache.stats.hits += 1;
+                    return value;
+                }
+            }
+            cache.stats.misses += 1;
+        }
+
+        let value = self.snapshot.get(name, key);
+        let mut cache = self.cache.lock().unwrap();
+        if cache.generation == self.generation {
+            cache.insert(name, key, value.clone());
+        }
+        value
+    }
+
+    fn iter<'a>(&'a self, name: &str, from: &[u8]) -> Iter<'a> {
+        self.snapshot.iter(name, from)
+    }
+
+    fn iter_rev<'a>(&'a self, name: &str, to: Bound<&[u8]>) -> Iter<'a> {
+        self.snapshot.iter_rev(name, to)
+    }
+
+    fn range<'a>(&'a self, name: &str, from: &[u8], to: &[u8]) -> Iter<'a> {
+        self.snapshot.range(name, from, to)
+    }
+
+    fn range_rev<'a>(&'a self, name: &str, from: &[u8], to: &[u8]) -> Iter<'a> {
+        self.snapshot.range_rev(name, from, to)
+    }
+}
+
+impl From<CachedDB> for Arc<Database> {
+    fn from(db: CachedDB) -> Arc<Database> {
+        Arc::from(Box::new(db) as Box<Database>)
+    }
+}
+
+impl fmt::Debug for CachedDB {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CachedDB")
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl fmt::Debug for CachedSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CachedSnapshot(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use storage::{Database, MemoryDB, Snapshot};
+    use super::{CachedDB, CacheStats};
+
+    fn put(db: &Database, name: &str, key: u8, value: Option<u8>) {
+        let mut fork = db.fork();
+        match value {
+            Some(value) => fork.put(name, vec![key], vec![value]),
+            None => fork.remove(name, vec![key]),
+        }
+        db.merge(fork.into_patch()).unwrap();
+    }
+
+    fn get(snapshot: &Snapshot, name: &str, key: u8) -> Option<u8> {
+        snapshot.get(name, &[key]).map(|value| value[0])
+    }
+
+    #[test]
+    fn test_cache_hits_and_misses() {
+        let db = CachedDB::new(Box::new(MemoryDB::new()), 1024);
+        put(&db, "a", 1, Some(1));
+
+        let snapshot = db.snapshot();
+        assert_eq!(get(&*snapshot, "a", 1), Some(1));
+        assert_eq!(get(&*snapshot, "a", 1), Some(1));
+        assert_eq!(get(&*snapshot, "a", 2), None);
+        assert_eq!(get(&*snapshot, "a", 2), None);
+        assert_eq!(get(&*snapshot, "b", 1), None);
+        assert!(snapshot.contains("a", &[1]));
+        assert_eq!(db.stats(), CacheStats { hits: 3, misses: 3 });
+    }
+
+    #[test]
+    fn test_cache_invalidation() {
+        let db = CachedDB::new(Box::new(MemoryDB::new()), 1024);
+        put(&db, "a", 1, Some(1));
+        put(&db, "a", 2, Some(2));
+
+        let old_snapshot = db.snapshot();
+        assert_eq!(get(&*old_snapshot, "a", 1), Some(1));
+        assert_eq!(get(&*old_snapshot, "a", 2), Some(2));
+        assert_eq!(get(&*old_snapshot, "a", 3), None);
+
+        put(&db, "a", 1, Some(10));
+        put(&db, "a", 3, Some(3));
+        // Old snapshots bypass the cache and still observe the old state.
+        assert_eq!(get(&*old_snapshot, "a", 1), Some(1));
+        assert_eq!(get(&*old_snapshot, "a", 3), None);
+
+        let snapshot = db.snapshot();
+        assert_eq!(get(&*snapshot, "a", 1), Some(10));
+        assert_eq!(get(&*snapshot, "a", 3), Some(3));
+        let hits = db.stats().hits;
+        assert_eq!(get(&*snapshot, "a", 2), Some(2));
+        assert_eq!(db.stats().hits, hits + 1);
+
+        put(&db, "a", 2, None);
+        assert_eq!(get(&*db.snapshot(), "a", 2), None);
+    }
+
+    #[test]
+    fn test_cache_eviction() {
+        // Each entry takes 3 bytes: 1 for the name, 1 for the key and 1 for the value.
+        let db = CachedDB::new(Box::new(MemoryDB::new()), 9);
+        for key in 0..4 {
+            put(&db, "a", key, Some(key));
+        }
+
+        let snapshot = db.snapshot();
+        for key in 0..3 {
+            assert_eq!(get(&*snapshot, "a", key), Some(key));
+        }
+        // Refreshes the entry for the key 0, so the key 1 is the least recently used one.
+        assert_eq!(get(&*snapshot, "a", 0), Some(0));
+        assert_eq!(get(&*snapshot, "a", 3), Some(3));
+        assert_eq!(db.stats(), CacheStats { hits: 1, misses: 4 });
+
+        assert_eq!(get(&*snapshot, "a", 0), Some(0));
+        assert_eq!(get(&*snapshot, "a", 2), Some(2));
+        assert_eq!(get(&*snapshot, "a", 3), Some(3));
+        assert_eq!(get(&*snapshot, "a", 1), Some(1));
+        assert_eq!(db.stats(), CacheStats { hits: 4, misses: 5 });
+    }
+}
@@ -12,14 +12,23 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cell::Cell;
 use std::collections::HashMap;
-use std::collections::btree_map::{BTreeMap, Range, Iter as BtmIter, IntoIter as BtmIntoIter};
+use std::collections::btree_map::{BTreeMap, Iter as BtmIter, IntoIter as BtmIntoIter};
 use std::collections::hash_map::{Iter as HmIter, IntoIter as HmIntoIter, Entry as HmEntry};
+use std::collections::Bound;
 use std::collections::Bound::*;
 use std::cmp::Ordering::*;
 use std::iter::{Peekable, Iterator as StdIterator};
+use std::io;
+use std::panic::{self, AssertUnwindSafe, RefUnwindSafe, UnwindSafe};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use super::Result;
+use super::base_index::assert_valid_name;
+use super::indexes_metadata;
+use super::stats::StorageStats;
 use self::NextIterValue::*;
 
 /// Map containing changes with corresponding key.
@@ -71,7 +80,7 @@ pub struct Patch {
 
 impl Patch {
     /// Creates a new empty `Patch` instance.
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Self { changes: HashMap::new() }
     }
 
@@ -95,6 +104,14 @@ impl Patch {
         self.changes.insert(name, changes);
     }
 
+    /// Inserts a single change for the given key into the changes with the given name.
+    pub(crate) fn insert_change(&mut self, name: String, key: Vec<u8>, change: Change) {
+        self.changes_entry(name)
+            .or_insert_with(Changes::new)
+            .data
+            .insert(key, change);
+    }
+
     /// Returns iterator over changes.
     pub fn iter(&self) -> HmIter<String, Changes> {
         self.changes.iter()
@@ -163,7 +180,17 @@ pub enum Change {
 ///
 /// `Fork` also supports checkpoints ([`checkpoint`], [`commit`] and
 /// [`rollback`] methods), which allows to rollback some of the latest changes (e.g., after
-/// a runtime error).
+/// a runtime error). Checkpoints can be nested: each call to `checkpoint` returns
+/// a [`Savepoint`], and the changes made after it can be discarded with [`rollback_to`]
+/// or kept with [`release`] without affecting the enclosing checkpoints.
+///
+/// Whole indices can be removed with [`drop_index`] or moved under another name
+/// with [`rename_index`]; both methods keep the indexes metadata consistent.
+///
+/// `Fork` is unwind safe: each change is recorded in the log of the active checkpoints
+/// together with its application, so a panic in the middle of an index operation cannot
+/// leave changes that a rollback misses. [`catch_unwind`] uses this to run a closure that
+/// may panic and to discard all of its changes in case of a panic.
 ///
 /// `Fork` implements the [`Snapshot`] trait and provides methods for both reading and
 /// writing data. Thus, `&mut Fork` is used as a storage view for creating
@@ -182,17 +209,121 @@ pub enum Change {
 /// [`checkpoint`]: #method.checkpoint
 /// [`commit`]: #method.commit
 /// [`rollback`]: #method.rollback
-// FIXME: make &mut Fork "unwind safe" (ECR-176)
+/// [`Savepoint`]: struct.Savepoint.html
+/// [`rollback_to`]: #method.rollback_to
+/// [`release`]: #method.release
+/// [`drop_index`]: #method.drop_index
+/// [`rename_index`]: #method.rename_index
+/// [`catch_unwind`]: #method.catch_unwind
 pub struct Fork {
     snapshot: Box<Snapshot>,
     patch: Patch,
     changelog: Vec<(String, Vec<u8>, Option<Change>)>,
-    logged: bool,
+    savepoints: Vec<(Savepoint, usize)>,
+    next_savepoint: u64,
+    budget: Budget,
+}
+
+// Limits of the storage operations performed with a fork, see `Fork::set_operations_limit`
+// and `Fork::set_time_limit`.
+#[derive(Debug, Default)]
+struct Budget {
+    operations_left: Cell<Option<u64>>,
+    deadline: Option<Instant>,
 }
 
+/// An identifier of a checkpoint in a `Fork`.
+///
+/// Savepoints are created by the [`checkpoint`] method and are consumed either by
+/// [`rollback_to`] or by [`release`]. Each savepoint is unique within its fork, so a savepoint
+/// that is already rolled back or released cannot be confused with a newer one.
+///
+/// [`checkpoint`]: struct.Fork.html#method.checkpoint
+/// [`rollback_to`]: struct.Fork.html#method.rollback_to
+/// [`release`]: struct.Fork.html#method.release
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Savepoint(u64);
+
+// The snapshot is only read, and the budget of the operations does not affect the data.
+impl UnwindSafe for Fork {}
+impl RefUnwindSafe for Fork {}
+
+/// Panic payload, with which a `Fork` unwinds when its limit of the storage operations
+/// is exhausted (see [`set_operations_limit`]).
+///
+/// [`set_operations_limit`]: struct.Fork.html#method.set_operations_limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OperationsLimitExceeded;
+
+/// Panic payload, with which a `Fork` unwinds when its time limit of the storage operations
+/// is exceeded (see [`set_time_limit`]).
+///
+/// [`set_time_limit`]: struct.Fork.html#method.set_time_limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeLimitExceeded;
+
+#[cfg(test)]
+thread_local! {
+    // Number of the changes to forks after which a panic is injected.
+    pub(crate) static PANIC_AFTER_CHANGES: Cell<Option<usize>> = Cell::new(None);
+}
+
+// Panics if the limit of the changes set in the tests is reached.
+#[cfg(test)]
+fn inject_panic() {
+    PANIC_AFTER_CHANGES.with(|changes| match changes.get() {
+        Some(0) => {
+            changes.set(None);
+            panic!("Injected panic");
+        }
+        Some(left) => changes.set(Some(left - 1)),
+        None => {}
+    });
+}
+
+#[cfg(not(test))]
+#[inline]
+fn inject_panic() {}
+
+// Applies the change to the key or, if the change is `None`, discards the change of the key.
+// If the log is given, the replaced change is recorded in it. Everything that can fail is
+// done before the change is applied, so the change and its log entry are always recorded
+// together.
+fn apply_logged(
+    changes: &mut Changes,
+    changelog: Option<&mut Vec<(String, Vec<u8>, Option<Change>)>>,
+    name: &str,
+    key: Vec<u8>,
+    change: Option<Change>,
+) {
+    inject_panic();
+    let log_entry = changelog.map(|changelog| {
+        changelog.reserve(1);
+        (changelog, name.to_string(), key.clone())
+    });
+    let old_change = match change {
+        Some(change) => changes.data.insert(key, change),
+        None => changes.data.remove(&key),
+    };
+    if let Some((changelog, name, key)) = log_entry {
+        changelog.push((name, key, old_change));
+    }
+}
+
+type ChangesRange<'a> = Peekable<Box<StdIterator<Item = (&'a Vec<u8>, &'a Change)> + 'a>>;
+
 struct ForkIter<'a> {
     snapshot: Iter<'a>,
-    changes: Option<Peekable<Range<'a, Vec<u8>, Change>>>,
+    changes: Option<ChangesRange<'a>>,
+    reverse: bool,
+    budget: &'a Budget,
+}
+
+/// An iterator that stops the underlying iterator at the specified bound.
+struct RangeIter<'a> {
+    iter: Iter<'a>,
+    bound: Vec<u8>,
+    reverse: bool,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -242,7 +373,9 @@ pub trait Database: Send + Sync + 'static {
             snapshot: self.snapshot(),
             patch: Patch::new(),
             changelog: Vec::new(),
-            logged: false,
+            savepoints: Vec::new(),
+            next_savepoint: 0,
+            budget: Budget::default(),
         }
     }
 
@@ -269,6 +402,28 @@ pub trait Database: Send + Sync + 'static {
     /// will be returned. In case of an error the method guarantees no changes were applied to
     /// the database.
     fn merge_sync(&self, patch: Patch) -> Result<()>;
+
+    /// Decodes a patch from the portable binary format and atomically applies it
+    /// to the database. See [`PatchReader`] for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the patch is malformed or its checksum does not match;
+    /// no changes are applied to the database in this case.
+    ///
+    /// [`PatchReader`]: struct.PatchReader.html
+    fn merge_encoded(&self, reader: &mut io::Read) -> Result<()> {
+        let patch = Patch::decode(reader)?;
+        self.merge(patch)
+    }
+
+    /// Returns the statistics of the storage operations per index, or `None` if
+    /// the database does not collect them. See [`MeteredDB`] for details.
+    ///
+    /// [`MeteredDB`]: struct.MeteredDB.html
+    fn storage_stats(&self) -> Option<StorageStats> {
+        None
+    }
 }
 
 /// A read-only snapshot of a storage backend.
@@ -294,6 +449,37 @@ pub trait Snapshot: 'static {
     /// Returns an iterator over the entries of the snapshot in ascending order starting from
     /// the specified key. The iterator element type is `(&[u8], &[u8])`.
     fn iter<'a>(&'a self, name: &str, from: &[u8]) -> Iter<'a>;
+
+    /// Returns an iterator over the entries of the snapshot in descending order starting from
+    /// the specified upper bound, which can be inclusive, exclusive or absent (in which case
+    /// the iteration starts from the last entry). The iterator element type is `(&[u8], &[u8])`.
+    fn iter_rev<'a>(&'a self, name: &str, to: Bound<&[u8]>) -> Iter<'a>;
+
+    /// Returns an iterator over the entries of the snapshot with the keys in the range
+    /// `[from, to)` in ascending order. The iterator element type is `(&[u8], &[u8])`.
+    ///
+    /// Default implementation stops the iterator returned by [`iter`](#tymethod.iter)
+    /// at the upper bound.
+    fn range<'a>(&'a self, name: &str, from: &[u8], to: &[u8]) -> Iter<'a> {
+        Box::new(RangeIter {
+            iter: self.iter(name, from),
+            bound: to.to_vec(),
+            reverse: false,
+        })
+    }
+
+    /// Returns an iterator over the entries of the snapshot with the keys in the range
+    /// `[from, to)` in descending order. The iterator element type is `(&[u8], &[u8])`.
+    ///
+    /// Default implementation stops the iterator returned by [`iter_rev`](#tymethod.iter_rev)
+    /// at the lower bound.
+    fn range_rev<'a>(&'a self, name: &str, from: &[u8], to: &[u8]) -> Iter<'a> {
+        Box::new(RangeIter {
+            iter: self.iter_rev(name, Excluded(to)),
+            bound: from.to_vec(),
+            reverse: true,
+        })
+    }
 }
 
 /// A trait that defines streaming iterator over storage view entries.
@@ -307,6 +493,7 @@ pub trait Iterator {
 
 impl Snapshot for Fork {
     fn get(&self, name: &str, key: &[u8]) -> Option<Vec<u8>> {
+        self.spend_operation();
         if let Some(changes) = self.patch.changes(name) {
             if let Some(change) = changes.data.get(key) {
                 match *change {
@@ -319,6 +506,7 @@ impl Snapshot for Fork {
     }
 
     fn contains(&self, name: &str, key: &[u8]) -> bool {
+        self.spend_operation();
         if let Some(changes) = self.patch.changes(name) {
             if let Some(change) = changes.data.get(key) {
                 match *change {
@@ -331,57 +519,134 @@ impl Snapshot for Fork {
     }
 
     fn iter<'a>(&'a self, name: &str, from: &[u8]) -> Iter<'a> {
+        self.spend_operation();
         let range = (Included(from), Unbounded);
         let changes = match self.patch.changes(name) {
-            Some(changes) => Some(changes.data.range::<[u8], _>(range).peekable()),
+            Some(changes) => {
+                let range: Box<StdIterator<Item = _>> =
+                    Box::new(changes.data.range::<[u8], _>(range));
+                Some(range.peekable())
+            }
             None => None,
         };
 
         Box::new(ForkIter {
             snapshot: self.snapshot.iter(name, from),
             changes,
+            reverse: false,
+            budget: &self.budget,
+        })
+    }
+
+    fn iter_rev<'a>(&'a self, name: &str, to: Bound<&[u8]>) -> Iter<'a> {
+        self.spend_operation();
+        let range = (Unbounded, to);
+        let changes = match self.patch.changes(name) {
+            Some(changes) => {
+                let range: Box<StdIterator<Item = _>> =
+                    Box::new(changes.data.range::<[u8], _>(range).rev());
+                Some(range.peekable())
+            }
+            None => None,
+        };
+
+        Box::new(ForkIter {
+            snapshot: self.snapshot.iter_rev(name, to),
+            changes,
+            reverse: true,
+            budget: &self.budget,
         })
     }
 }
 
 impl Fork {
-    /// Creates a new checkpoint.
+    /// Creates a new checkpoint and returns the corresponding savepoint.
     ///
-    /// # Panics
+    /// Checkpoints can be nested; changes made after the checkpoint can be discarded
+    /// with [`rollback_to`] or kept with [`release`].
     ///
-    /// Panics if another checkpoint was created before and has not been committed or rolled back.
-    pub fn checkpoint(&mut self) {
-        if self.logged {
-            panic!("call checkpoint before rollback or commit");
-        }
-        self.logged = true;
+    /// [`rollback_to`]: #method.rollback_to
+    /// [`release`]: #method.release
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{Database, MemoryDB, Snapshot};
+    ///
+    /// let db = MemoryDB::new();
+    /// let mut fork = db.fork();
+    /// let outer = fork.checkpoint();
+    /// fork.put("index_name", vec![1], vec![1]);
+    /// let inner = fork.checkpoint();
+    /// fork.put("index_name", vec![2], vec![2]);
+    ///
+    /// fork.rollback_to(inner);
+    /// assert!(fork.contains("index_name", &[1]));
+    /// assert!(!fork.contains("index_name", &[2]));
+    /// fork.release(outer);
+    /// ```
+    pub fn checkpoint(&mut self) -> Savepoint {
+        let savepoint = Savepoint(self.next_savepoint);
+        self.next_savepoint += 1;
+        self.savepoints.push((savepoint, self.changelog.len()));
+        savepoint
     }
 
     /// Finalizes all changes after the latest checkpoint.
     ///
+    /// The changes become a part of the enclosing checkpoint, if any.
+    ///
     /// # Panics
     ///
-    /// Panics if there is no active checkpoint, or the latest checkpoint
-    /// is already committed or rolled back.
+    /// Panics if there is no active checkpoint.
     pub fn commit(&mut self) {
-        if !self.logged {
-            panic!("call commit before checkpoint");
+        match self.savepoints.last() {
+            Some(&(savepoint, _)) => self.release(savepoint),
+            None => panic!("call commit before checkpoint"),
         }
-        self.changelog.clear();
-        self.logged = false;
     }
 
     /// Rolls back all changes after the latest checkpoint.
     ///
     /// # Panics
     ///
-    /// Panics if there is no active checkpoint, or the latest checkpoint
-    /// is already committed or rolled back.
+    /// Panics if there is no active checkpoint.
     pub fn rollback(&mut self) {
-        if !self.logged {
-            panic!("call rollback before checkpoint");
+        match self.savepoints.last() {
+            Some(&(savepoint, _)) => self.rollback_to(savepoint),
+            None => panic!("call rollback before checkpoint"),
+        }
+    }
+
+    /// Finalizes all changes after the specified savepoint. The savepoint and all
+    /// the savepoints created after it are removed.
+    ///
+    /// The changes become a part of the enclosing checkpoint, if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the savepoint is already rolled back or released.
+    pub fn release(&mut self, savepoint: Savepoint) {
+        let depth = self.savepoint_depth(savepoint);
+        self.savepoints.truncate(depth);
+        if self.savepoints.is_empty() {
+            self.changelog.clear();
         }
-        for (name, k, c) in self.changelog.drain(..).rev() {
+    }
+
+    /// Rolls back all changes after the specified savepoint. The savepoint and all
+    /// the savepoints created after it are removed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the savepoint is already rolled back or released.
+    pub fn rollback_to(&mut self, savepoint: Savepoint) {
+        let depth = self.savepoint_depth(savepoint);
+        let position = self.savepoints[depth].1;
+        // The savepoints are removed after all the changes are reverted, so an interrupted
+        // rollback can be repeated.
+        while self.changelog.len() > position {
+            let (name, k, c) = self.changelog.pop().unwrap();
             if let Some(changes) = self.patch.changes_mut(&name) {
                 match c {
                     Some(change) => changes.data.insert(k, change),
@@ -389,60 +654,111 @@ impl Fork {
                 };
             }
         }
-        self.logged = false;
+        self.savepoints.truncate(depth);
+    }
+
+    /// Calls the closure with the fork and returns its result. If the closure panics,
+    /// all the changes it has made to the fork, including the ones in its own unfinished
+    /// checkpoints, are rolled back and the panic payload is returned as an error.
+    ///
+    /// The closure is not required to be [`UnwindSafe`]: the changes made to the fork are
+    /// rolled back, and the consistency of any other state the closure modifies (for example,
+    /// through interior mutability) is the responsibility of the caller. This is the boundary
+    /// at which the panics of transactions and service hooks are caught.
+    ///
+    /// [`UnwindSafe`]: https://doc.rust-lang.org/std/panic/trait.UnwindSafe.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{Database, MemoryDB, Snapshot};
+    ///
+    /// let db = MemoryDB::new();
+    /// let mut fork = db.fork();
+    /// fork.put("index_name", vec![1], vec![1]);
+    /// let result = fork.catch_unwind(|fork| {
+    ///     fork.put("index_name", vec![2], vec![2]);
+    ///     panic!("Oops");
+    /// });
+    ///
+    /// assert!(result.is_err());
+    /// assert!(fork.contains("index_name", &[1]));
+    /// assert!(!fork.contains("index_name", &[2]));
+    /// ```
+    pub fn catch_unwind<F, R>(&mut self, f: F) -> thread::Result<R>
+    where
+        F: FnOnce(&mut Fork) -> R,
+    {
+        let savepoint = self.checkpoint();
+        // Neither `&mut Fork` nor the closure are required to be `UnwindSafe`, since
+        // the changes made before the panic are rolled back below.
+        let result = {
+            let fork = &mut *self;
+            panic::catch_unwind(AssertUnwindSafe(move || f(fork)))
+        };
+        match result {
+            Ok(_) => self.release(savepoint),
+            Err(_) => self.rollback_to(savepoint),
+        }
+        result
+    }
+
+    fn savepoint_depth(&self, savepoint: Savepoint) -> usize {
+        match self.savepoints.iter().position(|&(s, _)| s == savepoint) {
+            Some(depth) => depth,
+            None => panic!("savepoint {:?} is already rolled back or released", savepoint),
+        }
     }
 
     /// Inserts a key-value pair into the fork.
     pub fn put(&mut self, name: &str, key: Vec<u8>, value: Vec<u8>) {
-        let changes = self.patch.changes_entry(name.to_string()).or_insert_with(
-            Changes::new,
-        );
-        if self.logged {
-            self.changelog.push((
-                name.to_string(),
-                key.clone(),
-                changes.data.insert(key, Change::Put(value)),
-            ));
-        } else {
-            changes.data.insert(key, Change::Put(value));
-        }
+        self.apply_change(name, key, Change::Put(value));
     }
 
     /// Removes the key from the fork.
     pub fn remove(&mut self, name: &str, key: Vec<u8>) {
+        self.apply_change(name, key, Change::Delete);
+    }
+
+    fn apply_change(&mut self, name: &str, key: Vec<u8>, change: Change) {
+        self.spend_operation();
         let changes = self.patch.changes_entry(name.to_string()).or_insert_with(
             Changes::new,
         );
-        if self.logged {
-            self.changelog.push((
-                name.to_string(),
-                key.clone(),
-                changes.data.insert(key, Change::Delete),
-            ));
+        let changelog = if self.savepoints.is_empty() {
+            None
         } else {
-            changes.data.insert(key, Change::Delete);
-        }
+            Some(&mut self.changelog)
+        };
+        apply_logged(changes, changelog, name, key, Some(change));
     }
 
     /// Removes all keys starting with the specified prefix from the column family
     /// with the given `name`.
     pub fn remove_by_prefix(&mut self, name: &str, prefix: Option<&Vec<u8>>) {
+        self.spend_operation();
         let changes = self.patch.changes_entry(name.to_string()).or_insert_with(
             Changes::new,
         );
+        let logged = !self.savepoints.is_empty();
+        let changelog = &mut self.changelog;
+        let budget = &self.budget;
         // Remove changes
-        if let Some(prefix) = prefix {
-            let keys = changes
-                .data
-                .range::<Vec<u8>, _>((Included(prefix), Unbounded))
-                .map(|(k, _)| k.to_vec())
-                .take_while(|k| k.starts_with(prefix))
-                .collect::<Vec<_>>();
-            for k in keys {
-                changes.data.remove(&k);
+        let keys = match prefix {
+            Some(prefix) => {
+                changes
+                    .data
+                    .range::<Vec<u8>, _>((Included(prefix), Unbounded))
+                    .map(|(k, _)| k.to_vec())
+                    .take_while(|k| k.starts_with(prefix))
+                    .collect::<Vec<_>>()
             }
-        } else {
-            changes.data.clear();
+            None => changes.data.keys().cloned().collect::<Vec<_>>(),
+        };
+        for k in keys {
+            budget.spend();
+            let changelog = if logged { Some(&mut *changelog) } else { None };
+            apply_logged(changes, changelog, name, k, None);
         }
         // Remove from storage
         let mut iter = self.snapshot.iter(
@@ -450,11 +766,109 @@ impl Fork {
             prefix.map_or(&[], |k| k.as_slice()),
         );
         while let Some((k, ..)) = iter.next() {
-            let change = changes.data.insert(k.to_vec(), Change::Delete);
-            if self.logged {
-                self.changelog.push((name.to_string(), k.to_vec(), change));
+            budget.spend();
+            let changelog = if logged { Some(&mut *changelog) } else { None };
+            apply_logged(changes, changelog, name, k.to_vec(), Some(Change::Delete));
+        }
+    }
+
+    /// Removes all the data of the index with the given name together with its record
+    /// in the indexes metadata, so the name can be reused for an index of another type.
+    /// If the name belongs to an index family, all the indexes of the family are removed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` refers to an internal storage table.
+    pub fn drop_index(&mut self, name: &str) {
+        indexes_metadata::assert_not_internal(name);
+        self.remove_by_prefix(name, None);
+        indexes_metadata::remove_index_type(name, self);
+    }
+
+    /// Moves all the data of the index with the given name to the index with the new name
+    /// together with its record in the indexes metadata. If the name belongs to an index
+    /// family, all the indexes of the family are moved to the new family.
+    ///
+    /// All the entries of the index are loaded into memory during the renaming.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either name refers to an internal storage table, if the new name
+    /// contains invalid characters, if the index with the old name does not exist
+    /// or if the index with the new name already exists.
+    pub fn rename_index(&mut self, old_name: &str, new_name: &str) {
+        indexes_metadata::assert_not_internal(old_name);
+        indexes_metadata::assert_not_internal(new_name);
+        assert_valid_name(new_name);
+        let exists = |fork: &Fork, name: &str| {
+            fork.iter(name, &[]).next().is_some() || indexes_metadata::is_registered(name, fork)
+        };
+        if !exists(self, old_name) {
+            panic!("Attempt to rename a non-existing index '{}'", old_name);
+        }
+        if exists(self, new_name) {
+            panic!("Attempt to rename index '{}' to an existing index '{}'", old_name, new_name);
+        }
+
+        let mut entries = Vec::new();
+        {
+            let mut iter = self.iter(old_name, &[]);
+            while let Some((key, value)) = iter.next() {
+                entries.push((key.to_vec(), value.to_vec()));
             }
         }
+        self.remove_by_prefix(old_name, None);
+        for (key, value) in entries {
+            self.put(new_name, key, value);
+        }
+        indexes_metadata::rename_index_type(old_name, new_name, self);
+    }
+
+    /// Limits the number of the storage operations, which can be performed with the fork
+    /// from now on, or removes the limit if `None` is given. Each read (`get`, `contains`,
+    /// creation of an iterator and each entry yielded by the iterator), each change,
+    /// each `remove_by_prefix` call and each key removed by it is counted as one operation.
+    ///
+    /// When the limit is exhausted, the next operation unwinds with
+    /// the [`OperationsLimitExceeded`] payload without calling the panic hook.
+    /// The changes are not affected, so the unwinding can be caught with [`catch_unwind`].
+    ///
+    /// [`OperationsLimitExceeded`]: struct.OperationsLimitExceeded.html
+    /// [`catch_unwind`]: #method.catch_unwind
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{Database, MemoryDB, OperationsLimitExceeded, Snapshot};
+    ///
+    /// let db = MemoryDB::new();
+    /// let mut fork = db.fork();
+    /// fork.set_operations_limit(Some(1));
+    /// let result = fork.catch_unwind(|fork| {
+    ///     fork.put("index_name", vec![1], vec![1]);
+    ///     fork.put("index_name", vec![2], vec![2]);
+    /// });
+    ///
+    /// assert!(result.unwrap_err().is::<OperationsLimitExceeded>());
+    /// fork.set_operations_limit(None);
+    /// assert!(!fork.contains("index_name", &[1]));
+    /// ```
+    pub fn set_operations_limit(&mut self, limit: Option<u64>) {
+        self.budget.operations_left.set(limit);
+    }
+
+    /// Limits the time, during which the storage operations can be performed with the fork,
+    /// or removes the limit if `None` is given. The time is counted from now on.
+    ///
+    /// When the time is up, the next operation (see [`set_operations_limit`]) unwinds with
+    /// the [`TimeLimitExceeded`] payload without calling the panic hook. The changes are not
+    /// affected, so the unwinding can be caught with [`catch_unwind`].
+    ///
+    /// [`set_operations_limit`]: #method.set_operations_limit
+    /// [`TimeLimitExceeded`]: struct.TimeLimitExceeded.html
+    /// [`catch_unwind`]: #method.catch_unwind
+    pub fn set_time_limit(&mut self, limit: Option<Duration>) {
+        self.budget.deadline = limit.map(|limit| Instant::now() + limit);
     }
 
     /// Converts the fork into `Patch`.
@@ -476,7 +890,7 @@ impl Fork {
     ///
     /// Panics if checkpoint was created before and it was not committed or rolled back yet.
     pub fn merge(&mut self, patch: Patch) {
-        if self.logged {
+        if !self.savepoints.is_empty() {
             panic!("call merge before commit or rollback");
         }
 
@@ -490,6 +904,25 @@ impl Fork {
             }
         }
     }
+
+    fn spend_operation(&self) {
+        self.budget.spend();
+    }
+}
+
+impl Budget {
+    fn spend(&self) {
+        match self.operations_left.get() {
+            Some(0) => panic::resume_unwind(Box::new(OperationsLimitExceeded)),
+            Some(left) => self.operations_left.set(Some(left - 1)),
+            None => {}
+        }
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                panic::resume_unwind(Box::new(TimeLimitExceeded));
+            }
+        }
+    }
 }
 
 impl AsRef<Snapshot> for Snapshot + 'static {
@@ -511,22 +944,68 @@ impl ::std::fmt::Debug for Fork {
 }
 
 impl<'a> ForkIter<'a> {
+    fn next_entry(&mut self) -> Option<(&[u8], &[u8])> {
+        loop {
+            match self.step() {
+                Stored => return self.snapshot.next(),
+                Replaced => {
+                    self.snapshot.next();
+                    return self.changes.as_mut().unwrap().next().map(|(key, change)| {
+                        (
+                            key.as_slice(),
+                            match *change {
+                                Change::Put(ref value) => value.as_slice(),
+                                Change::Delete => unreachable!(),
+                            },
+                        )
+                    });
+                }
+                Inserted => {
+                    return self.changes.as_mut().unwrap().next().map(|(key, change)| {
+                        (
+                            key.as_slice(),
+                            match *change {
+                                Change::Put(ref value) => value.as_slice(),
+                                Change::Delete => unreachable!(),
+                            },
+                        )
+                    })
+                }
+                Deleted => {
+                    self.changes.as_mut().unwrap().next();
+                    self.snapshot.next();
+                }
+                MissDeleted => {
+                    self.changes.as_mut().unwrap().next();
+                }
+                Finished => return None,
+            }
+        }
+    }
+
     fn step(&mut self) -> NextIterValue {
         if let Some(ref mut changes) = self.changes {
             match changes.peek() {
                 Some(&(k, change)) => {
                     match self.snapshot.peek() {
                         Some((key, ..)) => {
+                            // Changes go before the stored entries if they are closer
+                            // to the start of the iteration.
+                            let ordering = if self.reverse {
+                                key.cmp(&k[..])
+                            } else {
+                                k[..].cmp(key)
+                            };
                             match *change {
                                 Change::Put(..) => {
-                                    match k[..].cmp(key) {
+                                    match ordering {
                                         Equal => Replaced,
                                         Less => Inserted,
                                         Greater => Stored,
                                     }
                                 }
                                 Change::Delete => {
-                                    match k[..].cmp(key) {
+                                    match ordering {
                                         Equal => Deleted,
                                         Less => MissDeleted,
                                         Greater => Stored,
@@ -560,42 +1039,12 @@ impl<'a> ForkIter<'a> {
 
 impl<'a> Iterator for ForkIter<'a> {
     fn next(&mut self) -> Option<(&[u8], &[u8])> {
-        loop {
-            match self.step() {
-                Stored => return self.snapshot.next(),
-                Replaced => {
-                    self.snapshot.next();
-                    return self.changes.as_mut().unwrap().next().map(|(key, change)| {
-                        (
-                            key.as_slice(),
-                            match *change {
-                                Change::Put(ref value) => value.as_slice(),
-                                Change::Delete => unreachable!(),
-                            },
-                        )
-                    });
-                }
-                Inserted => {
-                    return self.changes.as_mut().unwrap().next().map(|(key, change)| {
-                        (
-                            key.as_slice(),
-                            match *change {
-                                Change::Put(ref value) => value.as_slice(),
-                                Change::Delete => unreachable!(),
-                            },
-                        )
-                    })
-                }
-                Deleted => {
-                    self.changes.as_mut().unwrap().next();
-                    self.snapshot.next();
-                }
-                MissDeleted => {
-                    self.changes.as_mut().unwrap().next();
-                }
-                Finished => return None,
-            }
+        let budget = self.budget;
+        let entry = self.next_entry();
+        if entry.is_some() {
+            budget.spend();
         }
+        entry
     }
 
     fn peek(&mut self) -> Option<(&[u8], &[u8])> {
@@ -626,6 +1075,39 @@ impl<'a> Iterator for ForkIter<'a> {
     }
 }
 
+impl<'a> RangeIter<'a> {
+    fn in_range(&mut self) -> bool {
+        match self.iter.peek() {
+            Some((key, ..)) => {
+                if self.reverse {
+                    key >= &self.bound[..]
+                } else {
+                    key < &self.bound[..]
+                }
+            }
+            None => false,
+        }
+    }
+}
+
+impl<'a> Iterator for RangeIter<'a> {
+    fn next(&mut self) -> Option<(&[u8], &[u8])> {
+        if self.in_range() {
+            self.iter.next()
+        } else {
+            None
+        }
+    }
+
+    fn peek(&mut self) -> Option<(&[u8], &[u8])> {
+        if self.in_range() {
+            self.iter.peek()
+        } else {
+            None
+        }
+    }
+}
+
 impl<T: Database> From<T> for Box<Database> {
     fn from(db: T) -> Self {
         Box::new(db) as Box<Database>
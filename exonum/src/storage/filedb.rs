@@ -0,0 +1,878 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An implementation of a log-structured database stored in a single append-only file.
+//!
+//! The data file has the following layout (all integers are little-endian):
+//!
+//! - Header: the magic bytes `EXNFILDB` followed by the format version (`u16`).
+//! - Records, each of which is the length of the record body (`u64`) followed by the body,
+//!   a patch in the [portable encoding](../struct.PatchWriter.html).
+//!
+//! Every merge appends a single record to the file. The positions of the current values
+//! in the file are kept in an in-memory ordered index, which is rebuilt from the records
+//! when the database is opened.
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use std::collections::{BTreeMap, HashMap};
+use std::collections::Bound::{self, Included, Unbounded};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::iter::Iterator as StdIterator;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+use crypto::HASH_SIZE;
+use super::{Change, Database, DbOptions, Error, Iter, Iterator, Patch, PatchReader, PatchWriter,
+            Result, Snapshot};
+
+const DATA_FILE_NAME: &str = "data.log";
+const COMPACTED_FILE_NAME: &str = "data.log.compact";
+
+const FILE_MAGIC: &[u8; 8] = b"EXNFILDB";
+const FILE_FORMAT_VERSION: u16 = 1;
+const FILE_HEADER_SIZE: u64 = 10;
+const RECORD_LEN_SIZE: u64 = 8;
+
+// Sizes of the parts of an encoded patch: the magic bytes and the format version,
+// and the end tag followed by the checksum.
+const PATCH_HEADER_SIZE: u64 = 10;
+const PATCH_TRAILER_SIZE: u64 = 1 + HASH_SIZE as u64;
+
+/// Minimal size of the obsolete data in the file, which triggers the compaction.
+const COMPACTION_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// Position of a value in the data file.
+#[derive(Debug, Clone, Copy)]
+struct ValuePtr {
+    offset: u64,
+    len: u32,
+}
+
+type Table = BTreeMap<Vec<u8>, ValuePtr>;
+type Tables = HashMap<String, Arc<Table>>;
+
+/// Size of the encoded `Put` change of the entry.
+fn entry_size(key: &[u8], ptr: &ValuePtr) -> u64 {
+    9 + key.len() as u64 + u64::from(ptr.len)
+}
+
+/// Computes the positions of the values within a record.
+struct RecordLayout {
+    pos: u64,
+}
+
+impl RecordLayout {
+    fn new(offset: u64) -> Self {
+        RecordLayout { pos: offset + RECORD_LEN_SIZE + PATCH_HEADER_SIZE }
+    }
+
+    fn start_index(&mut self, name: &str) {
+        self.pos += 5 + name.len() as u64;
+    }
+
+    fn put(&mut self, key: &[u8], len: usize) -> ValuePtr {
+        self.pos += 9 + key.len() as u64;
+        let ptr = ValuePtr {
+            offset: self.pos,
+            len: len as u32,
+        };
+        self.pos += len as u64;
+        ptr
+    }
+
+    fn delete(&mut self, key: &[u8]) {
+        self.pos += 5 + key.len() as u64;
+    }
+
+    fn finish(self) -> u64 {
+        self.pos + PATCH_TRAILER_SIZE
+    }
+}
+
+/// A reader keeping track of its position in the data file.
+struct PositionReader<R> {
+    reader: R,
+    pos: u64,
+}
+
+impl<R: Read> Read for PositionReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+/// Read-only handle of the data file shared with the snapshots.
+struct DataFile {
+    file: Mutex<File>,
+}
+
+impl DataFile {
+    fn open(path: &Path) -> io::Result<Self> {
+        Ok(DataFile { file: Mutex::new(File::open(path)?) })
+    }
+
+    fn read(&self, ptr: &ValuePtr) -> Vec<u8> {
+        let mut file = self.file.lock().unwrap();
+        let mut value = vec![0; ptr.len as usize];
+        file.seek(SeekFrom::Start(ptr.offset))
+            .and_then(|_| file.read_exact(&mut value))
+            .unwrap_or_else(|e| panic!("Cannot read a value from the data file: {}", e));
+        value
+    }
+}
+
+/// Mutable state of a `FileDB`.
+struct State {
+    tables: Tables,
+    data: Arc<DataFile>,
+    writer: File,
+    // Length of the data file.
+    len: u64,
+    // Total size of the encoded current entries.
+    live: u64,
+}
+
+impl State {
+    fn apply(&mut self, name: String, key: Vec<u8>, ptr: Option<ValuePtr>) {
+        let is_empty = {
+            // Copies the table if it is shared with a snapshot, see the notes on `FileDB`.
+            let table = Arc::make_mut(self.tables.entry(name.clone()).or_insert_with(
+                Default::default,
+            ));
+            if let Some(ptr) = ptr {
+                self.live += entry_size(&key, &ptr);
+                if let Some(old) = table.insert(key.clone(), ptr) {
+                    self.live -= entry_size(&key, &old);
+                }
+            } else if let Some(old) = table.remove(&key) {
+                self.live -= entry_size(&key, &old);
+            }
+            table.is_empty()
+        };
+        if is_empty {
+            self.tables.remove(&name);
+        }
+    }
+
+    fn append(&mut self, bytes: &[u8], sync: bool) -> io::Result<()> {
+        let result = self.writer.write_all(bytes).and_then(|_| if sync {
+            self.writer.sync_data()
+        } else {
+            Ok(())
+        });
+        match result {
+            Ok(()) => {
+                self.len += bytes.len() as u64;
+                Ok(())
+            }
+            Err(e) => {
+                // Removes the partially written record, which would otherwise hide
+                // the following records when the file is read.
+                let len = self.len;
+                let _ = self.writer.set_len(len);
+                let _ = self.writer.seek(SeekFrom::Start(len));
+                Err(e)
+            }
+        }
+    }
+
+    fn needs_compaction(&self) -> bool {
+        let garbage = self.len.saturating_sub(FILE_HEADER_SIZE + self.live);
+        garbage >= COMPACTION_THRESHOLD && garbage > self.live
+    }
+}
+
+/// Database implementation storing the data in a single append-only file.
+///
+/// Each merged patch is appended to the data file as a checksummed record, and
+/// the positions of the current values are kept in an in-memory ordered index.
+/// The index is rebuilt from the file when the database is opened, so opening the database
+/// takes time proportional to the size of the file. The memory consumption is proportional
+/// to the total size of the keys, but the values are read from the file on demand.
+///
+/// Snapshots share the index with the database. The index of a table is copied on
+/// the first merge changing the table while a snapshot taken before the merge is alive,
+/// so such merges take time and memory proportional to the number of keys in the changed
+/// tables. Long-living snapshots of large tables should be avoided when the table
+/// is updated frequently.
+///
+/// Merges are atomic: if the process crashes while a patch is written, the incomplete
+/// record is discarded when the database is opened. `merge_sync` additionally
+/// flushes the record to the disk before returning. Damage of any record except
+/// the last one cannot be caused by a crash, so the database fails to open in this case.
+///
+/// Overwritten and removed values stay in the file until it is compacted, that is,
+/// rewritten with the current entries only. Compaction is performed automatically
+/// after a merge when the obsolete data takes more than half of the file and exceeds
+/// 16 MB, and can be requested explicitly with [`compact`].
+///
+/// `FileDB` has no dependencies beyond the Rust standard library, which makes it suitable
+/// for small deployments and test fixtures.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate exonum;
+/// # extern crate tempdir;
+/// use exonum::storage::{Database, DbOptions, FileDB, MapIndex};
+/// # use tempdir::TempDir;
+///
+/// # fn main() {
+/// # let dir = TempDir::new("exonum_filedb").unwrap();
+/// # let path = dir.path();
+/// let db = FileDB::open(path, &DbOptions::default()).unwrap();
+/// let mut fork = db.fork();
+/// MapIndex::new("index", &mut fork).put(&1_u64, 2_u64);
+/// db.merge_sync(fork.into_patch()).unwrap();
+/// drop(db);
+///
+/// let db = FileDB::open(path, &DbOptions::default()).unwrap();
+/// let snapshot = db.snapshot();
+/// let index: MapIndex<_, u64, u64> = MapIndex::new("index", &snapshot);
+/// assert_eq!(index.get(&1), Some(2));
+/// # }
+/// ```
+///
+/// [`compact`]: #method.compact
+pub struct FileDB {
+    dir: PathBuf,
+    state: RwLock<State>,
+}
+
+/// A snapshot of a `FileDB`.
+struct FileDBSnapshot {
+    tables: Tables,
+    data: Arc<DataFile>,
+}
+
+/// An iterator over the entries of a `FileDB`.
+struct FileDBIterator<'a> {
+    entries: Box<StdIterator<Item = (&'a Vec<u8>, &'a ValuePtr)> + 'a>,
+    data: &'a DataFile,
+    current: Option<(&'a [u8], Vec<u8>)>,
+    peeked: Option<(&'a [u8], Vec<u8>)>,
+}
+
+impl FileDB {
+    /// Opens a database stored in the specified directory with the specified options.
+    ///
+    /// Of all the options, only `create_if_missing` is used by this backend.
+    pub fn open<P: AsRef<Path>>(path: P, options: &DbOptions) -> Result<FileDB> {
+        let dir = path.as_ref().to_path_buf();
+        if !dir.exists() {
+            if options.create_if_missing {
+                fs::create_dir_all(&dir).map_err(io_error)?;
+            } else {
+                return Err(Error::new(format!("Database {:?} does not exist", dir)));
+            }
+        }
+        // The temporary file is left if the compaction was interrupted.
+        let compacted_path = dir.join(COMPACTED_FILE_NAME);
+        if compacted_path.exists() {
+            fs::remove_file(&compacted_path).map_err(io_error)?;
+        }
+
+        let path = dir.join(DATA_FILE_NAME);
+        let mut writer = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(&path)
+            .map_err(io_error)?;
+        let file_len = writer.metadata().map_err(io_error)?.len();
+        if file_len < FILE_HEADER_SIZE {
+            // The file is created or its creation was interrupted.
+            writer.set_len(0).map_err(io_error)?;
+            write_header(&mut writer).map_err(io_error)?;
+            writer.sync_all().map_err(io_error)?;
+            sync_dir(&dir).map_err(io_error)?;
+        }
+
+        let mut state = State {
+            tables: HashMap::new(),
+            data: Arc::new(DataFile::open(&path).map_err(io_error)?),
+            writer,
+            len: FILE_HEADER_SIZE,
+            live: 0,
+        };
+        replay(&path, &mut state)?;
+        let len = state.len;
+        if len < state.writer.metadata().map_err(io_error)?.len() {
+            state.writer.set_len(len).map_err(io_error)?;
+            state.writer.sync_all().map_err(io_error)?;
+        }
+        state.writer.seek(SeekFrom::Start(len)).map_err(io_error)?;
+
+        Ok(FileDB {
+            dir,
+            state: RwLock::new(state),
+        })
+    }
+
+    /// Rewrites the data file leaving only the current entries.
+    ///
+    /// The snapshots taken before the compaction remain valid.
+    pub fn compact(&self) -> Result<()> {
+        let mut state = self.state.write().unwrap();
+        self.compact_state(&mut state)
+    }
+
+    fn compact_state(&self, state: &mut State) -> Result<()> {
+        let compacted_path = self.dir.join(COMPACTED_FILE_NAME);
+        let path = self.dir.join(DATA_FILE_NAME);
+
+        let (tables, len) = {
+            let mut names = state.tables.keys().collect::<Vec<_>>();
+            names.sort();
+            // Positions of the entries in the compacted file.
+            let mut tables = HashMap::new();
+            let mut len = FILE_HEADER_SIZE;
+            if !names.is_empty() {
+                let mut layout = RecordLayout::new(len);
+                for &name in &names {
+                    layout.start_index(name);
+                    let table = state.tables[name]
+                        .iter()
+                        .map(|(key, ptr)| (key.clone(), layout.put(key, ptr.len as usize)))
+                        .collect::<Table>();
+                    tables.insert(name.clone(), Arc::new(table));
+                }
+                len = layout.finish();
+            }
+
+            let mut file = File::create(&compacted_path).map_err(io_error)?;
+            write_header(&mut file).map_err(io_error)?;
+            if !names.is_empty() {
+                let mut writer = BufWriter::new(file);
+                let body_len = len - FILE_HEADER_SIZE - RECORD_LEN_SIZE;
+                writer.write_all(&record_len(body_len)).map_err(io_error)?;
+                let mut writer = PatchWriter::new(writer).map_err(io_error)?;
+                for name in names {
+                    writer.start_index(name).map_err(io_error)?;
+                    for (key, ptr) in state.tables[name].iter() {
+                        let value = state.data.read(ptr);
+                        writer
+                            .write_change(key, &Change::Put(value))
+                            .map_err(io_error)?;
+                    }
+                }
+                file = writer.finish().map_err(io_error)?.into_inner().map_err(
+                    |e| io_error(e.into()),
+                )?;
+            }
+            file.sync_all().map_err(io_error)?;
+            (tables, len)
+        };
+
+        // The compacted file is opened before it replaces the data file, so the state
+        // keeps pointing to the data file if any of the operations fails.
+        let data = Arc::new(DataFile::open(&compacted_path).map_err(io_error)?);
+        let mut writer = OpenOptions::new()
+            .write(true)
+            .open(&compacted_path)
+            .map_err(io_error)?;
+        writer.seek(SeekFrom::Start(len)).map_err(io_error)?;
+        fs::rename(&compacted_path, &path).map_err(io_error)?;
+
+        state.tables = tables;
+        state.data = data;
+        state.writer = writer;
+        state.len = len;
+        sync_dir(&self.dir).map_err(io_error)
+    }
+
+    fn do_merge(&self, patch: Patch, sync: bool) -> Result<()> {
+        let mut indexes = patch
+            .into_iter()
+            .filter(|&(_, ref changes)| changes.iter().next().is_some())
+            .collect::<Vec<_>>();
+        if indexes.is_empty() {
+            return Ok(());
+        }
+        indexes.sort_by(|x, y| x.0.cmp(&y.0));
+
+        let mut state = self.state.write().unwrap();
+        // The length of the record body is written when the body is encoded.
+        let mut writer = PatchWriter::new(vec![0; RECORD_LEN_SIZE as usize]).map_err(io_error)?;
+        let mut layout = RecordLayout::new(state.len);
+        let mut updates = Vec::new();
+        for (name, changes) in indexes {
+            writer.start_index(&name).map_err(io_error)?;
+            layout.start_index(&name);
+            for (key, change) in changes {
+                writer.write_change(&key, &change).map_err(io_error)?;
+                let ptr = match change {
+                    Change::Put(ref value) => Some(layout.put(&key, value.len())),
+                    Change::Delete => {
+                        layout.delete(&key);
+                        None
+                    }
+                };
+                updates.push((name.clone(), key, ptr));
+            }
+        }
+        let mut bytes = writer.finish().map_err(io_error)?;
+        let body_len = bytes.len() as u64 - RECORD_LEN_SIZE;
+        bytes[..RECORD_LEN_SIZE as usize].copy_from_slice(&record_len(body_len));
+        debug_assert_eq!(layout.finish(), state.len + bytes.len() as u64);
+
+        state.append(&bytes, sync).map_err(io_error)?;
+        for (name, key, ptr) in updates {
+            state.apply(name, key, ptr);
+        }
+        if state.needs_compaction() {
+            if let Err(e) = self.compact_state(&mut state) {
+                warn!("Cannot compact the database {:?}: {}", self.dir, e);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn record_len(body_len: u64) -> [u8; RECORD_LEN_SIZE as usize] {
+    let mut buf = [0; RECORD_LEN_SIZE as usize];
+    LittleEndian::write_u64(&mut buf, body_len);
+    buf
+}
+
+fn write_header(file: &mut File) -> io::Result<()> {
+    let mut version = [0; 2];
+    LittleEndian::write_u16(&mut version, FILE_FORMAT_VERSION);
+    file.write_all(FILE_MAGIC)?;
+    file.write_all(&version)
+}
+
+/// Reads the records of the data file into the state. The reading stops at the last record
+/// if it is incomplete or damaged, which happens after a crash during the merge.
+/// Returns an error if any other record is damaged.
+fn replay(path: &Path, state: &mut State) -> Result<()> {
+    let file = File::open(path).map_err(io_error)?;
+    let file_len = file.metadata().map_err(io_error)?.len();
+    let mut reader = BufReader::new(file);
+
+    let mut header = [0; FILE_HEADER_SIZE as usize];
+    reader.read_exact(&mut header).map_err(io_error)?;
+    if &header[..FILE_MAGIC.len()] != FILE_MAGIC {
+        return Err(Error::new(format!("{:?} is not a database file", path)));
+    }
+    let version = LittleEndian::read_u16(&header[FILE_MAGIC.len()..]);
+    if version != FILE_FORMAT_VERSION {
+        return Err(Error::new(
+            format!("Unsupported database format version: {}", version),
+        ));
+    }
+
+    let mut pos = FILE_HEADER_SIZE;
+    while pos < file_len {
+        let end = if file_len - pos < RECORD_LEN_SIZE {
+            None
+        } else {
+            let mut buf = [0; RECORD_LEN_SIZE as usize];
+            reader.read_exact(&mut buf).map_err(io_error)?;
+            match LittleEndian::read_u64(&buf).checked_add(pos + RECORD_LEN_SIZE) {
+                Some(end) if end <= file_len => Some(end),
+                _ => None,
+            }
+        };
+        // The reader is not used after a failure, so its position does not matter then.
+        let result = match end {
+            Some(end) => read_record(&mut reader, pos, end),
+            None => Err(Error::new("Incomplete record")),
+        };
+
+        match result {
+            Ok(updates) => {
+                for (name, key, ptr) in updates {
+                    state.apply(name, key, ptr);
+                }
+                pos = end.unwrap();
+                state.len = pos;
+            }
+            Err(ref e) if end.map_or(false, |end| end < file_len) => {
+                return Err(Error::new(format!(
+                    "The database file {:?} is damaged at offset {}: {}",
+                    path,
+                    pos,
+                    e
+                )));
+            }
+            Err(e) => {
+                warn!(
+                    "Discarding the damaged end of the database file {:?} at offset {}: {}",
+                    path,
+                    pos,
+                    e
+                );
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decodes the body of the record occupying the specified range of the data file.
+/// The body is read from `reader` entry by entry, so only the keys of the record
+/// are kept in memory.
+fn read_record<R: Read>(
+    reader: R,
+    offset: u64,
+    end: u64,
+) -> Result<Vec<(String, Vec<u8>, Option<ValuePtr>)>> {
+    let mut reader = PositionReader {
+        reader: reader.take(end - offset - RECORD_LEN_SIZE),
+        pos: offset + RECORD_LEN_SIZE,
+    };
+    let mut layout = RecordLayout::new(offset);
+    let mut updates = Vec::new();
+    {
+        let mut current: Option<String> = None;
+        for entry in PatchReader::new(&mut reader)? {
+            let (name, key, change) = entry?;
+            if current.as_ref() != Some(&name) {
+                layout.start_index(&name);
+                current = Some(name.clone());
+            }
+            let ptr = match change {
+                Change::Put(value) => Some(layout.put(&key, value.len())),
+                Change::Delete => {
+                    layout.delete(&key);
+                    None
+                }
+            };
+            updates.push((name, key, ptr));
+        }
+    }
+    if layout.finish() != reader.pos || reader.pos != end {
+        return Err(Error::new("Unexpected layout of the record"));
+    }
+    Ok(updates)
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+fn io_error(e: io::Error) -> Error {
+    Error::new(format!("Database file I/O error: {}", e))
+}
+
+impl Database for FileDB {
+    fn snapshot(&self) -> Box<Snapshot> {
+        let state = self.state.read().unwrap();
+        Box::new(FileDBSnapshot {
+            tables: state.tables.clone(),
+            data: Arc::clone(&state.data),
+        })
+    }
+
+    fn merge(&self, patch: Patch) -> Result<()> {
+        self.do_merge(patch, false)
+    }
+
+    fn merge_sync(&self, patch: Patch) -> Result<()> {
+        self.do_merge(patch, true)
+    }
+}
+
+impl FileDBSnapshot {
+    fn iterator<'a, I>(&'a self, entries: I) -> Iter<'a>
+    where
+        I: StdIterator<Item = (&'a Vec<u8>, &'a ValuePtr)> + 'a,
+    {
+        Box::new(FileDBIterator {
+            entries: Box::new(entries),
+            data: &self.data,
+            current: None,
+            peeked: None,
+        })
+    }
+}
+
+impl Snapshot for FileDBSnapshot {
+    fn get(&self, name: &str, key: &[u8]) -> Option<Vec<u8>> {
+        self.tables
+            .get(name)
+            .and_then(|table| table.get(key))
+            .map(|ptr| self.data.read(ptr))
+    }
+
+    fn contains(&self, name: &str, key: &[u8]) -> bool {
+        self.tables.get(name).map_or(
+            false,
+            |table| table.contains_key(key),
+        )
+    }
+
+    fn iter<'a>(&'a self, name: &str, from: &[u8]) -> Iter<'a> {
+        match self.tables.get(name) {
+            Some(table) => self.iterator(table.range::<[u8], _>((Included(from), Unbounded))),
+            None => self.iterator(::std::iter::empty()),
+        }
+    }
+
+    fn iter_rev<'a>(&'a self, name: &str, to: Bound<&[u8]>) -> Iter<'a> {
+        match self.tables.get(name) {
+            Some(table) => self.iterator(table.range::<[u8], _>((Unbounded, to)).rev()),
+            None => self.iterator(::std::iter::empty()),
+        }
+    }
+}
+
+impl<'a> FileDBIterator<'a> {
+    fn read_next(&mut self) -> Option<(&'a [u8], Vec<u8>)> {
+        let data = self.data;
+        self.entries.next().map(|(key, ptr)| (&key[..], data.read(ptr)))
+    }
+}
+
+impl<'a> Iterator for FileDBIterator<'a> {
+    fn next(&mut self) -> Option<(&[u8], &[u8])> {
+        self.current = match self.peeked.take() {
+            Some(entry) => Some(entry),
+            None => self.read_next(),
+        };
+        self.current.as_ref().map(|&(key, ref value)| (key, &value[..]))
+    }
+
+    fn peek(&mut self) -> Option<(&[u8], &[u8])> {
+        if self.peeked.is_none() {
+            self.peeked = self.read_next();
+        }
+        self.peeked.as_ref().map(|&(key, ref value)| (key, &value[..]))
+    }
+}
+
+impl From<FileDB> for Arc<Database> {
+    fn from(db: FileDB) -> Arc<Database> {
+        Arc::from(Box::new(db) as Box<Database>)
+    }
+}
+
+impl fmt::Debug for FileDB {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FileDB").field("dir", &self.dir).finish()
+    }
+}
+
+impl fmt::Debug for FileDBSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FileDBSnapshot(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::OpenOptions;
+    use std::io::{Read, Seek, SeekFrom, Write};
+    use std::path::Path;
+
+    use tempdir::TempDir;
+
+    use storage::{Database, DbOptions, Snapshot};
+    use super::{FileDB, DATA_FILE_NAME, FILE_HEADER_SIZE};
+
+    fn open(path: &Path) -> FileDB {
+        FileDB::open(path, &DbOptions::default()).unwrap()
+    }
+
+    fn put(db: &Database, name: &str, entries: &[(u8, Option<u8>)]) {
+        let mut fork = db.fork();
+        for &(key, value) in entries {
+            match value {
+                Some(value) => fork.put(name, vec![key], vec![value; 100]),
+                None => fork.remove(name, vec![key]),
+            }
+        }
+        db.merge(fork.into_patch()).unwrap();
+    }
+
+    fn entries(snapshot: &Snapshot, name: &str) -> Vec<(u8, u8)> {
+        let mut iter = snapshot.iter(name, &[]);
+        let mut entries = Vec::new();
+        while let Some((key, value)) = iter.next() {
+            entries.push((key[0], value[0]));
+        }
+        entries
+    }
+
+    fn file_len(path: &Path) -> u64 {
+        path.join(DATA_FILE_NAME).metadata().unwrap().len()
+    }
+
+    #[test]
+    fn test_filedb_reopen() {
+        let dir = TempDir::new("exonum_filedb_reopen").unwrap();
+        {
+            let db = open(dir.path());
+            put(&db, "a", &[(1, Some(1)), (2, Some(2)), (3, Some(3))]);
+            put(&db, "a", &[(2, None), (3, Some(30))]);
+            put(&db, "b", &[(1, Some(10))]);
+            put(&db, "b", &[(1, None)]);
+        }
+
+        let db = open(dir.path());
+        let snapshot = db.snapshot();
+        assert_eq!(entries(&*snapshot, "a"), vec![(1, 1), (3, 30)]);
+        assert_eq!(entries(&*snapshot, "b"), vec![]);
+        assert_eq!(snapshot.get("a", &[3]), Some(vec![30; 100]));
+        assert!(!snapshot.contains("a", &[2]));
+    }
+
+    #[test]
+    fn test_filedb_missing_database() {
+        let dir = TempDir::new("exonum_filedb_missing").unwrap();
+        let options = DbOptions {
+            create_if_missing: false,
+            ..DbOptions::default()
+        };
+        assert!(FileDB::open(dir.path().join("db"), &options).is_err());
+    }
+
+    #[test]
+    fn test_filedb_damaged_record() {
+        let dir = TempDir::new("exonum_filedb_damaged").unwrap();
+        let len = {
+            let db = open(dir.path());
+            put(&db, "a", &[(1, Some(1))]);
+            let len = file_len(dir.path());
+            put(&db, "a", &[(1, Some(2)), (2, Some(2))]);
+            len
+        };
+
+        // Emulates the crash in the middle of writing the last record.
+        let file = OpenOptions::new()
+            .write(true)
+            .open(dir.path().join(DATA_FILE_NAME))
+            .unwrap();
+        let full_len = file_len(dir.path());
+        file.set_len((len + full_len) / 2).unwrap();
+        drop(file);
+
+        {
+            let db = open(dir.path());
+            assert_eq!(file_len(dir.path()), len);
+            assert_eq!(entries(&*db.snapshot(), "a"), vec![(1, 1)]);
+            put(&db, "a", &[(3, Some(3))]);
+        }
+        let db = open(dir.path());
+        assert_eq!(entries(&*db.snapshot(), "a"), vec![(1, 1), (3, 3)]);
+    }
+
+    // Inverts the byte at the specified offset of the data file.
+    fn damage(path: &Path, offset: u64) {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path.join(DATA_FILE_NAME))
+            .unwrap();
+        let mut byte = [0];
+        file.seek(SeekFrom::Start(offset)).unwrap();
+        file.read_exact(&mut byte).unwrap();
+        file.seek(SeekFrom::Start(offset)).unwrap();
+        file.write_all(&[!byte[0]]).unwrap();
+    }
+
+    #[test]
+    fn test_filedb_damaged_last_record() {
+        let dir = TempDir::new("exonum_filedb_damaged_last").unwrap();
+        let len = {
+            let db = open(dir.path());
+            put(&db, "a", &[(1, Some(1))]);
+            let len = file_len(dir.path());
+            put(&db, "a", &[(1, Some(2))]);
+            len
+        };
+        damage(dir.path(), (len + file_len(dir.path())) / 2);
+
+        let db = open(dir.path());
+        assert_eq!(file_len(dir.path()), len);
+        assert_eq!(entries(&*db.snapshot(), "a"), vec![(1, 1)]);
+    }
+
+    #[test]
+    fn test_filedb_damaged_middle_record() {
+        let dir = TempDir::new("exonum_filedb_damaged_middle").unwrap();
+        let (first_len, second_len) = {
+            let db = open(dir.path());
+            put(&db, "a", &[(1, Some(1))]);
+            let first_len = file_len(dir.path());
+            put(&db, "a", &[(1, Some(2))]);
+            let second_len = file_len(dir.path());
+            put(&db, "a", &[(2, Some(3))]);
+            (first_len, second_len)
+        };
+        let full_len = file_len(dir.path());
+        damage(dir.path(), (first_len + second_len) / 2);
+
+        // The records after the damaged one are not discarded.
+        assert!(FileDB::open(dir.path(), &DbOptions::default()).is_err());
+        assert_eq!(file_len(dir.path()), full_len);
+    }
+
+    #[test]
+    fn test_filedb_compaction() {
+        let dir = TempDir::new("exonum_filedb_compaction").unwrap();
+        let db = open(dir.path());
+        for i in 0..10 {
+            put(&db, "a", &[(1, Some(i)), (2, Some(i)), (i, Some(i))]);
+        }
+        put(&db, "b", &[(1, Some(1))]);
+        put(&db, "b", &[(1, None)]);
+
+        let old_snapshot = db.snapshot();
+        let len = file_len(dir.path());
+        db.compact().unwrap();
+        assert!(file_len(dir.path()) < len);
+
+        let expected = vec![
+            (0, 0),
+            (1, 9),
+            (2, 9),
+            (3, 3),
+            (4, 4),
+            (5, 5),
+            (6, 6),
+            (7, 7),
+            (8, 8),
+            (9, 9),
+        ];
+        assert_eq!(entries(&*old_snapshot, "a"), expected);
+        assert_eq!(entries(&*db.snapshot(), "a"), expected);
+
+        put(&db, "a", &[(1, Some(100))]);
+        drop(db);
+        let db = open(dir.path());
+        assert_eq!(db.snapshot().get("a", &[1]), Some(vec![100; 100]));
+        assert_eq!(entries(&*db.snapshot(), "a").len(), 10);
+
+        // Compaction of an empty database leaves the header only.
+        let mut fork = db.fork();
+        fork.drop_index("a");
+        db.merge(fork.into_patch()).unwrap();
+        db.compact().unwrap();
+        assert_eq!(file_len(dir.path()), FILE_HEADER_SIZE);
+        assert_eq!(entries(&*db.snapshot(), "a"), vec![]);
+    }
+}
@@ -0,0 +1,715 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An implementation of a Merkelized map with keys of arbitrary length.
+
+use std::borrow::{Borrow, Cow};
+use std::marker::PhantomData;
+use std::fmt;
+
+use byteorder::{ByteOrder, LittleEndian};
+use serde::{Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+use crypto::{hash, CryptoHash, Hash, HashStream};
+use encoding::serialize::encode_hex;
+use super::{BaseIndex, Error, Fork, Snapshot, StorageKey, StorageValue};
+use super::indexes_metadata::IndexType;
+use super::proof_map_index::{MapProof, ProofMapIndex, ProofMapIndexIter, ProofMapIndexValues};
+
+/// A Merkelized version of a map with keys of arbitrary length.
+///
+/// Unlike [`ProofMapIndex`], `HashedProofMapIndex` accepts any key that implements
+/// the [`StorageKey`] trait. The key is hashed to get a path in the underlying Merkle Patricia
+/// tree, and the original key is stored next to the value, so it can be restored
+/// during the iteration and is revealed by the proofs.
+///
+/// Since the position of an entry in the tree depends on the hash of its key, the entries are
+/// iterated in an arbitrary (but deterministic) order rather than in the order of the keys.
+///
+/// [`ProofMapIndex`]: ../proof_map_index/struct.ProofMapIndex.html
+/// [`StorageKey`]: ../trait.StorageKey.html
+pub struct HashedProofMapIndex<T, K, V> {
+    map: ProofMapIndex<T, Hash, HashedMapEntry<V>>,
+    _k: PhantomData<K>,
+}
+
+/// An entry of a `HashedProofMapIndex` that contains the original key along with the value.
+///
+/// Entries are the leaves of the Merkle Patricia tree of the index. The hash of an entry
+/// commits both to the serialized key and to the value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashedMapEntry<V> {
+    key: Vec<u8>,
+    value: V,
+}
+
+/// A proof of existence or non-existence for a key of a `HashedProofMapIndex`.
+///
+/// This struct is created by the [`get_proof`] method on [`HashedProofMapIndex`].
+///
+/// [`get_proof`]: struct.HashedProofMapIndex.html#method.get_proof
+/// [`HashedProofMapIndex`]: struct.HashedProofMapIndex.html
+#[derive(Debug)]
+pub struct HashedMapProof<V> {
+    proof: MapProof<HashedMapEntry<V>>,
+}
+
+/// An iterator over the entries of a `HashedProofMapIndex`.
+///
+/// This struct is created by the [`iter`] method on [`HashedProofMapIndex`].
+/// See its documentation for more.
+///
+/// [`iter`]: struct.HashedProofMapIndex.html#method.iter
+/// [`HashedProofMapIndex`]: struct.HashedProofMapIndex.html
+#[derive(Debug)]
+pub struct HashedProofMapIndexIter<'a, K, V> {
+    base_iter: ProofMapIndexIter<'a, Hash, HashedMapEntry<V>>,
+    _k: PhantomData<K>,
+}
+
+/// An iterator over the keys of a `HashedProofMapIndex`.
+///
+/// This struct is created by the [`keys`] method on [`HashedProofMapIndex`].
+/// See its documentation for more.
+///
+/// [`keys`]: struct.HashedProofMapIndex.html#method.keys
+/// [`HashedProofMapIndex`]: struct.HashedProofMapIndex.html
+#[derive(Debug)]
+pub struct HashedProofMapIndexKeys<'a, K, V> {
+    base_iter: ProofMapIndexValues<'a, HashedMapEntry<V>>,
+    _k: PhantomData<K>,
+}
+
+/// An iterator over the values of a `HashedProofMapIndex`.
+///
+/// This struct is created by the [`values`] method on [`HashedProofMapIndex`].
+/// See its documentation for more.
+///
+/// [`values`]: struct.HashedProofMapIndex.html#method.values
+/// [`HashedProofMapIndex`]: struct.HashedProofMapIndex.html
+#[derive(Debug)]
+pub struct HashedProofMapIndexValues<'a, V> {
+    base_iter: ProofMapIndexValues<'a, HashedMapEntry<V>>,
+}
+
+fn key_bytes<Q: StorageKey + ?Sized>(key: &Q) -> Vec<u8> {
+    let mut buffer = vec![0; key.size()];
+    key.write(&mut buffer);
+    buffer
+}
+
+impl<V> HashedMapEntry<V> {
+    /// Returns the serialized key of the entry.
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+
+    /// Returns the value of the entry.
+    pub fn value(&self) -> &V {
+        &self.value
+    }
+}
+
+impl<V: StorageValue> CryptoHash for HashedMapEntry<V> {
+    fn hash(&self) -> Hash {
+        HashStream::new()
+            .update(&self.key)
+            .update(self.value.hash().as_ref())
+            .hash()
+    }
+}
+
+impl<V: StorageValue> StorageValue for HashedMapEntry<V> {
+    fn into_bytes(self) -> Vec<u8> {
+        let value = self.value.into_bytes();
+        let mut buffer = vec![0; 4];
+        LittleEndian::write_u32(&mut buffer, self.key.len() as u32);
+        buffer.reserve(self.key.len() + value.len());
+        buffer.extend_from_slice(&self.key);
+        buffer.extend_from_slice(&value);
+        buffer
+    }
+
+    fn from_bytes(value: Cow<[u8]>) -> Self {
+        let key_len = LittleEndian::read_u32(&value[..4]) as usize;
+        let key = value[4..4 + key_len].to_vec();
+        let value = V::from_bytes(Cow::Borrowed(&value[4 + key_len..]));
+        HashedMapEntry { key, value }
+    }
+}
+
+impl<V: Serialize> Serialize for HashedMapEntry<V> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("HashedMapEntry", 2)?;
+        state.serialize_field("key", &encode_hex(&self.key))?;
+        state.serialize_field("value", &self.value)?;
+        state.end()
+    }
+}
+
+impl<V: fmt::Debug + StorageValue> HashedMapProof<V> {
+    /// Returns Merkle root hash of the map proof.
+    pub fn merkle_root(&self) -> Hash {
+        self.proof.merkle_root()
+    }
+
+    /// Verifies the correctness of the proof by the trusted root hash and the requested key.
+    ///
+    /// If the proof is valid and the requested key exists, `Ok(Some(&V))` is returned.
+    /// If the proof is valid and the requested key does not exists, `Ok(None)` is returned.
+    /// If the proof is invalid, `Err` is returned.
+    pub fn validate<Q>(&self, key: &Q, merkle_root: Hash) -> Result<Option<&V>, Error>
+    where
+        Q: StorageKey + ?Sized,
+    {
+        let key = key_bytes(key);
+        match self.proof.validate(&hash(&key), merkle_root)? {
+            Some(entry) => {
+                if entry.key != key {
+                    return Err(Error::new(format!(
+                        "Proof is inconsistent with searched key: \
+                         {:?}. Proof: {:?}",
+                        key,
+                        self
+                    )));
+                }
+                Ok(Some(&entry.value))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl<V: Serialize> Serialize for HashedMapProof<V> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.proof.serialize(serializer)
+    }
+}
+
+impl<T, K, V> HashedProofMapIndex<T, K, V>
+where
+    T: AsRef<Snapshot>,
+    K: StorageKey,
+    V: StorageValue,
+{
+    /// Creates a new index representation based on the name and storage view.
+    ///
+    /// Storage view can be specified as [`&Snapshot`] or [`&mut Fork`]. In the first case only
+    /// immutable methods are available. In the second case both immutable and mutable methods are
+    /// available.
+    ///
+    /// [`&Snapshot`]: ../trait.Snapshot.html
+    /// [`&mut Fork`]: ../struct.Fork.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, HashedProofMapIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let snapshot = db.snapshot();
+    /// let index: HashedProofMapIndex<_, String, u8> = HashedProofMapIndex::new(name, &snapshot);
+    ///
+    /// let mut fork = db.fork();
+    /// let mut mut_index: HashedProofMapIndex<_, String, u8> =
+    ///     HashedProofMapIndex::new(name, &mut fork);
+    /// # drop(index);
+    /// # drop(mut_index);
+    /// ```
+    pub fn new<S: AsRef<str>>(index_name: S, view: T) -> Self {
+        HashedProofMapIndex {
+            map: ProofMapIndex::from_base(
+                BaseIndex::new(index_name, IndexType::HashedProofMap, view),
+            ),
+            _k: PhantomData,
+        }
+    }
+
+    /// Creates a new index representation based on the name, common prefix of its keys
+    /// and storage view.
+    ///
+    /// Storage view can be specified as [`&Snapshot`] or [`&mut Fork`]. In the first case only
+    /// immutable methods are available. In the second case both immutable and mutable methods are
+    /// available.
+    ///
+    /// [`&Snapshot`]: ../trait.Snapshot.html
+    /// [`&mut Fork`]: ../struct.Fork.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, HashedProofMapIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let index_id = vec![01];
+    ///
+    /// let snapshot = db.snapshot();
+    /// let index: HashedProofMapIndex<_, u64, u8> =
+    ///                             HashedProofMapIndex::new_in_family(name, &index_id, &snapshot);
+    ///
+    /// let mut fork = db.fork();
+    /// let mut mut_index : HashedProofMapIndex<_, u64, u8> =
+    ///                             HashedProofMapIndex::new_in_family(name, &index_id, &mut fork);
+    /// # drop(index);
+    /// # drop(mut_index);
+    /// ```
+    pub fn new_in_family<S: AsRef<str>, I: StorageKey>(
+        family_name: S,
+        index_id: &I,
+        view: T,
+    ) -> Self {
+        HashedProofMapIndex {
+            map: ProofMapIndex::from_base(BaseIndex::new_in_family(
+                family_name,
+                index_id,
+                IndexType::HashedProofMap,
+                view,
+            )),
+            _k: PhantomData,
+        }
+    }
+
+    /// Returns the Merkle root hash of the map or default hash value if it is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, HashedProofMapIndex};
+    /// use exonum::crypto::Hash;
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = HashedProofMapIndex::new(name, &mut fork);
+    ///
+    /// let default_hash = index.merkle_root();
+    /// assert_eq!(Hash::default(), default_hash);
+    ///
+    /// index.put(&"key".to_owned(), 100);
+    /// let hash = index.merkle_root();
+    /// assert_ne!(hash, default_hash);
+    /// ```
+    pub fn merkle_root(&self) -> Hash {
+        self.map.merkle_root()
+    }
+
+    /// Returns a value corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, HashedProofMapIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = HashedProofMapIndex::new(name, &mut fork);
+    /// assert_eq!(None, index.get("key"));
+    ///
+    /// index.put(&"key".to_owned(), 2);
+    /// assert_eq!(Some(2), index.get("key"));
+    /// ```
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: StorageKey + ?Sized,
+    {
+        self.map.get(&hash(&key_bytes(key))).map(|entry| entry.value)
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, HashedProofMapIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = HashedProofMapIndex::new(name, &mut fork);
+    /// assert!(!index.contains(&1u64));
+    ///
+    /// index.put(&1u64, 2);
+    /// assert!(index.contains(&1u64));
+    /// ```
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: StorageKey + ?Sized,
+    {
+        self.map.contains(&hash(&key_bytes(key)))
+    }
+
+    /// Returns the proof of existence or non-existence for the specified key.
+    ///
+    /// If the key exists, the proof contains the original key along with the value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, HashedProofMapIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = HashedProofMapIndex::new(name, &mut fork);
+    /// index.put(&"key".to_owned(), 2u8);
+    ///
+    /// let proof = index.get_proof("key");
+    /// assert_eq!(proof.validate("key", index.merkle_root()).unwrap(), Some(&2));
+    /// ```
+    pub fn get_proof<Q>(&self, key: &Q) -> HashedMapProof<V>
+    where
+        K: Borrow<Q>,
+        Q: StorageKey + ?Sized,
+    {
+        HashedMapProof { proof: self.map.get_proof(&hash(&key_bytes(key))) }
+    }
+
+    /// Returns an iterator over the entries of the map. The iterator element type is (K, V).
+    ///
+    /// The entries are ordered by the hashes of their keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, HashedProofMapIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let snapshot = db.snapshot();
+    /// let index: HashedProofMapIndex<_, String, u8> = HashedProofMapIndex::new(name, &snapshot);
+    ///
+    /// for val in index.iter() {
+    ///     println!("{:?}", val);
+    /// }
+    /// ```
+    pub fn iter(&self) -> HashedProofMapIndexIter<K, V> {
+        HashedProofMapIndexIter {
+            base_iter: self.map.iter(),
+            _k: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the entries of the map in reverse order. The iterator element
+    /// type is (K, V).
+    ///
+    /// The entries are ordered by the hashes of their keys, so there is no way to iterate over
+    /// a range of keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, HashedProofMapIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let snapshot = db.snapshot();
+    /// let index: HashedProofMapIndex<_, String, u8> = HashedProofMapIndex::new(name, &snapshot);
+    ///
+    /// for val in index.iter_rev() {
+    ///     println!("{:?}", val);
+    /// }
+    /// ```
+    pub fn iter_rev(&self) -> HashedProofMapIndexIter<K, V> {
+        HashedProofMapIndexIter {
+            base_iter: self.map.iter_rev(),
+            _k: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the keys of the map. The iterator element type is K.
+    ///
+    /// The keys are ordered by their hashes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, HashedProofMapIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let snapshot = db.snapshot();
+    /// let index: HashedProofMapIndex<_, String, u8> = HashedProofMapIndex::new(name, &snapshot);
+    ///
+    /// for key in index.keys() {
+    ///     println!("{}", key);
+    /// }
+    /// ```
+    pub fn keys(&self) -> HashedProofMapIndexKeys<K, V> {
+        HashedProofMapIndexKeys {
+            base_iter: self.map.values(),
+            _k: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the values of the map. The iterator element type is V.
+    ///
+    /// The values are ordered by the hashes of their keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, HashedProofMapIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let snapshot = db.snapshot();
+    /// let index: HashedProofMapIndex<_, String, u8> = HashedProofMapIndex::new(name, &snapshot);
+    ///
+    /// for val in index.values() {
+    ///     println!("{}", val);
+    /// }
+    /// ```
+    pub fn values(&self) -> HashedProofMapIndexValues<V> {
+        HashedProofMapIndexValues { base_iter: self.map.values() }
+    }
+}
+
+impl<'a, K, V> HashedProofMapIndex<&'a mut Fork, K, V>
+where
+    K: StorageKey,
+    V: StorageValue,
+{
+    /// Inserts the key-value pair into the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, HashedProofMapIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = HashedProofMapIndex::new(name, &mut fork);
+    ///
+    /// index.put(&(1u64 << 40), 2);
+    /// assert!(index.contains(&(1u64 << 40)));
+    /// ```
+    pub fn put(&mut self, key: &K, value: V) {
+        let key = key_bytes(key);
+        self.map.put(&hash(&key), HashedMapEntry { key, value });
+    }
+
+    /// Removes the key from the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, HashedProofMapIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = HashedProofMapIndex::new(name, &mut fork);
+    ///
+    /// index.put(&"key".to_owned(), 2);
+    /// assert!(index.contains("key"));
+    ///
+    /// index.remove("key");
+    /// assert!(!index.contains("key"));
+    /// ```
+    pub fn remove<Q>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: StorageKey + ?Sized,
+    {
+        self.map.remove(&hash(&key_bytes(key)))
+    }
+
+    /// Clears the map, removing all entries.
+    ///
+    /// # Notes
+    ///
+    /// Currently this method is not optimized to delete large set of data. During the execution of
+    /// this method the amount of allocated memory is linearly dependent on the number of elements
+    /// in the index.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, HashedProofMapIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = HashedProofMapIndex::new(name, &mut fork);
+    ///
+    /// index.put(&"key".to_owned(), 2);
+    /// assert!(index.contains("key"));
+    ///
+    /// index.clear();
+    /// assert!(!index.contains("key"));
+    /// ```
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+}
+
+impl<'a, T, K, V> ::std::iter::IntoIterator for &'a HashedProofMapIndex<T, K, V>
+where
+    T: AsRef<Snapshot>,
+    K: StorageKey,
+    V: StorageValue,
+{
+    type Item = (K::Owned, V);
+    type IntoIter = HashedProofMapIndexIter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V> Iterator for HashedProofMapIndexIter<'a, K, V>
+where
+    K: StorageKey,
+    V: StorageValue,
+{
+    type Item = (K::Owned, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.base_iter.next().map(
+            |(_, entry)| (K::read(&entry.key), entry.value),
+        )
+    }
+}
+
+impl<'a, K, V> Iterator for HashedProofMapIndexKeys<'a, K, V>
+where
+    K: StorageKey,
+    V: StorageValue,
+{
+    type Item = K::Owned;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.base_iter.next().map(|entry| K::read(&entry.key))
+    }
+}
+
+impl<'a, V> Iterator for HashedProofMapIndexValues<'a, V>
+where
+    V: StorageValue,
+{
+    type Item = V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.base_iter.next().map(|entry| entry.value)
+    }
+}
+
+impl<T, K, V> fmt::Debug for HashedProofMapIndex<T, K, V>
+where
+    T: AsRef<Snapshot>,
+    V: StorageValue + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HashedProofMapIndex")
+            .field("map", &self.map)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crypto::{hash, Hash};
+    use storage::{Database, MemoryDB};
+    use encoding::serialize::json::reexport::to_string;
+    use super::HashedProofMapIndex;
+
+    const IDX_NAME: &'static str = "idx_name";
+
+    #[test]
+    fn test_hashed_proof_map_methods() {
+        let db = MemoryDB::new();
+        let mut fork = db.fork();
+        let mut index = HashedProofMapIndex::new(IDX_NAME, &mut fork);
+        assert_eq!(index.merkle_root(), Hash::zero());
+
+        index.put(&"short".to_owned(), 1u64);
+        index.put(&"a much longer key than 32 bytes, which is fine".to_owned(), 2);
+        index.put(&String::new(), 3);
+        assert_eq!(index.get("short"), Some(1));
+        assert_eq!(index.get("a much longer key than 32 bytes, which is fine"), Some(2));
+        assert_eq!(index.get(""), Some(3));
+        assert_eq!(index.get("absent"), None);
+
+        index.put(&"short".to_owned(), 4);
+        assert_eq!(index.get("short"), Some(4));
+
+        index.remove("short");
+        assert!(!index.contains("short"));
+        assert!(index.contains(""));
+
+        index.clear();
+        assert!(!index.contains(""));
+        assert_eq!(index.merkle_root(), Hash::zero());
+    }
+
+    #[test]
+    fn test_hashed_proof_map_iter() {
+        let db = MemoryDB::new();
+        let mut fork = db.fork();
+        let mut index = HashedProofMapIndex::new(IDX_NAME, &mut fork);
+        for i in 0u64..10 {
+            index.put(&format!("key {}", i), i * 2);
+        }
+
+        let mut entries = index.iter().collect::<Vec<_>>();
+        entries.sort();
+        assert_eq!(
+            entries,
+            (0u64..10).map(|i| (format!("key {}", i), i * 2)).collect::<Vec<_>>()
+        );
+
+        let mut keys = index.keys().collect::<Vec<String>>();
+        keys.sort();
+        assert_eq!(keys, (0..10).map(|i| format!("key {}", i)).collect::<Vec<_>>());
+
+        let mut values = index.values().collect::<Vec<u64>>();
+        values.sort();
+        assert_eq!(values, (0..10).map(|i| i * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_hashed_proof_map_proofs() {
+        let db = MemoryDB::new();
+        let mut fork = db.fork();
+        let mut index = HashedProofMapIndex::new(IDX_NAME, &mut fork);
+        for i in 0u64..50 {
+            index.put(&i, hash(&[i as u8]));
+        }
+        let merkle_root = index.merkle_root();
+
+        for i in 0u64..50 {
+            let proof = index.get_proof(&i);
+            assert_eq!(proof.merkle_root(), merkle_root);
+            assert_eq!(proof.validate(&i, merkle_root).unwrap(), Some(&hash(&[i as u8])));
+            assert!(to_string(&proof).unwrap().contains(&format!("{:016x}", i)));
+        }
+
+        let proof = index.get_proof(&100);
+        assert_eq!(proof.validate(&100u64, merkle_root).unwrap(), None);
+        assert!(proof.validate(&100u64, hash(&[])).is_err());
+    }
+}
@@ -0,0 +1,578 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Versioned storage mode that allows to read the database state as of previous versions.
+//!
+//! When the history is enabled with [`set_retention`], every patch recorded with [`record`]
+//! is supplemented with the previous values of all the changed keys. These undo records
+//! are stored in the same database and are merged atomically with the patch itself, so
+//! [`snapshot_at`] can restore a read-only snapshot as of any version within the retention
+//! window by looking up the undo records of the later versions when the snapshot is read.
+//!
+//! Versions are arbitrary increasing numbers; the blockchain uses block heights as versions.
+//! Changes recorded with version `v` are the changes made after version `v - 1` was reached.
+//!
+//! [`set_retention`]: fn.set_retention.html
+//! [`record`]: fn.record.html
+//! [`snapshot_at`]: fn.snapshot_at.html
+
+use byteorder::{ByteOrder, BigEndian};
+
+use std::cmp::{self, Ordering};
+use std::collections::Bound::{self, Excluded, Included, Unbounded};
+
+use super::{Database, Fork, Iter, Iterator, Patch, Result, Snapshot};
+
+/// Name of the column family that contains the undo records and the history settings.
+pub const HISTORY_TABLE_NAME: &str = "__HISTORY__";
+
+const VERSION_SIZE: usize = 8;
+const NAME_LEN_SIZE: usize = 4;
+const STATE_SIZE: usize = 17;
+
+/// Settings and bounds of the stored history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct HistoryState {
+    /// Number of the latest versions that can be restored.
+    retention: u64,
+    /// The earliest version that can be restored, or `None` if nothing is recorded yet.
+    oldest: Option<u64>,
+}
+
+impl HistoryState {
+    fn get(view: &Snapshot) -> Option<Self> {
+        view.get(HISTORY_TABLE_NAME, &[]).map(|buf| {
+            HistoryState {
+                retention: BigEndian::read_u64(&buf[0..8]),
+                oldest: if buf[8] == 0 {
+                    None
+                } else {
+                    Some(BigEndian::read_u64(&buf[9..17]))
+                },
+            }
+        })
+    }
+
+    fn put(&self, fork: &mut Fork) {
+        let mut buf = vec![0; STATE_SIZE];
+        BigEndian::write_u64(&mut buf[0..8], self.retention);
+        if let Some(oldest) = self.oldest {
+            buf[8] = 1;
+            BigEndian::write_u64(&mut buf[9..17], oldest);
+        }
+        fork.put(HISTORY_TABLE_NAME, Vec::new(), buf);
+    }
+}
+
+fn version_key(version: u64) -> Vec<u8> {
+    let mut buf = vec![0; VERSION_SIZE];
+    BigEndian::write_u64(&mut buf, version);
+    buf
+}
+
+fn record_key(version: u64, name: &str, key: &[u8]) -> Vec<u8> {
+    let mut buf = vec![0; VERSION_SIZE + NAME_LEN_SIZE];
+    BigEndian::write_u64(&mut buf[..VERSION_SIZE], version);
+    BigEndian::write_u32(&mut buf[VERSION_SIZE..], name.len() as u32);
+    buf.extend_from_slice(name.as_bytes());
+    buf.extend_from_slice(key);
+    buf
+}
+
+// Returns the smallest key, which is greater than all the keys starting with `prefix`,
+// or `None` if there is no such key.
+fn prefix_end(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut end = prefix.to_vec();
+    while let Some(byte) = end.pop() {
+        if byte < u8::max_value() {
+            end.push(byte + 1);
+            return Some(end);
+        }
+    }
+    None
+}
+
+// Decodes the value stored in an undo record.
+fn record_value(record: &[u8]) -> Option<Vec<u8>> {
+    if record[0] == 0 {
+        None
+    } else {
+        Some(record[1..].to_vec())
+    }
+}
+
+fn parse_record_key(buf: &[u8]) -> (u64, String, Vec<u8>) {
+    let name_start = VERSION_SIZE + NAME_LEN_SIZE;
+    let version = BigEndian::read_u64(&buf[..VERSION_SIZE]);
+    let name_len = BigEndian::read_u32(&buf[VERSION_SIZE..name_start]) as usize;
+    let name = String::from_utf8(buf[name_start..name_start + name_len].to_vec())
+        .expect("Malformed history record");
+    (version, name, buf[name_start + name_len..].to_vec())
+}
+
+/// Enables the history with the given retention window, or disables it if `retention`
+/// is `None`.
+///
+/// The retention window is the number of the latest versions, which can be restored
+/// in addition to the current one. Changing the retention window of the enabled history
+/// keeps the recorded versions; enabling or disabling the history removes all the recorded
+/// versions.
+///
+/// # Errors
+///
+/// Returns an error if the settings cannot be merged into the database.
+pub fn set_retention(db: &Database, retention: Option<u64>) -> Result<()> {
+    let mut fork = db.fork();
+    match (HistoryState::get(&fork), retention) {
+        (Some(mut state), Some(retention)) => {
+            state.retention = retention;
+            state.put(&mut fork);
+        }
+        (_, Some(retention)) => {
+            fork.remove_by_prefix(HISTORY_TABLE_NAME, None);
+            HistoryState {
+                retention,
+                oldest: None,
+            }.put(&mut fork);
+        }
+        (_, None) => fork.remove_by_prefix(HISTORY_TABLE_NAME, None),
+    }
+    db.merge(fork.into_patch())
+}
+
+/// Returns the retention window of the history, or `None` if the history is disabled.
+pub fn retention(view: &Snapshot) -> Option<u64> {
+    HistoryState::get(view).map(|state| state.retention)
+}
+
+/// Returns the earliest version that can be restored with [`snapshot_at`], or `None`
+/// if the history is disabled or no versions have been recorded yet.
+///
+/// [`snapshot_at`]: fn.snapshot_at.html
+pub fn oldest_version(view: &Snapshot) -> Option<u64> {
+    HistoryState::get(view).and_then(|state| state.oldest)
+}
+
+/// Records undo information for `patch`, which is about to be merged into the database
+/// as a part of the specified `version`, into `fork`. Also removes the undo records that
+/// are outside of the retention window.
+///
+/// `fork` should be created from the same database state that `patch` is based on,
+/// and should not contain any other changes. Both `fork` and `patch` should then be merged
+/// into the database atomically. Does nothing if the history is disabled.
+pub fn record(fork: &mut Fork, patch: &Patch, version: u64) {
+    let mut state = match HistoryState::get(fork) {
+        Some(state) => state,
+        None => return,
+    };
+
+    let cutoff = version.saturating_sub(state.retention);
+    let outdated = {
+        let mut keys = Vec::new();
+        let mut iter = fork.range(
+            HISTORY_TABLE_NAME,
+            &version_key(0),
+            &version_key(cutoff.saturating_add(1)),
+        );
+        while let Some((key, ..)) = iter.next() {
+            keys.push(key.to_vec());
+        }
+        keys
+    };
+    for key in outdated {
+        fork.remove(HISTORY_TABLE_NAME, key);
+    }
+
+    if version > cutoff {
+        for (name, changes) in patch.iter() {
+            if name == HISTORY_TABLE_NAME {
+                continue;
+            }
+            for (key, _) in changes.iter() {
+                let record_key = record_key(version, name, key);
+                // Only the earliest change within the version is relevant.
+                if fork.contains(HISTORY_TABLE_NAME, &record_key) {
+                    continue;
+                }
+                let record = match fork.get(name, key) {
+                    Some(value) => {
+                        let mut record = Vec::with_capacity(value.len() + 1);
+                        record.push(1);
+                        record.extend_from_slice(&value);
+                        record
+                    }
+                    None => vec![0],
+                };
+                fork.put(HISTORY_TABLE_NAME, record_key, record);
+            }
+        }
+    }
+
+    let oldest = cmp::max(
+        state.oldest.unwrap_or_else(|| version.saturating_sub(1)),
+        cutoff,
+    );
+    if state.oldest != Some(oldest) {
+        state.oldest = Some(oldest);
+        state.put(fork);
+    }
+}
+
+/// Returns a read-only snapshot of the database as of the specified version.
+///
+/// Returns `None` if the history is disabled or the version is outside of the retention
+/// window. The caller is responsible for checking that the version is not greater than
+/// the current one.
+///
+/// The undo records are not loaded in advance. Instead, every read of the snapshot looks up
+/// the records of each version recorded after the requested one, so the reads of the older
+/// versions are slower.
+pub fn snapshot_at(db: &Database, version: u64) -> Option<Box<Snapshot>> {
+    let snapshot = db.snapshot();
+    match oldest_version(&*snapshot) {
+        Some(oldest) if oldest <= version => {}
+        _ => return None,
+    }
+
+    let mut versions = Vec::new();
+    let mut next = version.checked_add(1);
+    while let Some(from) = next {
+        let recorded = {
+            let mut iter = snapshot.iter(HISTORY_TABLE_NAME, &version_key(from));
+            iter.next().map(|(key, _)| BigEndian::read_u64(&key[..VERSION_SIZE]))
+        };
+        match recorded {
+            Some(recorded) => {
+                versions.push(recorded);
+                next = recorded.checked_add(1);
+            }
+            None => break,
+        }
+    }
+    Some(Box::new(HistorySnapshot { snapshot, versions }))
+}
+
+/// A snapshot of the database as of a previous version.
+struct HistorySnapshot {
+    snapshot: Box<Snapshot>,
+    // Versions with undo records recorded after the restored version, in ascending order.
+    versions: Vec<u64>,
+}
+
+/// An iterator over the undo records of an index within a single version.
+struct RecordsIter<'a> {
+    iter: Iter<'a>,
+    prefix: Vec<u8>,
+}
+
+/// An iterator merging the current entries of an index with its undo records.
+struct HistoryIter<'a> {
+    base: Iter<'a>,
+    // Iterators over the records of the versions in ascending order.
+    records: Vec<RecordsIter<'a>>,
+    reverse: bool,
+    current: Option<(Vec<u8>, Vec<u8>)>,
+    peeked: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl HistorySnapshot {
+    fn history_iter<'a, F>(&'a self, base: Iter<'a>, reverse: bool, records: F) -> Iter<'a>
+    where
+        F: Fn(u64) -> RecordsIter<'a>,
+    {
+        Box::new(HistoryIter {
+            base,
+            records: self.versions.iter().map(|&version| records(version)).collect(),
+            reverse,
+            current: None,
+            peeked: None,
+        })
+    }
+}
+
+impl Snapshot for HistorySnapshot {
+    fn get(&self, name: &str, key: &[u8]) -> Option<Vec<u8>> {
+        // The record of the earliest version contains the value as of the restored version.
+        for &version in &self.versions {
+            let record = self.snapshot.get(
+                HISTORY_TABLE_NAME,
+                &record_key(version, name, key),
+            );
+            if let Some(record) = record {
+                return record_value(&record);
+            }
+        }
+        self.snapshot.get(name, key)
+    }
+
+    fn iter<'a>(&'a self, name: &str, from: &[u8]) -> Iter<'a> {
+        let snapshot = &self.snapshot;
+        self.history_iter(snapshot.iter(name, from), false, |version| {
+            RecordsIter {
+                iter: snapshot.iter(HISTORY_TABLE_NAME, &record_key(version, name, from)),
+                prefix: record_key(version, name, &[]),
+            }
+        })
+    }
+
+    fn iter_rev<'a>(&'a self, name: &str, to: Bound<&[u8]>) -> Iter<'a> {
+        let snapshot = &self.snapshot;
+        self.history_iter(snapshot.iter_rev(name, to), true, |version| {
+            let prefix = record_key(version, name, &[]);
+            let bound = match to {
+                Included(key) => Included(record_key(version, name, key)),
+                Excluded(key) => Excluded(record_key(version, name, key)),
+                Unbounded => prefix_end(&prefix).map_or(Unbounded, Excluded),
+            };
+            let iter = match bound {
+                Included(ref key) => snapshot.iter_rev(HISTORY_TABLE_NAME, Included(&key[..])),
+                Excluded(ref key) => snapshot.iter_rev(HISTORY_TABLE_NAME, Excluded(&key[..])),
+                Unbounded => snapshot.iter_rev(HISTORY_TABLE_NAME, Unbounded),
+            };
+            RecordsIter { iter, prefix }
+        })
+    }
+}
+
+impl<'a> RecordsIter<'a> {
+    // Returns the key of the index and the undo record, which the iterator points to.
+    fn peek(&mut self) -> Option<(&[u8], &[u8])> {
+        let prefix = &self.prefix;
+        match self.iter.peek() {
+            Some((key, record)) if key.starts_with(prefix) => Some((&key[prefix.len()..], record)),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> HistoryIter<'a> {
+    fn read_next(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let ordering = if self.reverse {
+            Ordering::Greater
+        } else {
+            Ordering::Less
+        };
+        loop {
+            let mut next_key = self.base.peek().map(|(key, _)| key.to_vec());
+            for records in &mut self.records {
+                if let Some((key, _)) = records.peek() {
+                    if next_key.as_ref().map_or(true, |next| key.cmp(next) == ordering) {
+                        next_key = Some(key.to_vec());
+                    }
+                }
+            }
+            let key = next_key?;
+
+            // The record of the earliest version takes precedence over the later records
+            // and the current value.
+            let mut value = None;
+            let mut is_resolved = false;
+            for records in &mut self.records {
+                let record = match records.peek() {
+                    Some((index_key, record)) if index_key == &key[..] => record_value(record),
+                    _ => continue,
+                };
+                records.iter.next();
+                if !is_resolved {
+                    value = record;
+                    is_resolved = true;
+                }
+            }
+            let base_value = match self.base.peek() {
+                Some((base_key, base_value)) if base_key == &key[..] => Some(base_value.to_vec()),
+                _ => None,
+            };
+            if base_value.is_some() {
+                self.base.next();
+            }
+            if !is_resolved {
+                value = base_value;
+            }
+
+            if let Some(value) = value {
+                return Some((key, value));
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for HistoryIter<'a> {
+    fn next(&mut self) -> Option<(&[u8], &[u8])> {
+        self.current = match self.peeked.take() {
+            Some(entry) => Some(entry),
+            None => self.read_next(),
+        };
+        self.current.as_ref().map(|&(ref key, ref value)| (&key[..], &value[..]))
+    }
+
+    fn peek(&mut self) -> Option<(&[u8], &[u8])> {
+        if self.peeked.is_none() {
+            self.peeked = self.read_next();
+        }
+        self.peeked.as_ref().map(|&(ref key, ref value)| (&key[..], &value[..]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use storage::{Database, MapIndex, MemoryDB};
+
+    const IDX_NAME: &'static str = "idx_name";
+
+    fn commit_version(db: &Database, version: u64, values: &[(u8, Option<u8>)]) {
+        let mut fork = db.fork();
+        {
+            let mut index = MapIndex::new(IDX_NAME, &mut fork);
+            for &(key, value) in values {
+                match value {
+                    Some(value) => index.put(&key, value),
+                    None => index.remove(&key),
+                }
+            }
+        }
+        let patch = fork.into_patch();
+        let mut history = db.fork();
+        record(&mut history, &patch, version);
+        history.merge(patch);
+        db.merge(history.into_patch()).unwrap();
+    }
+
+    fn map_state(snapshot: &Snapshot) -> Vec<(u8, u8)> {
+        let index: MapIndex<_, u8, u8> = MapIndex::new(IDX_NAME, snapshot);
+        index.iter().collect()
+    }
+
+    fn snapshots_at_versions(db: Box<Database>) {
+        assert!(snapshot_at(&*db, 0).is_none());
+        set_retention(&*db, Some(10)).unwrap();
+        assert_eq!(retention(&*db.snapshot()), Some(10));
+        assert_eq!(oldest_version(&*db.snapshot()), None);
+
+        commit_version(&*db, 0, &[(1, Some(1)), (2, Some(2))]);
+        commit_version(&*db, 1, &[(1, Some(10)), (3, Some(3))]);
+        commit_version(&*db, 2, &[(2, None), (3, Some(30))]);
+        // Several patches can be recorded within the same version.
+        commit_version(&*db, 3, &[(1, Some(100))]);
+        commit_version(&*db, 3, &[(1, None), (4, Some(4))]);
+        assert_eq!(oldest_version(&*db.snapshot()), Some(0));
+
+        let at = |version| map_state(&*snapshot_at(&*db, version).unwrap());
+        assert_eq!(at(0), vec![(1, 1), (2, 2)]);
+        assert_eq!(at(1), vec![(1, 10), (2, 2), (3, 3)]);
+        assert_eq!(at(2), vec![(1, 10), (3, 30)]);
+        assert_eq!(at(3), vec![(3, 30), (4, 4)]);
+        assert_eq!(at(3), map_state(&*db.snapshot()));
+
+        // Single keys and the reverse iteration are restored as well.
+        let snapshot = snapshot_at(&*db, 1).unwrap();
+        let index: MapIndex<_, u8, u8> = MapIndex::new(IDX_NAME, &snapshot);
+        assert_eq!(index.get(&1), Some(10));
+        assert_eq!(index.get(&2), Some(2));
+        assert!(index.contains(&3));
+        assert!(!index.contains(&4));
+        assert_eq!(
+            index.iter_rev().collect::<Vec<_>>(),
+            vec![(3, 3), (2, 2), (1, 10)]
+        );
+    }
+
+    fn history_retention(db: Box<Database>) {
+        set_retention(&*db, Some(2)).unwrap();
+        for version in 5..10 {
+            commit_version(&*db, version, &[(1, Some(version as u8))]);
+        }
+        assert_eq!(oldest_version(&*db.snapshot()), Some(7));
+        assert!(snapshot_at(&*db, 6).is_none());
+        assert_eq!(map_state(&*snapshot_at(&*db, 7).unwrap()), vec![(1, 7)]);
+        assert_eq!(map_state(&*snapshot_at(&*db, 8).unwrap()), vec![(1, 8)]);
+
+        let snapshot = db.snapshot();
+        let mut iter = snapshot.iter(HISTORY_TABLE_NAME, &version_key(0));
+        while let Some((key, ..)) = iter.next() {
+            assert!(parse_record_key(key).0 > 7);
+        }
+
+        // Changing the retention window keeps the recorded versions.
+        set_retention(&*db, Some(5)).unwrap();
+        assert_eq!(map_state(&*snapshot_at(&*db, 7).unwrap()), vec![(1, 7)]);
+
+        // Disabling the history removes the recorded versions.
+        set_retention(&*db, None).unwrap();
+        assert!(snapshot_at(&*db, 9).is_none());
+        commit_version(&*db, 10, &[(1, Some(10))]);
+        let snapshot = db.snapshot();
+        assert!(snapshot.iter(HISTORY_TABLE_NAME, &[]).next().is_none());
+
+        set_retention(&*db, Some(5)).unwrap();
+        commit_version(&*db, 11, &[(1, Some(11))]);
+        assert!(snapshot_at(&*db, 9).is_none());
+        assert_eq!(map_state(&*snapshot_at(&*db, 10).unwrap()), vec![(1, 10)]);
+    }
+
+    #[test]
+    fn test_record_key_roundtrip() {
+        let key = record_key(5, "name", &[1, 2, 3]);
+        assert_eq!(parse_record_key(&key), (5, "name".to_owned(), vec![1, 2, 3]));
+    }
+
+    mod memorydb_tests {
+        use storage::{Database, MemoryDB};
+
+        fn create_database() -> Box<Database> {
+            Box::new(MemoryDB::new())
+        }
+
+        #[test]
+        fn test_snapshots_at_versions() {
+            super::snapshots_at_versions(create_database());
+        }
+
+        #[test]
+        fn test_history_retention() {
+            super::history_retention(create_database());
+        }
+    }
+
+    mod rocksdb_tests {
+        use std::path::Path;
+        use tempdir::TempDir;
+        use storage::{Database, DbOptions, RocksDB};
+
+        fn create_database(path: &Path) -> Box<Database> {
+            let opts = DbOptions::default();
+            Box::new(RocksDB::open(path, &opts).unwrap())
+        }
+
+        #[test]
+        fn test_snapshots_at_versions() {
+            let dir = TempDir::new("exonum_history1").unwrap();
+            super::snapshots_at_versions(create_database(dir.path()));
+        }
+
+        #[test]
+        fn test_history_retention() {
+            let dir = TempDir::new("exonum_history2").unwrap();
+            super::history_retention(create_database(dir.path()));
+        }
+    }
+
+    #[test]
+    fn test_history_disabled() {
+        let db = MemoryDB::new();
+        commit_version(&db, 0, &[(1, Some(1))]);
+        assert!(snapshot_at(&db, 0).is_none());
+        assert!(db.snapshot().iter(HISTORY_TABLE_NAME, &[]).next().is_none());
+    }
+}
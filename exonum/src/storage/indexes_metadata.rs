@@ -16,6 +16,7 @@ use std::borrow::Cow;
 use std::error::Error;
 
 use storage::{BaseIndex, Fork, Snapshot, StorageValue};
+use storage::history::HISTORY_TABLE_NAME;
 use crypto::{CryptoHash, Hash};
 use encoding::{Field, Offset, CheckedOffset, Error as EncodingError};
 use encoding::serialize::{WriteBufferWrapper, json};
@@ -29,23 +30,73 @@ encoding_struct!(
     }
 );
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// Type of an index, which is recorded in the indexes metadata when the index is created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum IndexType {
+    /// [`Entry`](struct.Entry.html).
     Entry,
+    /// [`KeySetIndex`](key_set_index/struct.KeySetIndex.html).
     KeySet,
+    /// [`ListIndex`](list_index/struct.ListIndex.html).
     List,
+    /// [`SparseListIndex`](sparse_list_index/struct.SparseListIndex.html).
     SparseList,
+    /// [`MapIndex`](map_index/struct.MapIndex.html).
     Map,
+    /// [`ProofListIndex`](proof_list_index/struct.ProofListIndex.html).
     ProofList,
+    /// [`ProofMapIndex`](proof_map_index/struct.ProofMapIndex.html).
     ProofMap,
+    /// [`ValueSetIndex`](value_set_index/struct.ValueSetIndex.html).
     ValueSet,
+    /// [`HashedProofMapIndex`](hashed_proof_map_index/struct.HashedProofMapIndex.html).
+    HashedProofMap,
+    /// [`ProofKeySetIndex`](proof_key_set_index/struct.ProofKeySetIndex.html).
+    ProofKeySet,
 }
 
-impl From<u8> for IndexType {
-    fn from(num: u8) -> Self {
+/// Information about an index registered in the indexes metadata.
+///
+/// This struct is returned by the [`list_indexes`] and [`index_info`] functions.
+///
+/// [`list_indexes`]: fn.list_indexes.html
+/// [`index_info`]: fn.index_info.html
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexInfo {
+    /// Name of the index or of the index family.
+    pub name: String,
+    /// Type of the index.
+    pub index_type: IndexType,
+    /// `true` if the name corresponds to an index family.
+    pub is_family: bool,
+}
+
+/// Information about an index together with the size of its data.
+///
+/// This struct is returned by the [`index_details`] function.
+///
+/// [`index_details`]: fn.index_details.html
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexDetails {
+    /// Name of the index or of the index family.
+    pub name: String,
+    /// Type of the index.
+    pub index_type: IndexType,
+    /// `true` if the name corresponds to an index family.
+    pub is_family: bool,
+    /// Number of entries stored in the database for the index or for all the indexes
+    /// of the family. For Merkelized indexes, the internal nodes of the tree are counted too.
+    pub entries: u64,
+    /// Total size of the keys and values of the stored entries in bytes.
+    pub bytes: u64,
+}
+
+impl IndexType {
+    /// Converts a number into the index type, returning `None` for unknown numbers.
+    pub fn from_u8(num: u8) -> Option<Self> {
         use self::IndexType::*;
-        match num {
+        Some(match num {
             0 => Entry,
             1 => KeySet,
             2 => List,
@@ -54,14 +105,22 @@ impl From<u8> for IndexType {
             5 => ProofList,
             6 => ProofMap,
             7 => ValueSet,
-            invalid => {
-                panic!(
-                    "Unreachable pattern ({:?}) while constructing table type. \
-                    Storage data is probably corrupted",
-                    invalid
-                )
-            }
-        }
+            8 => HashedProofMap,
+            9 => ProofKeySet,
+            _ => return None,
+        })
+    }
+}
+
+impl From<u8> for IndexType {
+    fn from(num: u8) -> Self {
+        IndexType::from_u8(num).unwrap_or_else(|| {
+            panic!(
+                "Unreachable pattern ({:?}) while constructing table type. \
+                Storage data is probably corrupted",
+                num
+            )
+        })
     }
 }
 
@@ -124,6 +183,93 @@ impl json::ExonumJson for IndexType {
     }
 }
 
+/// Returns the information about all the indexes registered in the indexes metadata,
+/// sorted by their names.
+///
+/// Only the indexes metadata is read, so the sizes of the indexes are not returned;
+/// use [`index_details`] to get them for a particular index.
+///
+/// # Examples
+///
+/// ```
+/// use exonum::storage::{Database, MemoryDB, ListIndex, IndexType, list_indexes};
+///
+/// let db = MemoryDB::new();
+/// let mut fork = db.fork();
+/// ListIndex::new("list", &mut fork).extend(vec![1u8, 2, 3]);
+/// db.merge(fork.into_patch()).unwrap();
+///
+/// let indexes = list_indexes(&*db.snapshot());
+/// assert_eq!(indexes.len(), 1);
+/// assert_eq!(indexes[0].name, "list");
+/// assert_eq!(indexes[0].index_type, IndexType::List);
+/// ```
+///
+/// [`index_details`]: fn.index_details.html
+pub fn list_indexes(view: &Snapshot) -> Vec<IndexInfo> {
+    let metadata = BaseIndex::indexes_metadata(view);
+    metadata
+        .iter::<_, String, IndexMetadata>(&())
+        .map(|(name, metadata)| {
+            IndexInfo {
+                name,
+                index_type: metadata.index_type(),
+                is_family: metadata.is_family(),
+            }
+        })
+        .collect()
+}
+
+/// Returns the information about the index with the given name, or `None` if the index
+/// is not registered in the indexes metadata.
+pub fn index_info(view: &Snapshot, name: &str) -> Option<IndexInfo> {
+    let metadata = BaseIndex::indexes_metadata(view);
+    metadata.get::<_, IndexMetadata>(name).map(|metadata| {
+        IndexInfo {
+            name: name.to_owned(),
+            index_type: metadata.index_type(),
+            is_family: metadata.is_family(),
+        }
+    })
+}
+
+/// Returns the information about the index with the given name together with the size
+/// of its data, or `None` if the index is not registered in the indexes metadata.
+///
+/// Note that the entries of the index are iterated over to count them, so this function
+/// may take a considerable time for a large index.
+///
+/// # Examples
+///
+/// ```
+/// use exonum::storage::{Database, MemoryDB, ListIndex, index_details};
+///
+/// let db = MemoryDB::new();
+/// let mut fork = db.fork();
+/// ListIndex::new("list", &mut fork).extend(vec![1u8, 2, 3]);
+/// db.merge(fork.into_patch()).unwrap();
+///
+/// let details = index_details(&*db.snapshot(), "list").unwrap();
+/// // Three items and the length of the list.
+/// assert_eq!(details.entries, 4);
+/// ```
+pub fn index_details(view: &Snapshot, name: &str) -> Option<IndexDetails> {
+    let info = index_info(view, name)?;
+    let (mut entries, mut bytes) = (0, 0);
+    let mut iter = view.iter(name, &[]);
+    while let Some((key, value)) = iter.next() {
+        entries += 1;
+        bytes += (key.len() + value.len()) as u64;
+    }
+    Some(IndexDetails {
+        name: info.name,
+        index_type: info.index_type,
+        is_family: info.is_family,
+        entries,
+        bytes,
+    })
+}
+
 pub fn assert_index_type(name: &str, index_type: IndexType, is_family: bool, view: &Snapshot) {
     let metadata = BaseIndex::indexes_metadata(view);
     if let Some(value) = metadata.get::<_, IndexMetadata>(name) {
@@ -158,25 +304,59 @@ pub fn assert_index_type(name: &str, index_type: IndexType, is_family: bool, vie
 }
 
 pub fn set_index_type(name: &str, index_type: IndexType, is_family: bool, view: &mut Fork) {
-    if name == INDEXES_METADATA_TABLE_NAME {
-        panic!("Attempt to access an internal storage infrastructure");
-    }
+    assert_not_internal(name);
     let mut metadata = BaseIndex::indexes_metadata(view);
     if metadata.get::<_, IndexMetadata>(name).is_none() {
         metadata.put(&name.to_owned(), IndexMetadata::new(index_type, is_family));
     }
 }
 
+pub fn is_registered(name: &str, view: &Snapshot) -> bool {
+    let metadata = BaseIndex::indexes_metadata(view);
+    metadata.contains(name)
+}
+
+pub fn remove_index_type(name: &str, view: &mut Fork) {
+    let mut metadata = BaseIndex::indexes_metadata(view);
+    metadata.remove(name);
+}
+
+pub fn rename_index_type(old_name: &str, new_name: &str, view: &mut Fork) {
+    let mut metadata = BaseIndex::indexes_metadata(view);
+    if let Some(value) = metadata.get::<_, IndexMetadata>(old_name) {
+        metadata.remove(old_name);
+        metadata.put(&new_name.to_owned(), value);
+    }
+}
+
+pub fn assert_not_internal(name: &str) {
+    if name == INDEXES_METADATA_TABLE_NAME || name == HISTORY_TABLE_NAME {
+        panic!("Attempt to access an internal storage infrastructure");
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{IndexType, IndexMetadata, INDEXES_METADATA_TABLE_NAME};
-    use storage::{MemoryDB, Database, MapIndex, ProofMapIndex};
+    use super::{IndexType, IndexMetadata, INDEXES_METADATA_TABLE_NAME, index_details, index_info,
+                list_indexes};
+    use storage::{MemoryDB, Database, Entry, MapIndex, ProofMapIndex};
 
     #[test]
     fn index_metadata_roundtrip() {
         use self::IndexType::*;
-        let index_types = [Entry, KeySet, List, SparseList, Map, ProofList, ProofMap, ValueSet];
-        let is_family = [true, true, false, false, true, false, true, false];
+        let index_types = [
+            Entry,
+            KeySet,
+            List,
+            SparseList,
+            Map,
+            ProofList,
+            ProofMap,
+            ValueSet,
+            HashedProofMap,
+            ProofKeySet,
+        ];
+        let is_family = [true, true, false, false, true, false, true, false, true, false];
         for (t, f) in index_types.iter().zip(&is_family) {
             let metadata = IndexMetadata::new(*t, *f);
             assert_eq!(metadata.index_type(), *t);
@@ -184,6 +364,53 @@ mod tests {
         }
     }
 
+    #[test]
+    fn list_registered_indexes() {
+        let database = MemoryDB::new();
+        let mut fork = database.fork();
+        assert!(list_indexes(&fork).is_empty());
+        {
+            MapIndex::new("map", &mut fork).put(&1u8, vec![1u8; 10]);
+            MapIndex::new_in_family("family", &1u8, &mut fork).put(&1u8, 1u8);
+            MapIndex::new_in_family("family", &2u8, &mut fork).put(&1u8, 1u8);
+            ProofMapIndex::new("proof_map", &mut fork).put(&[0u8; 32], 1u8);
+            Entry::<_, u8>::new("entry", &mut fork).set(1);
+            Entry::<_, u8>::new("entry", &mut fork).remove();
+        }
+        database.merge(fork.into_patch()).unwrap();
+
+        let snapshot = database.snapshot();
+        let indexes = list_indexes(&*snapshot);
+        let summary = indexes
+            .iter()
+            .map(|info| (info.name.as_str(), info.index_type, info.is_family))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("entry", IndexType::Entry, false),
+                ("family", IndexType::Map, true),
+                ("map", IndexType::Map, false),
+                ("proof_map", IndexType::ProofMap, false),
+            ]
+        );
+        assert_eq!(index_info(&*snapshot, "map").as_ref(), Some(&indexes[2]));
+        assert_eq!(index_info(&*snapshot, "unknown"), None);
+
+        let sizes = indexes
+            .iter()
+            .map(|info| {
+                let details = index_details(&*snapshot, &info.name).unwrap();
+                assert_eq!(details.name, info.name);
+                assert_eq!(details.index_type, info.index_type);
+                assert_eq!(details.is_family, info.is_family);
+                (details.entries, details.bytes)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, vec![(0, 0), (2, 6), (1, 11), (1, 35)]);
+        assert_eq!(index_details(&*snapshot, "unknown"), None);
+    }
+
     #[test]
     fn access_indexes_metadata() {
         let database = MemoryDB::new();
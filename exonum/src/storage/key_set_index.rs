@@ -17,8 +17,9 @@
 use std::marker::PhantomData;
 use std::borrow::Borrow;
 
-use super::{BaseIndex, BaseIndexIter, Snapshot, Fork, StorageKey};
+use super::{BaseIndex, BaseIndexIter, Snapshot, Fork, KeyComponent, StorageKey};
 use super::indexes_metadata::IndexType;
+use super::keys::component_prefix;
 
 /// A set of items that implement `StorageKey` trait.
 ///
@@ -174,6 +175,74 @@ where
     pub fn iter_from(&self, from: &K) -> KeySetIndexIter<K> {
         KeySetIndexIter { base_iter: self.base.iter_from(&(), from) }
     }
+
+    /// An iterator visiting the elements in ascending order, which start with the specified
+    /// components of a [composite key][1]. The iterator element type is K.
+    ///
+    /// [1]: ../trait.KeyComponent.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, KeySetIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = KeySetIndex::new(name, &mut fork);
+    /// index.insert(("a".to_owned(), 1u8));
+    /// index.insert(("ab".to_owned(), 2));
+    ///
+    /// assert_eq!(vec![("a".to_owned(), 1)], index.iter_prefix("a").collect::<Vec<_>>());
+    /// ```
+    pub fn iter_prefix<P>(&self, prefix: &P) -> KeySetIndexIter<K>
+    where
+        P: KeyComponent + ?Sized,
+    {
+        KeySetIndexIter { base_iter: self.base.iter(&component_prefix(prefix)) }
+    }
+
+    /// An iterator visiting all elements in descending order. The iterator element type is K.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, KeySetIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = KeySetIndex::new(name, &mut fork);
+    /// index.insert(1u8);
+    /// index.insert(2);
+    ///
+    /// assert_eq!(vec![2, 1], index.iter_rev().collect::<Vec<_>>());
+    /// ```
+    pub fn iter_rev(&self) -> KeySetIndexIter<K> {
+        KeySetIndexIter { base_iter: self.base.iter_rev(&()) }
+    }
+
+    /// An iterator visiting the elements in the range `[from, to)` in ascending order.
+    /// The iterator element type is K.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, KeySetIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = KeySetIndex::new(name, &mut fork);
+    /// index.insert(1u8);
+    /// index.insert(2);
+    /// index.insert(3);
+    ///
+    /// assert_eq!(vec![2, 3], index.range(&2, &4).collect::<Vec<_>>());
+    /// ```
+    pub fn range(&self, from: &K, to: &K) -> KeySetIndexIter<K> {
+        KeySetIndexIter { base_iter: self.base.range(&(), from, to) }
+    }
 }
 
 impl<'a, K> KeySetIndex<&'a mut Fork, K>
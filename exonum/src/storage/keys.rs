@@ -291,6 +291,276 @@ impl StorageKey for SystemTime {
     }
 }
 
+/// A type that can be an element of a composite (tuple) key in the blockchain storage.
+///
+/// Unlike `StorageKey`, the encoding of a key component is self-delimiting, so several
+/// components can be concatenated and read back. The encoding preserves the order:
+/// composite keys are sorted by the first component, then by the second one, and so on.
+///
+/// Fixed-size types use their `StorageKey` encoding. Strings and byte sequences are
+/// escaped: each zero byte is written as `0x00 0xff`, and the sequence is terminated by
+/// `0x00 0x00`.
+///
+/// `StorageKey` is implemented for tuples of up to four elements, all of which but
+/// the last one must implement `KeyComponent`. The last element uses its `StorageKey`
+/// encoding, so it does not need to be self-delimiting.
+///
+/// # Examples
+///
+/// ```
+/// use exonum::crypto::PublicKey;
+/// use exonum::storage::{MemoryDB, Database, MapIndex};
+///
+/// let db = MemoryDB::new();
+/// let mut fork = db.fork();
+/// let mut index = MapIndex::new("transfers", &mut fork);
+/// let (alice, bob) = (PublicKey::new([1; 32]), PublicKey::new([2; 32]));
+/// index.put(&(alice, 2_u64), 20_u64);
+/// index.put(&(alice, 1), 10);
+/// index.put(&(bob, 1), 30);
+///
+/// let transfers = index.iter_prefix(&alice).collect::<Vec<_>>();
+/// assert_eq!(transfers, vec![((alice, 1), 10), ((alice, 2), 20)]);
+/// ```
+pub trait KeyComponent: StorageKey {
+    /// Returns the size of the serialized component in bytes.
+    fn component_size(&self) -> usize;
+
+    /// Serializes the component into the specified buffer of bytes.
+    ///
+    /// The caller must guarantee that the size of the buffer is equal to the precalculated size
+    /// of the serialized component.
+    fn write_component(&self, buffer: &mut [u8]);
+
+    /// Deserializes the component from the beginning of the specified buffer of bytes.
+    /// Returns the component and the number of bytes read.
+    fn read_component(buffer: &[u8]) -> (Self::Owned, usize);
+}
+
+macro_rules! key_component_for_fixed_size {
+    ($($type:ty => $size:expr;)+) => {
+        $(
+            impl KeyComponent for $type {
+                fn component_size(&self) -> usize {
+                    $size
+                }
+
+                fn write_component(&self, buffer: &mut [u8]) {
+                    self.write(buffer)
+                }
+
+                fn read_component(buffer: &[u8]) -> (Self::Owned, usize) {
+                    (Self::read(&buffer[..$size]), $size)
+                }
+            }
+        )+
+    }
+}
+
+key_component_for_fixed_size! {
+    () => 0;
+    u8 => 1;
+    i8 => 1;
+    u16 => 2;
+    i16 => 2;
+    u32 => 4;
+    i32 => 4;
+    u64 => 8;
+    i64 => 8;
+    Hash => HASH_SIZE;
+    PublicKey => PUBLIC_KEY_LENGTH;
+    SystemTime => 12;
+}
+
+const ESCAPE_BYTE: u8 = 0x00;
+const ESCAPED_ZERO: u8 = 0xff;
+const TERMINATOR: u8 = 0x00;
+
+fn escaped_size(bytes: &[u8]) -> usize {
+    let zeros = bytes.iter().filter(|&&byte| byte == ESCAPE_BYTE).count();
+    bytes.len() + zeros + 2
+}
+
+fn write_escaped(bytes: &[u8], buffer: &mut [u8]) {
+    let mut pos = 0;
+    for &byte in bytes {
+        buffer[pos] = byte;
+        pos += 1;
+        if byte == ESCAPE_BYTE {
+            buffer[pos] = ESCAPED_ZERO;
+            pos += 1;
+        }
+    }
+    buffer[pos] = ESCAPE_BYTE;
+    buffer[pos + 1] = TERMINATOR;
+}
+
+fn read_escaped(buffer: &[u8]) -> (Vec<u8>, usize) {
+    let mut bytes = Vec::new();
+    let mut pos = 0;
+    loop {
+        let byte = buffer[pos];
+        if byte == ESCAPE_BYTE {
+            match buffer[pos + 1] {
+                TERMINATOR => return (bytes, pos + 2),
+                ESCAPED_ZERO => bytes.push(ESCAPE_BYTE),
+                other => panic!("Invalid escape sequence in the key component: {}", other),
+            }
+            pos += 2;
+        } else {
+            bytes.push(byte);
+            pos += 1;
+        }
+    }
+}
+
+/// Uses the escaped encoding of bytes.
+impl KeyComponent for Vec<u8> {
+    fn component_size(&self) -> usize {
+        escaped_size(self)
+    }
+
+    fn write_component(&self, buffer: &mut [u8]) {
+        write_escaped(self, buffer)
+    }
+
+    fn read_component(buffer: &[u8]) -> (Self::Owned, usize) {
+        read_escaped(buffer)
+    }
+}
+
+/// Uses the escaped encoding of bytes.
+impl KeyComponent for [u8] {
+    fn component_size(&self) -> usize {
+        escaped_size(self)
+    }
+
+    fn write_component(&self, buffer: &mut [u8]) {
+        write_escaped(self, buffer)
+    }
+
+    fn read_component(buffer: &[u8]) -> (Self::Owned, usize) {
+        read_escaped(buffer)
+    }
+}
+
+/// Uses the escaped encoding of UTF-8 bytes.
+impl KeyComponent for String {
+    fn component_size(&self) -> usize {
+        escaped_size(self.as_bytes())
+    }
+
+    fn write_component(&self, buffer: &mut [u8]) {
+        write_escaped(self.as_bytes(), buffer)
+    }
+
+    fn read_component(buffer: &[u8]) -> (Self::Owned, usize) {
+        let (bytes, read) = read_escaped(buffer);
+        (unsafe { String::from_utf8_unchecked(bytes) }, read)
+    }
+}
+
+/// Uses the escaped encoding of UTF-8 bytes.
+impl KeyComponent for str {
+    fn component_size(&self) -> usize {
+        escaped_size(self.as_bytes())
+    }
+
+    fn write_component(&self, buffer: &mut [u8]) {
+        write_escaped(self.as_bytes(), buffer)
+    }
+
+    fn read_component(buffer: &[u8]) -> (Self::Owned, usize) {
+        String::read_component(buffer)
+    }
+}
+
+/// Serializes the prefix of a composite key, which can be used to iterate over
+/// all keys starting with the given components.
+pub(crate) fn component_prefix<P: KeyComponent + ?Sized>(prefix: &P) -> Vec<u8> {
+    let mut buffer = vec![0; prefix.component_size()];
+    prefix.write_component(&mut buffer);
+    buffer
+}
+
+// spell-checker:ignore tuples
+
+macro_rules! storage_key_for_tuples {
+    ($(($($name:ident: $index:tt),+; $last_name:ident: $last_index:tt))+) => {
+        $(
+            /// Uses the `KeyComponent` encoding for all elements except the last one,
+            /// which uses the `StorageKey` encoding.
+            impl<$($name,)+ $last_name> StorageKey for ($($name,)+ $last_name)
+            where
+                $($name: KeyComponent<Owned = $name> + Clone,)+
+                $last_name: StorageKey<Owned = $last_name> + Clone,
+            {
+                fn size(&self) -> usize {
+                    $(self.$index.component_size() +)+ self.$last_index.size()
+                }
+
+                fn write(&self, buffer: &mut [u8]) {
+                    let mut pos = 0;
+                    $(
+                        let size = self.$index.component_size();
+                        self.$index.write_component(&mut buffer[pos..pos + size]);
+                        pos += size;
+                    )+
+                    self.$last_index.write(&mut buffer[pos..]);
+                }
+
+                #[allow(non_snake_case)]
+                fn read(buffer: &[u8]) -> Self::Owned {
+                    let mut pos = 0;
+                    $(
+                        let ($name, read) = $name::read_component(&buffer[pos..]);
+                        pos += read;
+                    )+
+                    ($($name,)+ $last_name::read(&buffer[pos..]))
+                }
+            }
+
+            /// Concatenates the `KeyComponent` encodings of all elements.
+            impl<$($name,)+ $last_name> KeyComponent for ($($name,)+ $last_name)
+            where
+                $($name: KeyComponent<Owned = $name> + Clone,)+
+                $last_name: KeyComponent<Owned = $last_name> + Clone,
+            {
+                fn component_size(&self) -> usize {
+                    $(self.$index.component_size() +)+ self.$last_index.component_size()
+                }
+
+                fn write_component(&self, buffer: &mut [u8]) {
+                    let mut pos = 0;
+                    $(
+                        let size = self.$index.component_size();
+                        self.$index.write_component(&mut buffer[pos..pos + size]);
+                        pos += size;
+                    )+
+                    self.$last_index.write_component(&mut buffer[pos..]);
+                }
+
+                #[allow(non_snake_case)]
+                fn read_component(buffer: &[u8]) -> (Self::Owned, usize) {
+                    let mut pos = 0;
+                    $(
+                        let ($name, read) = $name::read_component(&buffer[pos..]);
+                        pos += read;
+                    )+
+                    let ($last_name, read) = $last_name::read_component(&buffer[pos..]);
+                    (($($name,)+ $last_name), pos + read)
+                }
+            }
+        )+
+    }
+}
+
+storage_key_for_tuples! {
+    (A: 0; B: 1)
+    (A: 0, B: 1; C: 2)
+    (A: 0, B: 1, C: 2; D: 3)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -574,6 +844,96 @@ mod tests {
         }
     }
 
+    #[test]
+    fn tuple_key_round_trip() {
+        let key = (
+            "a\0b".to_owned(),
+            vec![0_u8, 255, 0],
+            PublicKey::new([1; PUBLIC_KEY_LENGTH]),
+            -5_i64,
+        );
+        let mut buffer = get_buffer(&key);
+        key.write(&mut buffer);
+        assert_eq!(<(String, Vec<u8>, PublicKey, i64)>::read(&buffer), key);
+
+        let key = ((1_u8, "x".to_owned()), vec![0_u8; 3]);
+        let mut buffer = get_buffer(&key);
+        key.write(&mut buffer);
+        assert_eq!(<((u8, String), Vec<u8>)>::read(&buffer), key);
+    }
+
+    #[test]
+    fn bytes_component_encoding() {
+        let bytes = vec![1_u8, 0, 2];
+        let mut buffer = vec![0; bytes.component_size()];
+        bytes.write_component(&mut buffer);
+        assert_eq!(buffer, vec![1, 0, 0xff, 2, 0, 0]);
+        assert_eq!(Vec::<u8>::read_component(&buffer), (bytes, 6));
+    }
+
+    #[test]
+    fn tuple_key_ordering() {
+        use rand::{Rng, thread_rng};
+
+        let mut rng = thread_rng();
+        let mut gen_bytes = || {
+            let len = rng.gen_range(0, 4);
+            (0..len).map(|_| rng.gen_range(0_u8, 3)).collect::<Vec<_>>()
+        };
+        let mut keys = (0..1_000)
+            .map(|_| (gen_bytes(), gen_bytes(), gen_bytes()))
+            .collect::<Vec<_>>();
+        keys.sort();
+        keys.dedup();
+
+        let buffers = keys.iter()
+            .map(|key| {
+                let mut buffer = get_buffer(key);
+                key.write(&mut buffer);
+                buffer
+            })
+            .collect::<Vec<_>>();
+        for w in buffers.windows(2) {
+            assert!(w[0] < w[1]);
+        }
+    }
+
+    #[test]
+    fn test_tuple_key_prefix_in_index() {
+        use storage::{Database, MapIndex, MemoryDB};
+
+        let db: Box<Database> = Box::new(MemoryDB::new());
+        let mut fork = db.fork();
+        {
+            let mut index: MapIndex<_, (String, u8, u64), u64> =
+                MapIndex::new("test_index", &mut fork);
+            index.put(&("a".to_owned(), 1, 2), 1);
+            index.put(&("a".to_owned(), 1, 1), 2);
+            index.put(&("a".to_owned(), 2, 1), 3);
+            index.put(&("a\0".to_owned(), 1, 1), 4);
+            index.put(&("ab".to_owned(), 1, 1), 5);
+            index.put(&("".to_owned(), 1, 1), 6);
+        }
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        let index: MapIndex<_, (String, u8, u64), u64> = MapIndex::new("test_index", snapshot);
+        assert_eq!(index.values().collect::<Vec<_>>(), vec![6, 2, 1, 3, 4, 5]);
+        assert_eq!(index.values_prefix("a").collect::<Vec<_>>(), vec![2, 1, 3]);
+        assert_eq!(
+            index.keys_prefix(&("a".to_owned(), 1_u8)).collect::<Vec<_>>(),
+            vec![("a".to_owned(), 1, 1), ("a".to_owned(), 1, 2)]
+        );
+        assert_eq!(index.iter_prefix("b").count(), 0);
+        assert_eq!(
+            index
+                .iter_from(&("a".to_owned(), 1, 2))
+                .map(|(_, value)| value)
+                .collect::<Vec<_>>(),
+            vec![1, 3, 4, 5]
+        );
+    }
+
     fn get_buffer<T: StorageKey + ?Sized>(key: &T) -> Vec<u8> {
         vec![0; key.size()]
     }
@@ -248,6 +248,49 @@ where
     pub fn iter_from(&self, from: u64) -> ListIndexIter<V> {
         ListIndexIter { base_iter: self.base.iter_from(&(), &from) }
     }
+
+    /// Returns an iterator over the list in reverse order. The iterator element type is V.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ListIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = ListIndex::new(name, &mut fork);
+    ///
+    /// index.extend([1, 2, 3, 4, 5].iter().cloned());
+    ///
+    /// let latest = index.iter_rev().take(2).collect::<Vec<_>>();
+    /// assert_eq!(vec![5, 4], latest);
+    /// ```
+    pub fn iter_rev(&self) -> ListIndexIter<V> {
+        ListIndexIter { base_iter: self.base.range_rev(&(), &0u64, &self.len()) }
+    }
+
+    /// Returns an iterator over the items of the list with positions in the range `[from, to)`.
+    /// The iterator element type is V.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ListIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = ListIndex::new(name, &mut fork);
+    ///
+    /// index.extend([1, 2, 3, 4, 5].iter().cloned());
+    ///
+    /// let items = index.range(1, 3).collect::<Vec<_>>();
+    /// assert_eq!(vec![2, 3], items);
+    /// ```
+    pub fn range(&self, from: u64, to: u64) -> ListIndexIter<V> {
+        ListIndexIter { base_iter: self.base.range(&(), &from, &to) }
+    }
 }
 
 impl<'a, V> ListIndex<&'a mut Fork, V>
@@ -512,6 +555,14 @@ mod tests {
             list_index.iter_from(3).collect::<Vec<u8>>(),
             Vec::<u8>::new()
         );
+
+        assert_eq!(list_index.iter_rev().collect::<Vec<u8>>(), vec![3, 2, 1]);
+        assert_eq!(list_index.range(0, 3).collect::<Vec<u8>>(), vec![1, 2, 3]);
+        assert_eq!(list_index.range(1, 2).collect::<Vec<u8>>(), vec![2]);
+        assert_eq!(
+            list_index.range(2, 2).collect::<Vec<u8>>(),
+            Vec::<u8>::new()
+        );
     }
 
     mod memorydb_tests {
@@ -17,8 +17,9 @@
 use std::marker::PhantomData;
 use std::borrow::Borrow;
 
-use super::{BaseIndex, BaseIndexIter, Snapshot, Fork, StorageKey, StorageValue};
+use super::{BaseIndex, BaseIndexIter, Snapshot, Fork, KeyComponent, StorageKey, StorageValue};
 use super::indexes_metadata::IndexType;
+use super::keys::component_prefix;
 
 /// A map of keys and values.
 ///
@@ -325,6 +326,135 @@ where
     {
         MapIndexValues { base_iter: self.base.iter_from(&(), from) }
     }
+
+    /// Returns an iterator over the entries of the map in ascending order, the keys of which
+    /// start with the specified components of a [composite key][1]. The iterator element type
+    /// is (K, V).
+    ///
+    /// [1]: ../trait.KeyComponent.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, MapIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = MapIndex::new(name, &mut fork);
+    /// index.put(&(1u8, "a".to_owned()), 10u8);
+    /// index.put(&(2, "b".to_owned()), 20);
+    ///
+    /// assert_eq!(
+    ///     vec![((2, "b".to_owned()), 20)],
+    ///     index.iter_prefix(&2u8).collect::<Vec<_>>()
+    /// );
+    /// ```
+    pub fn iter_prefix<P>(&self, prefix: &P) -> MapIndexIter<K, V>
+    where
+        P: KeyComponent + ?Sized,
+    {
+        MapIndexIter { base_iter: self.base.iter(&component_prefix(prefix)) }
+    }
+
+    /// Returns an iterator over the keys of the map in ascending order, which start
+    /// with the specified components of a [composite key][1]. The iterator element type is K.
+    ///
+    /// [1]: ../trait.KeyComponent.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, MapIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let snapshot = db.snapshot();
+    /// let index: MapIndex<_, (u8, u8), u8> = MapIndex::new(name, &snapshot);
+    ///
+    /// for key in index.keys_prefix(&2u8) {
+    ///     println!("{:?}", key);
+    /// }
+    /// ```
+    pub fn keys_prefix<P>(&self, prefix: &P) -> MapIndexKeys<K>
+    where
+        P: KeyComponent + ?Sized,
+    {
+        MapIndexKeys { base_iter: self.base.iter(&component_prefix(prefix)) }
+    }
+
+    /// Returns an iterator over the values of the map in ascending order of keys, which start
+    /// with the specified components of a [composite key][1]. The iterator element type is V.
+    ///
+    /// [1]: ../trait.KeyComponent.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, MapIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let snapshot = db.snapshot();
+    /// let index: MapIndex<_, (u8, u8), u8> = MapIndex::new(name, &snapshot);
+    ///
+    /// for val in index.values_prefix(&2u8) {
+    ///     println!("{}", val);
+    /// }
+    /// ```
+    pub fn values_prefix<P>(&self, prefix: &P) -> MapIndexValues<V>
+    where
+        P: KeyComponent + ?Sized,
+    {
+        MapIndexValues { base_iter: self.base.iter(&component_prefix(prefix)) }
+    }
+
+    /// Returns an iterator over the entries of the map in descending order. The iterator element
+    /// type is (K, V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, MapIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = MapIndex::new(name, &mut fork);
+    /// index.put(&1u8, 10u8);
+    /// index.put(&2, 20);
+    ///
+    /// assert_eq!(vec![(2, 20), (1, 10)], index.iter_rev().collect::<Vec<_>>());
+    /// ```
+    pub fn iter_rev(&self) -> MapIndexIter<K, V> {
+        MapIndexIter { base_iter: self.base.iter_rev(&()) }
+    }
+
+    /// Returns an iterator over the entries of the map with the keys in the range `[from, to)`
+    /// in ascending order. The iterator element type is (K, V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, MapIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = MapIndex::new(name, &mut fork);
+    /// index.put(&1u8, 10u8);
+    /// index.put(&2, 20);
+    /// index.put(&3, 30);
+    ///
+    /// assert_eq!(vec![(1, 10), (2, 20)], index.range(&0, &3).collect::<Vec<_>>());
+    /// ```
+    pub fn range<Q>(&self, from: &Q, to: &Q) -> MapIndexIter<K, V>
+    where
+        K: Borrow<Q>,
+        Q: StorageKey + ?Sized,
+    {
+        MapIndexIter { base_iter: self.base.range(&(), from, to) }
+    }
 }
 
 impl<'a, K, V> MapIndex<&'a mut Fork, K, V>
@@ -548,6 +678,19 @@ mod tests {
             Vec::<u8>::new()
         );
 
+        assert_eq!(
+            map_index.iter_rev().collect::<Vec<(u8, u8)>>(),
+            vec![(3, 3), (2, 2), (1, 1)]
+        );
+        assert_eq!(
+            map_index.range(&0, &3).collect::<Vec<(u8, u8)>>(),
+            vec![(1, 1), (2, 2)]
+        );
+        assert_eq!(
+            map_index.range(&2, &10).collect::<Vec<(u8, u8)>>(),
+            vec![(2, 2), (3, 3)]
+        );
+
         map_index.remove(&1u8);
         assert_eq!(
             map_index.iter_from(&0u8).collect::<Vec<(u8, u8)>>(),
@@ -18,6 +18,7 @@ use std::sync::{Arc, RwLock};
 use std::clone::Clone;
 use std::collections::btree_map::BTreeMap;
 use std::collections::HashMap;
+use std::collections::Bound::{self, Unbounded};
 
 use super::{Database, Snapshot, Patch, Iterator, Iter, Result};
 use super::db::Change;
@@ -106,6 +107,22 @@ impl Snapshot for MemoryDB {
 
         Box::new(MemoryDBIter { data, index: 0 })
     }
+
+    fn iter_rev(&self, name: &str, to: Bound<&[u8]>) -> Iter {
+        let map_guard = self.map.read().unwrap();
+        let data = match map_guard.get(name) {
+            Some(table) => {
+                table
+                    .range::<[u8], _>((Unbounded, to))
+                    .rev()
+                    .map(|(k, v)| (k.to_vec(), v.to_vec()))
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+
+        Box::new(MemoryDBIter { data, index: 0 })
+    }
 }
 
 impl Iterator for MemoryDBIter {
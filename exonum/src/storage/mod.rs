@@ -24,7 +24,10 @@
 //! that is, the Exonum process has exclusive access to the DB during blockchain operation.
 //! You can interact with the `Database` from multiple threads by cloning its instance.
 //!
-//! Exonum provides two database types: [`RocksDB`] and [`MemoryDB`].
+//! Exonum provides three database types: [`RocksDB`], [`MemoryDB`] and [`FileDB`].
+//! `FileDB` is a dependency-free log-structured database stored in a single file.
+//! A `RocksDB` database can also be opened as a [`ReadOnlyRocksDB`], for example, to inspect
+//! the data of a running node.
 //!
 //! # Snapshot and Fork
 //!
@@ -42,12 +45,22 @@
 //! as a [`Patch`]. A patch can be atomically [`merge`]d into a database. Different threads
 //! may call `merge` concurrently.
 //!
+//! A patch can be encoded into a portable binary format with [`Patch::encode`] or
+//! [`PatchWriter`], for example, to be shipped to a replica or stored as a delta backup.
+//! An encoded patch is decoded with [`PatchReader`] and can be applied to a database
+//! with [`merge_encoded`].
+//!
 //! # `StorageKey` and `StorageValue` traits
 //!
 //! If you need to use your own data types as keys or values in the storage, you need to implement
 //! the [`StorageKey`] or [`StorageValue`] traits respectively. These traits are already
 //! implemented for most standard types.
 //!
+//! Tuples of up to four elements can be used as composite keys if their elements implement
+//! the [`KeyComponent`] trait. The order of composite keys is preserved in the storage, and
+//! the entries with the keys starting with the given components can be iterated over with
+//! the `iter_prefix` method of [`MapIndex`].
+//!
 //! # Indices
 //!
 //! Indices are structures representing data collections stored in the database.
@@ -76,6 +89,10 @@
 //!   proofs of existence and is implemented as a Merkle tree.
 //! - [`ProofMapIndex`] is a Merkelized version of `MapIndex` that supports cryptographic
 //!   proofs of existence and is implemented as a binary Merkle Patricia tree.
+//! - [`HashedProofMapIndex`] is a version of `ProofMapIndex` that accepts keys of arbitrary
+//!   length by hashing them to get a path in the Merkle Patricia tree.
+//! - [`ProofKeySetIndex`] is a Merkelized version of `KeySetIndex` that supports cryptographic
+//!   proofs of membership and non-membership of elements.
 //! - [`KeySetIndex`] and [`ValueSetIndex`] is a set of items, similar to [`BTreeSet`] and
 //!   [`HashSet`].
 //!
@@ -84,13 +101,20 @@
 //! [`Database`]: trait.Database.html
 //! [`RocksDB`]: struct.RocksDB.html
 //! [`MemoryDB`]: struct.MemoryDB.html
+//! [`FileDB`]: struct.FileDB.html
+//! [`ReadOnlyRocksDB`]: struct.ReadOnlyRocksDB.html
 //! [`Snapshot`]: trait.Snapshot.html
 //! [`Fork`]: struct.Fork.html
 //! [`Patch`]: struct.Patch.html
 //! [1]: trait.Database.html#tymethod.snapshot
 //! [2]: trait.Database.html#method.fork
 //! [`merge`]: trait.Database.html#tymethod.merge
+//! [`Patch::encode`]: struct.Patch.html#method.encode
+//! [`PatchWriter`]: struct.PatchWriter.html
+//! [`PatchReader`]: struct.PatchReader.html
+//! [`merge_encoded`]: trait.Database.html#method.merge_encoded
 //! [`StorageKey`]: trait.StorageKey.html
+//! [`KeyComponent`]: trait.KeyComponent.html
 //! [`StorageValue`]: trait.StorageValue.html
 //! [`Entry`]: struct.Entry.html
 //! [`ListIndex`]: list_index/struct.ListIndex.html
@@ -98,6 +122,8 @@
 //! [`MapIndex`]: map_index/struct.MapIndex.html
 //! [`ProofListIndex`]: proof_list_index/struct.ProofListIndex.html
 //! [`ProofMapIndex`]: proof_map_index/struct.ProofMapIndex.html
+//! [`HashedProofMapIndex`]: hashed_proof_map_index/struct.HashedProofMapIndex.html
+//! [`ProofKeySetIndex`]: proof_key_set_index/struct.ProofKeySetIndex.html
 //! [`KeySetIndex`]: key_set_index/struct.KeySetIndex.html
 //! [`ValueSetIndex`]: value_set_index/struct.ValueSetIndex.html
 //! [`BaseIndex`]: base_index/struct.BaseIndex.html
@@ -110,14 +136,21 @@
 //! [`HashSet`]: https://doc.rust-lang.org/std/collections/struct.HashSet.html
 
 pub use self::error::Error;
-pub use self::db::{Database, Snapshot, Fork, Patch, PatchIterator, Change, Changes,
-                   ChangesIterator, Iterator, Iter};
+pub use self::db::{Database, Snapshot, Fork, Savepoint, Patch, PatchIterator, Change, Changes,
+                   ChangesIterator, Iterator, Iter, OperationsLimitExceeded, TimeLimitExceeded};
 
-pub use self::options::DbOptions;
+pub use self::options::{CompressionType, DbOptions};
+pub use self::indexes_metadata::{IndexDetails, IndexInfo, IndexType, index_details, index_info,
+                                 list_indexes};
+pub use self::patch_encoding::{PatchReader, PatchWriter, PATCH_FORMAT_VERSION};
+pub use self::stats::{IndexStats, MeteredDB, PatchStats, StorageStats};
 pub use self::rocksdb::RocksDB;
+pub use self::read_only::ReadOnlyRocksDB;
 pub use self::memorydb::MemoryDB;
+pub use self::filedb::FileDB;
+pub use self::cache::{CachedDB, CacheStats};
 
-pub use self::keys::StorageKey;
+pub use self::keys::{KeyComponent, StorageKey};
 pub use self::values::StorageValue;
 
 pub use self::entry::Entry;
@@ -130,6 +163,8 @@ pub use self::key_set_index::KeySetIndex;
 pub use self::value_set_index::ValueSetIndex;
 pub use self::proof_list_index::{ProofListIndex, ListProof};
 pub use self::proof_map_index::{ProofMapIndex, MapProof};
+pub use self::hashed_proof_map_index::{HashedProofMapIndex, HashedMapProof};
+pub use self::proof_key_set_index::{ProofKeySetIndex, KeySetProof};
 
 /// A specialized `Result` type for I/O operations with storage.
 pub type Result<T> = ::std::result::Result<T, Error>;
@@ -137,8 +172,13 @@ pub type Result<T> = ::std::result::Result<T, Error>;
 mod error;
 mod db;
 mod options;
+mod patch_encoding;
+mod stats;
 mod rocksdb;
+mod read_only;
 mod memorydb;
+mod filedb;
+mod cache;
 
 mod keys;
 mod values;
@@ -146,6 +186,8 @@ mod values;
 mod entry;
 
 pub mod base_index;
+pub mod history;
+pub mod archive;
 mod indexes_metadata;
 
 pub mod map_index;
@@ -155,6 +197,8 @@ pub mod key_set_index;
 pub mod value_set_index;
 pub mod proof_list_index;
 pub mod proof_map_index;
+pub mod hashed_proof_map_index;
+pub mod proof_key_set_index;
 
 #[cfg(test)]
 mod tests;
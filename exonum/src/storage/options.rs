@@ -25,6 +25,86 @@ pub struct DbOptions {
     ///
     /// Defaults to `true`.
     pub create_if_missing: bool,
+    /// Number of the latest blocks, as of which the blockchain state can be read
+    /// in addition to the current state. See the [`history`] module for details.
+    ///
+    /// Defaults to `None`, which means the history is not stored.
+    ///
+    /// [`history`]: history/index.html
+    #[serde(default)]
+    pub history_retention: Option<u64>,
+    /// Total size in bytes of the keys and values kept in the read cache of the database.
+    /// See [`CachedDB`] for details.
+    ///
+    /// Defaults to `None`, which means the cache is disabled.
+    ///
+    /// [`CachedDB`]: struct.CachedDB.html
+    #[serde(default)]
+    pub cache_size: Option<usize>,
+    /// Compression algorithm applied to the stored data blocks.
+    ///
+    /// Defaults to `None`, which means the default algorithm of the backend is used
+    /// (Snappy for `RocksDB`).
+    #[serde(default)]
+    pub compression_type: Option<CompressionType>,
+    /// Size in bytes of the LRU cache of the uncompressed data blocks.
+    ///
+    /// Defaults to `None`, which means the default size of the backend is used
+    /// (8 MB for `RocksDB`).
+    #[serde(default)]
+    pub block_cache_size: Option<usize>,
+    /// Size in bytes of the data accumulated in memory before it is flushed to the disk.
+    ///
+    /// Defaults to `None`, which means the default size of the backend is used
+    /// (64 MB for `RocksDB`).
+    #[serde(default)]
+    pub write_buffer_size: Option<usize>,
+    /// Number of bits per key in the Bloom filters, which speed up the lookups of the keys
+    /// that are absent in the database. The value of 10 gives about 1% of false positives.
+    ///
+    /// Defaults to `None`, which means Bloom filters are not used.
+    #[serde(default)]
+    pub bloom_filter_bits_per_key: Option<i32>,
+    /// Whether the write-ahead log is synced to the disk on every merge, even if
+    /// `Database::merge` rather than `Database::merge_sync` is used.
+    ///
+    /// Defaults to `false`.
+    #[serde(default)]
+    pub wal_sync: bool,
+    /// Whether the database is opened in the read-only mode. A read-only database
+    /// can be opened while it is used by another process; it provides snapshots, but
+    /// rejects merges. See [`ReadOnlyRocksDB`] for details.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// [`ReadOnlyRocksDB`]: struct.ReadOnlyRocksDB.html
+    #[serde(default)]
+    pub read_only: bool,
+    /// Whether the storage operations are counted per index. See [`MeteredDB`] for details.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// [`MeteredDB`]: struct.MeteredDB.html
+    #[serde(default)]
+    pub storage_stats: bool,
+}
+
+/// Compression algorithms supported by databases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionType {
+    /// No compression.
+    None,
+    /// Snappy compression.
+    Snappy,
+    /// Zlib compression.
+    Zlib,
+    /// Bzip2 compression.
+    Bz2,
+    /// LZ4 compression.
+    Lz4,
+    /// LZ4 high compression.
+    Lz4hc,
 }
 
 impl Default for DbOptions {
@@ -32,6 +112,49 @@ impl Default for DbOptions {
         Self {
             max_open_files: None,
             create_if_missing: true,
+            history_retention: None,
+            cache_size: None,
+            compression_type: None,
+            block_cache_size: None,
+            write_buffer_size: None,
+            bloom_filter_bits_per_key: None,
+            wal_sync: false,
+            read_only: false,
+            storage_stats: false,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use toml;
+
+    use super::{CompressionType, DbOptions};
+
+    #[test]
+    fn test_db_options_from_toml() {
+        let options: DbOptions = toml::from_str(
+            r#"
+            create_if_missing = true
+            max_open_files = 1024
+            compression_type = "lz4"
+            block_cache_size = 134217728
+            write_buffer_size = 67108864
+            bloom_filter_bits_per_key = 10
+            wal_sync = true
+            "#,
+        ).unwrap();
+        assert_eq!(options.max_open_files, Some(1024));
+        assert_eq!(options.compression_type, Some(CompressionType::Lz4));
+        assert_eq!(options.block_cache_size, Some(128 * 1024 * 1024));
+        assert_eq!(options.write_buffer_size, Some(64 * 1024 * 1024));
+        assert_eq!(options.bloom_filter_bits_per_key, Some(10));
+        assert!(options.wal_sync);
+
+        // The tuning options can be omitted.
+        let options: DbOptions = toml::from_str("create_if_missing = false").unwrap();
+        assert!(!options.create_if_missing);
+        assert_eq!(options.compression_type, None);
+        assert!(!options.wal_sync);
+    }
+}
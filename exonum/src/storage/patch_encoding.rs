@@ -0,0 +1,553 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Portable binary encoding of `Patch`.
+//!
+//! An encoded patch has the following layout (all integers are little-endian):
+//!
+//! - Header: the magic bytes `EXNPATCH` followed by the format version (`u16`).
+//! - Records, each starting with a one-byte tag:
+//!   - `0x01`: start of an index; name length (`u32`) and the UTF-8 name.
+//!   - `0x02`: `Put` change; key length (`u32`), key, value length (`u32`) and value.
+//!   - `0x03`: `Delete` change; key length (`u32`) and key.
+//!   - `0x00`: end of the patch; followed by the SHA-256 hash of all the preceding bytes
+//!     (including this tag), which serves as a checksum.
+//!
+//! Each index is started at most once, and changes within an index are listed
+//! in the strictly ascending order of keys, so equal patches have equal encodings.
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use std::collections::HashSet;
+use std::io::{self, Read, Write};
+use std::iter::Iterator as StdIterator;
+use std::mem;
+
+use crypto::{Hash, HashStream, HASH_SIZE};
+use super::{Change, Error, Patch, Result};
+
+const PATCH_MAGIC: &[u8; 8] = b"EXNPATCH";
+/// Version of the patch encoding format produced by `PatchWriter`.
+pub const PATCH_FORMAT_VERSION: u16 = 1;
+
+const TAG_END: u8 = 0;
+const TAG_INDEX: u8 = 1;
+const TAG_PUT: u8 = 2;
+const TAG_DELETE: u8 = 3;
+
+impl Patch {
+    /// Encodes the patch into the portable binary format and writes it to `writer`.
+    /// Returns the writer back after the encoding is finished.
+    ///
+    /// See [`PatchWriter`](struct.PatchWriter.html) for details.
+    pub fn encode<W: Write>(&self, writer: W) -> io::Result<W> {
+        let mut indexes = self.iter().collect::<Vec<_>>();
+        indexes.sort_by(|x, y| x.0.cmp(y.0));
+
+        let mut writer = PatchWriter::new(writer)?;
+        for (name, changes) in indexes {
+            writer.start_index(name)?;
+            for (key, change) in changes.iter() {
+                writer.write_change(key, change)?;
+            }
+        }
+        writer.finish()
+    }
+
+    /// Reads a patch encoded into the portable binary format from `reader`.
+    ///
+    /// See [`PatchReader`](struct.PatchReader.html) for details.
+    pub fn decode<R: Read>(reader: R) -> Result<Patch> {
+        PatchReader::new(reader)?.into_patch()
+    }
+}
+
+/// Streaming encoder of a patch into the portable binary format.
+///
+/// Changes are written to the underlying writer as soon as they are passed to the encoder,
+/// so a patch can be encoded without building it in memory. The encoder checks that
+/// every index is started only once and that the keys within an index are strictly
+/// ascending; otherwise, an error of the `InvalidInput` kind is returned.
+///
+/// # Examples
+///
+/// ```
+/// use exonum::storage::{Change, PatchWriter, Patch};
+///
+/// let mut writer = PatchWriter::new(Vec::new()).unwrap();
+/// writer.start_index("index").unwrap();
+/// writer.write_change(&[1], &Change::Put(vec![10])).unwrap();
+/// writer.write_change(&[2], &Change::Delete).unwrap();
+/// let bytes = writer.finish().unwrap();
+///
+/// let patch = Patch::decode(&bytes[..]).unwrap();
+/// assert_eq!(patch.len(), 2);
+/// ```
+#[derive(Debug)]
+pub struct PatchWriter<W> {
+    writer: ChecksumWriter<W>,
+    names: HashSet<String>,
+    in_index: bool,
+    last_key: Option<Vec<u8>>,
+}
+
+impl<W: Write> PatchWriter<W> {
+    /// Creates a new encoder and writes the header to `writer`.
+    pub fn new(writer: W) -> io::Result<Self> {
+        Ok(PatchWriter {
+            writer: ChecksumWriter::new(writer, PATCH_MAGIC, PATCH_FORMAT_VERSION)?,
+            names: HashSet::new(),
+            in_index: false,
+            last_key: None,
+        })
+    }
+
+    /// Starts changes of the index with the given name.
+    pub fn start_index(&mut self, name: &str) -> io::Result<()> {
+        if !self.names.insert(name.to_owned()) {
+            return Err(invalid_input(
+                format!("Index {:?} is already written", name),
+            ));
+        }
+        self.in_index = true;
+        self.last_key = None;
+        self.writer.write_raw(&[TAG_INDEX])?;
+        self.writer.write_chunk(name.as_bytes())
+    }
+
+    /// Writes a change of the given key to the current index.
+    pub fn write_change(&mut self, key: &[u8], change: &Change) -> io::Result<()> {
+        if !self.in_index {
+            return Err(invalid_input("A change is written before any index"));
+        }
+        if let Some(ref last_key) = self.last_key {
+            if key <= &last_key[..] {
+                return Err(invalid_input(format!(
+                    "Keys are not in the ascending order: {:?}, {:?}",
+                    last_key,
+                    key
+                )));
+            }
+        }
+        self.last_key = Some(key.to_vec());
+
+        match *change {
+            Change::Put(ref value) => {
+                self.writer.write_raw(&[TAG_PUT])?;
+                self.writer.write_chunk(key)?;
+                self.writer.write_chunk(value)
+            }
+            Change::Delete => {
+                self.writer.write_raw(&[TAG_DELETE])?;
+                self.writer.write_chunk(key)
+            }
+        }
+    }
+
+    /// Writes the end of the patch and its checksum and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.write_raw(&[TAG_END])?;
+        self.writer.finish()
+    }
+}
+
+/// Writer of a header, length-prefixed chunks and a trailing checksum, which is
+/// the SHA-256 hash of all the bytes written before it.
+#[derive(Debug)]
+pub(crate) struct ChecksumWriter<W> {
+    writer: W,
+    hasher: HashStream,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    /// Creates a new writer and writes the magic bytes and the format version.
+    pub fn new(writer: W, magic: &[u8], version: u16) -> io::Result<Self> {
+        let mut checksum_writer = ChecksumWriter {
+            writer,
+            hasher: HashStream::new(),
+        };
+        let mut version_bytes = [0; 2];
+        LittleEndian::write_u16(&mut version_bytes, version);
+        checksum_writer.write_raw(magic)?;
+        checksum_writer.write_raw(&version_bytes)?;
+        Ok(checksum_writer)
+    }
+
+    /// Writes the checksum and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let checksum = mem::replace(&mut self.hasher, HashStream::new()).hash();
+        self.writer.write_all(checksum.as_ref())?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    pub fn write_raw(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.writer.write_all(bytes)?;
+        self.hasher = mem::replace(&mut self.hasher, HashStream::new()).update(bytes);
+        Ok(())
+    }
+
+    pub fn write_chunk(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if bytes.len() > u32::max_value() as usize {
+            return Err(invalid_input("A chunk is too long to be encoded"));
+        }
+        let mut len = [0; 4];
+        LittleEndian::write_u32(&mut len, bytes.len() as u32);
+        self.write_raw(&len)?;
+        self.write_raw(bytes)
+    }
+}
+
+/// Streaming decoder of a patch encoded into the portable binary format.
+///
+/// The decoder is an iterator over `(index name, key, change)` triples. The checksum
+/// of the patch is verified only after the last change is read, so the changes
+/// must not be used until the iterator has returned `None` without errors. Use
+/// [`into_patch`](#method.into_patch) to read and verify the whole patch at once.
+///
+/// # Examples
+///
+/// ```
+/// use exonum::storage::{Database, MemoryDB, PatchReader};
+///
+/// let db = MemoryDB::new();
+/// let mut fork = db.fork();
+/// fork.put("index", vec![1], vec![10]);
+/// let bytes = fork.into_patch().encode(Vec::new()).unwrap();
+///
+/// let reader = PatchReader::new(&bytes[..]).unwrap();
+/// db.merge(reader.into_patch().unwrap()).unwrap();
+/// assert_eq!(db.snapshot().get("index", &[1]), Some(vec![10]));
+/// ```
+#[derive(Debug)]
+pub struct PatchReader<R> {
+    reader: ChecksumReader<R>,
+    names: HashSet<String>,
+    current: Option<String>,
+    last_key: Option<Vec<u8>>,
+    finished: bool,
+}
+
+impl<R: Read> PatchReader<R> {
+    /// Creates a new decoder and reads the header from `reader`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the header is malformed or the format version is not supported.
+    pub fn new(reader: R) -> Result<Self> {
+        Ok(PatchReader {
+            reader: ChecksumReader::new(reader, PATCH_MAGIC, PATCH_FORMAT_VERSION, "patch")?,
+            names: HashSet::new(),
+            current: None,
+            last_key: None,
+            finished: false,
+        })
+    }
+
+    /// Reads all the changes, verifies the checksum and collects the changes into a patch.
+    pub fn into_patch(self) -> Result<Patch> {
+        let mut patch = Patch::new();
+        for entry in self {
+            let (name, key, change) = entry?;
+            patch.insert_change(name, key, change);
+        }
+        Ok(patch)
+    }
+
+    fn read_entry(&mut self) -> Result<Option<(String, Vec<u8>, Change)>> {
+        loop {
+            let tag = self.reader.read_raw(1)?[0];
+            match tag {
+                TAG_INDEX => {
+                    let name = String::from_utf8(self.reader.read_chunk()?).map_err(|_| {
+                        Error::new("Index name is not a valid UTF-8 string")
+                    })?;
+                    if !self.names.insert(name.clone()) {
+                        return Err(Error::new(format!("Duplicate index {:?}", name)));
+                    }
+                    self.current = Some(name);
+                    self.last_key = None;
+                }
+                TAG_PUT | TAG_DELETE => {
+                    let name = match self.current {
+                        Some(ref name) => name.clone(),
+                        None => return Err(Error::new("A change precedes any index")),
+                    };
+                    let key = self.reader.read_chunk()?;
+                    if let Some(ref last_key) = self.last_key {
+                        if key <= *last_key {
+                            return Err(Error::new(format!(
+                                "Keys are not in the ascending order: {:?}, {:?}",
+                                last_key,
+                                key
+                            )));
+                        }
+                    }
+                    self.last_key = Some(key.clone());
+                    let change = if tag == TAG_PUT {
+                        Change::Put(self.reader.read_chunk()?)
+                    } else {
+                        Change::Delete
+                    };
+                    return Ok(Some((name, key, change)));
+                }
+                TAG_END => {
+                    self.reader.verify_checksum()?;
+                    return Ok(None);
+                }
+                tag => return Err(Error::new(format!("Unknown record tag: {}", tag))),
+            }
+        }
+    }
+}
+
+impl<R: Read> StdIterator for PatchReader<R> {
+    type Item = Result<(String, Vec<u8>, Change)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.read_entry() {
+            Ok(Some(entry)) => Some(Ok(entry)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Reader of the data written by `ChecksumWriter`.
+#[derive(Debug)]
+pub(crate) struct ChecksumReader<R> {
+    reader: R,
+    hasher: HashStream,
+}
+
+impl<R: Read> ChecksumReader<R> {
+    /// Creates a new reader and checks the magic bytes and the format version.
+    /// `kind` is the name of the data used in error messages.
+    pub fn new(reader: R, magic: &[u8], version: u16, kind: &str) -> Result<Self> {
+        let mut checksum_reader = ChecksumReader {
+            reader,
+            hasher: HashStream::new(),
+        };
+        if checksum_reader.read_raw(magic.len())? != magic {
+            return Err(Error::new(format!("The input is not an encoded {}", kind)));
+        }
+        let actual_version = LittleEndian::read_u16(&checksum_reader.read_raw(2)?);
+        if actual_version != version {
+            return Err(Error::new(format!(
+                "Unsupported {} format version: {}",
+                kind,
+                actual_version
+            )));
+        }
+        Ok(checksum_reader)
+    }
+
+    /// Reads the checksum and compares it with the hash of all the bytes read before it.
+    pub fn verify_checksum(&mut self) -> Result<()> {
+        let expected = mem::replace(&mut self.hasher, HashStream::new()).hash();
+        let mut checksum = [0; HASH_SIZE];
+        self.reader.read_exact(&mut checksum).map_err(read_error)?;
+        if Hash::new(checksum) != expected {
+            return Err(Error::new("Checksum mismatch"));
+        }
+        Ok(())
+    }
+
+    pub fn read_raw(&mut self, len: usize) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        (&mut self.reader)
+            .take(len as u64)
+            .read_to_end(&mut bytes)
+            .map_err(read_error)?;
+        if bytes.len() != len {
+            return Err(Error::new("Unexpected end of input"));
+        }
+        self.hasher = mem::replace(&mut self.hasher, HashStream::new()).update(&bytes);
+        Ok(bytes)
+    }
+
+    pub fn read_chunk(&mut self) -> Result<Vec<u8>> {
+        let len = LittleEndian::read_u32(&self.read_raw(4)?);
+        self.read_raw(len as usize)
+    }
+}
+
+pub(crate) fn invalid_input<T: Into<String>>(message: T) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message.into())
+}
+
+fn read_error(e: io::Error) -> Error {
+    if e.kind() == io::ErrorKind::UnexpectedEof {
+        Error::new("Unexpected end of input")
+    } else {
+        Error::new(format!("Cannot read the encoded patch: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use storage::{Change, Database, MemoryDB, Patch};
+    use super::{PatchReader, PatchWriter};
+
+    fn sample_patch(db: &Database) -> Patch {
+        let mut fork = db.fork();
+        fork.put("a", vec![1, 2], vec![3]);
+        fork.put("a", vec![1], vec![]);
+        fork.remove("a", vec![0]);
+        fork.put("b", vec![], vec![4; 300]);
+        fork.remove("c", vec![5]);
+        fork.into_patch()
+    }
+
+    fn entries(patch: &Patch) -> Vec<(String, Vec<u8>, Change)> {
+        let mut entries = patch
+            .iter()
+            .flat_map(|(name, changes)| {
+                changes.iter().map(move |(key, change)| {
+                    (name.clone(), key.clone(), change.clone())
+                })
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by(|x, y| (&x.0, &x.1).cmp(&(&y.0, &y.1)));
+        entries
+    }
+
+    fn contents(db: &Database, name: &str) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let snapshot = db.snapshot();
+        let mut iter = snapshot.iter(name, &[]);
+        let mut contents = Vec::new();
+        while let Some((key, value)) = iter.next() {
+            contents.push((key.to_vec(), value.to_vec()));
+        }
+        contents
+    }
+
+    #[test]
+    fn test_patch_roundtrip() {
+        let db = MemoryDB::new();
+        let patch = sample_patch(&db);
+        let bytes = patch.encode(Vec::new()).unwrap();
+        let decoded = Patch::decode(&bytes[..]).unwrap();
+        assert_eq!(entries(&patch), entries(&decoded));
+        // The encoding is deterministic.
+        assert_eq!(decoded.encode(Vec::new()).unwrap(), bytes);
+
+        let empty = db.fork().into_patch().encode(Vec::new()).unwrap();
+        assert!(Patch::decode(&empty[..]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_patch_reader_stream() {
+        let db = MemoryDB::new();
+        let bytes = sample_patch(&db).encode(Vec::new()).unwrap();
+        let reader = PatchReader::new(&bytes[..]).unwrap();
+        let streamed = reader.map(|entry| entry.unwrap()).collect::<Vec<_>>();
+        assert_eq!(
+            streamed,
+            vec![
+                ("a".to_owned(), vec![0], Change::Delete),
+                ("a".to_owned(), vec![1], Change::Put(vec![])),
+                ("a".to_owned(), vec![1, 2], Change::Put(vec![3])),
+                ("b".to_owned(), vec![], Change::Put(vec![4; 300])),
+                ("c".to_owned(), vec![5], Change::Delete),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_encoded() {
+        let source = MemoryDB::new();
+        let mut fork = source.fork();
+        fork.put("a", vec![1], vec![10]);
+        fork.put("a", vec![2], vec![20]);
+        source.merge(fork.into_patch()).unwrap();
+        let bytes = sample_patch(&source).encode(Vec::new()).unwrap();
+        source.merge(Patch::decode(&bytes[..]).unwrap()).unwrap();
+
+        let replica = MemoryDB::new();
+        let mut fork = replica.fork();
+        fork.put("a", vec![1], vec![10]);
+        fork.put("a", vec![2], vec![20]);
+        replica.merge(fork.into_patch()).unwrap();
+        replica.merge_encoded(&mut &bytes[..]).unwrap();
+
+        for name in &["a", "b", "c"] {
+            assert_eq!(contents(&source, name), contents(&replica, name));
+        }
+        assert_eq!(replica.snapshot().get("a", &[2]), Some(vec![20]));
+        assert_eq!(replica.snapshot().get("a", &[1]), Some(vec![]));
+    }
+
+    #[test]
+    fn test_corrupted_patch() {
+        let db = MemoryDB::new();
+        let bytes = sample_patch(&db).encode(Vec::new()).unwrap();
+
+        // Every single-byte corruption is detected.
+        for i in 0..bytes.len() {
+            let mut corrupted = bytes.clone();
+            corrupted[i] ^= 0x40;
+            assert!(Patch::decode(&corrupted[..]).is_err(), "Byte {}", i);
+        }
+        // Every truncation is detected.
+        for len in 0..bytes.len() {
+            assert!(Patch::decode(&bytes[..len]).is_err(), "Length {}", len);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let mut bytes = MemoryDB::new()
+            .fork()
+            .into_patch()
+            .encode(Vec::new())
+            .unwrap();
+        bytes[8] = 2;
+        let err = PatchReader::new(&bytes[..]).unwrap_err();
+        assert!(err.to_string().contains("version"));
+    }
+
+    #[test]
+    fn test_patch_writer_order() {
+        let mut writer = PatchWriter::new(Vec::new()).unwrap();
+        let err = writer.write_change(&[1], &Change::Delete).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        writer.start_index("a").unwrap();
+        writer.write_change(&[1], &Change::Delete).unwrap();
+        let err = writer.write_change(&[1], &Change::Delete).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = writer.write_change(&[0], &Change::Delete).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        writer.start_index("b").unwrap();
+        writer.write_change(&[0], &Change::Delete).unwrap();
+        let err = writer.start_index("a").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        let bytes = writer.finish().unwrap();
+        assert_eq!(Patch::decode(&bytes[..]).unwrap().len(), 2);
+    }
+}
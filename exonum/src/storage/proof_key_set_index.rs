@@ -0,0 +1,481 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An implementation of a Merkelized set of keys.
+
+use std::fmt;
+
+use serde::{Serialize, Serializer};
+
+use crypto::Hash;
+use super::{BaseIndex, Error, Fork, Snapshot, StorageKey};
+use super::indexes_metadata::IndexType;
+use super::proof_map_index::{MapProof, ProofMapIndex, ProofMapIndexKeys, ProofMapKey};
+
+/// A Merkelized version of a set of keys.
+///
+/// `ProofKeySetIndex` is a Merkelized counterpart of [`KeySetIndex`]. The elements are stored
+/// as the keys of a binary Merkle Patricia tree with empty values, so the index can produce
+/// proofs of both membership and non-membership of an element. The Merkle root of the set
+/// can be used in [`Service::state_hash`].
+///
+/// `ProofKeySetIndex` requires that the elements implement the [`ProofMapKey`] trait.
+///
+/// [`KeySetIndex`]: ../key_set_index/struct.KeySetIndex.html
+/// [`Service::state_hash`]: ../../blockchain/trait.Service.html#tymethod.state_hash
+/// [`ProofMapKey`]: ../proof_map_index/trait.ProofMapKey.html
+pub struct ProofKeySetIndex<T, K> {
+    map: ProofMapIndex<T, K, ()>,
+}
+
+/// A proof of membership or non-membership for an element of a `ProofKeySetIndex`.
+///
+/// This struct is created by the [`get_proof`] method on [`ProofKeySetIndex`].
+///
+/// [`get_proof`]: struct.ProofKeySetIndex.html#method.get_proof
+/// [`ProofKeySetIndex`]: struct.ProofKeySetIndex.html
+#[derive(Debug)]
+pub struct KeySetProof {
+    proof: MapProof<()>,
+}
+
+/// An iterator over the items of a `ProofKeySetIndex`.
+///
+/// This struct is created by the [`iter`] or
+/// [`iter_from`] methods on [`ProofKeySetIndex`]. See its documentation for more.
+///
+/// [`iter`]: struct.ProofKeySetIndex.html#method.iter
+/// [`iter_from`]: struct.ProofKeySetIndex.html#method.iter_from
+/// [`ProofKeySetIndex`]: struct.ProofKeySetIndex.html
+#[derive(Debug)]
+pub struct ProofKeySetIndexIter<'a, K> {
+    base_iter: ProofMapIndexKeys<'a, K>,
+}
+
+impl KeySetProof {
+    /// Returns Merkle root hash of the set proof.
+    pub fn merkle_root(&self) -> Hash {
+        self.proof.merkle_root()
+    }
+
+    /// Verifies the correctness of the proof by the trusted root hash and the requested element.
+    ///
+    /// If the proof is valid, `Ok(true)` is returned for a member of the set and `Ok(false)`
+    /// otherwise. If the proof is invalid, `Err` is returned.
+    pub fn validate<K: ProofMapKey>(&self, key: &K, merkle_root: Hash) -> Result<bool, Error> {
+        self.proof.validate(key, merkle_root).map(
+            |value| value.is_some(),
+        )
+    }
+}
+
+impl Serialize for KeySetProof {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.proof.serialize(serializer)
+    }
+}
+
+impl<T, K> ProofKeySetIndex<T, K>
+where
+    T: AsRef<Snapshot>,
+    K: ProofMapKey,
+{
+    /// Creates a new index representation based on the name and storage view.
+    ///
+    /// Storage view can be specified as [`&Snapshot`] or [`&mut Fork`]. In the first case only
+    /// immutable methods are available. In the second case both immutable and mutable methods are
+    /// available.
+    ///
+    /// [`&Snapshot`]: ../trait.Snapshot.html
+    /// [`&mut Fork`]: ../struct.Fork.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ProofKeySetIndex};
+    /// use exonum::crypto::PublicKey;
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let snapshot = db.snapshot();
+    /// let index: ProofKeySetIndex<_, PublicKey> = ProofKeySetIndex::new(name, &snapshot);
+    ///
+    /// let mut fork = db.fork();
+    /// let mut mut_index: ProofKeySetIndex<_, PublicKey> = ProofKeySetIndex::new(name, &mut fork);
+    /// # drop(index);
+    /// # drop(mut_index);
+    /// ```
+    pub fn new<S: AsRef<str>>(index_name: S, view: T) -> Self {
+        ProofKeySetIndex {
+            map: ProofMapIndex::from_base(
+                BaseIndex::new(index_name, IndexType::ProofKeySet, view),
+            ),
+        }
+    }
+
+    /// Creates a new index representation based on the name, common prefix of its keys
+    /// and storage view.
+    ///
+    /// Storage view can be specified as [`&Snapshot`] or [`&mut Fork`]. In the first case only
+    /// immutable methods are available. In the second case both immutable and mutable methods are
+    /// available.
+    ///
+    /// [`&Snapshot`]: ../trait.Snapshot.html
+    /// [`&mut Fork`]: ../struct.Fork.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ProofKeySetIndex};
+    /// use exonum::crypto::Hash;
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let index_id = vec![01];
+    ///
+    /// let snapshot = db.snapshot();
+    /// let index: ProofKeySetIndex<_, Hash> =
+    ///                             ProofKeySetIndex::new_in_family(name, &index_id, &snapshot);
+    ///
+    /// let mut fork = db.fork();
+    /// let mut mut_index : ProofKeySetIndex<_, Hash> =
+    ///                             ProofKeySetIndex::new_in_family(name, &index_id, &mut fork);
+    /// # drop(index);
+    /// # drop(mut_index);
+    /// ```
+    pub fn new_in_family<S: AsRef<str>, I: StorageKey>(
+        family_name: S,
+        index_id: &I,
+        view: T,
+    ) -> Self {
+        ProofKeySetIndex {
+            map: ProofMapIndex::from_base(BaseIndex::new_in_family(
+                family_name,
+                index_id,
+                IndexType::ProofKeySet,
+                view,
+            )),
+        }
+    }
+
+    /// Returns the Merkle root hash of the set or default hash value if it is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ProofKeySetIndex};
+    /// use exonum::crypto::Hash;
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = ProofKeySetIndex::new(name, &mut fork);
+    ///
+    /// let default_hash = index.merkle_root();
+    /// assert_eq!(Hash::default(), default_hash);
+    ///
+    /// index.insert(Hash::default());
+    /// let hash = index.merkle_root();
+    /// assert_ne!(hash, default_hash);
+    /// ```
+    pub fn merkle_root(&self) -> Hash {
+        self.map.merkle_root()
+    }
+
+    /// Returns `true` if the set contains the specified element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ProofKeySetIndex};
+    /// use exonum::crypto::Hash;
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = ProofKeySetIndex::new(name, &mut fork);
+    /// assert!(!index.contains(&Hash::default()));
+    ///
+    /// index.insert(Hash::default());
+    /// assert!(index.contains(&Hash::default()));
+    /// ```
+    pub fn contains(&self, item: &K) -> bool {
+        self.map.contains(item)
+    }
+
+    /// Returns the proof of membership or non-membership for the specified element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ProofKeySetIndex};
+    /// use exonum::crypto::hash;
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = ProofKeySetIndex::new(name, &mut fork);
+    /// let (member, stranger) = (hash(&[1]), hash(&[2]));
+    /// index.insert(member);
+    ///
+    /// let proof = index.get_proof(&member);
+    /// assert!(proof.validate(&member, index.merkle_root()).unwrap());
+    /// let proof = index.get_proof(&stranger);
+    /// assert!(!proof.validate(&stranger, index.merkle_root()).unwrap());
+    /// ```
+    pub fn get_proof(&self, item: &K) -> KeySetProof {
+        KeySetProof { proof: self.map.get_proof(item) }
+    }
+
+    /// An iterator visiting all elements in ascending order. The iterator element type is K.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ProofKeySetIndex};
+    /// use exonum::crypto::Hash;
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let snapshot = db.snapshot();
+    /// let index: ProofKeySetIndex<_, Hash> = ProofKeySetIndex::new(name, &snapshot);
+    ///
+    /// for val in index.iter() {
+    ///     println!("{:?}", val);
+    /// }
+    /// ```
+    pub fn iter(&self) -> ProofKeySetIndexIter<K> {
+        ProofKeySetIndexIter { base_iter: self.map.keys() }
+    }
+
+    /// An iterator visiting all elements in ascending order starting from the specified value.
+    /// The iterator element type is K.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ProofKeySetIndex};
+    /// use exonum::crypto::Hash;
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let snapshot = db.snapshot();
+    /// let index: ProofKeySetIndex<_, Hash> = ProofKeySetIndex::new(name, &snapshot);
+    ///
+    /// for val in index.iter_from(&Hash::default()) {
+    ///     println!("{:?}", val);
+    /// }
+    /// ```
+    pub fn iter_from(&self, from: &K) -> ProofKeySetIndexIter<K> {
+        ProofKeySetIndexIter { base_iter: self.map.keys_from(from) }
+    }
+}
+
+impl<'a, K> ProofKeySetIndex<&'a mut Fork, K>
+where
+    K: ProofMapKey,
+{
+    /// Adds a key to the set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ProofKeySetIndex};
+    /// use exonum::crypto::Hash;
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = ProofKeySetIndex::new(name, &mut fork);
+    ///
+    /// index.insert(Hash::default());
+    /// assert!(index.contains(&Hash::default()));
+    /// ```
+    pub fn insert(&mut self, item: K) {
+        self.map.put(&item, ())
+    }
+
+    /// Removes a key from the set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ProofKeySetIndex};
+    /// use exonum::crypto::Hash;
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = ProofKeySetIndex::new(name, &mut fork);
+    ///
+    /// index.insert(Hash::default());
+    /// assert!(index.contains(&Hash::default()));
+    ///
+    /// index.remove(&Hash::default());
+    /// assert!(!index.contains(&Hash::default()));
+    /// ```
+    pub fn remove(&mut self, item: &K) {
+        self.map.remove(item)
+    }
+
+    /// Clears the set, removing all values.
+    ///
+    /// # Notes
+    ///
+    /// Currently this method is not optimized to delete large set of data. During the execution of
+    /// this method the amount of allocated memory is linearly dependent on the number of elements
+    /// in the index.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ProofKeySetIndex};
+    /// use exonum::crypto::Hash;
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = ProofKeySetIndex::new(name, &mut fork);
+    ///
+    /// index.insert(Hash::default());
+    /// assert!(index.contains(&Hash::default()));
+    ///
+    /// index.clear();
+    /// assert!(!index.contains(&Hash::default()));
+    /// ```
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+}
+
+impl<'a, T, K> ::std::iter::IntoIterator for &'a ProofKeySetIndex<T, K>
+where
+    T: AsRef<Snapshot>,
+    K: ProofMapKey,
+{
+    type Item = K::Owned;
+    type IntoIter = ProofKeySetIndexIter<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K> Iterator for ProofKeySetIndexIter<'a, K>
+where
+    K: ProofMapKey,
+{
+    type Item = K::Owned;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.base_iter.next()
+    }
+}
+
+impl<T, K> fmt::Debug for ProofKeySetIndex<T, K>
+where
+    T: AsRef<Snapshot>,
+    K: ProofMapKey,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ProofKeySetIndex")
+            .field("map", &self.map)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crypto::{hash, Hash};
+    use storage::{Database, MemoryDB, ProofMapIndex};
+    use encoding::serialize::json::reexport::to_string;
+    use super::ProofKeySetIndex;
+
+    const IDX_NAME: &'static str = "idx_name";
+
+    #[test]
+    fn test_proof_key_set_methods() {
+        let db = MemoryDB::new();
+        let mut fork = db.fork();
+        let mut index = ProofKeySetIndex::new(IDX_NAME, &mut fork);
+        assert_eq!(index.merkle_root(), Hash::zero());
+
+        let items = (0u8..10).map(|i| hash(&[i])).collect::<Vec<_>>();
+        for item in &items {
+            index.insert(*item);
+        }
+        assert!(items.iter().all(|item| index.contains(item)));
+        assert!(!index.contains(&hash(&[100])));
+
+        let mut sorted = items.clone();
+        sorted.sort();
+        assert_eq!(index.iter().collect::<Vec<_>>(), sorted);
+        assert_eq!(index.iter_from(&sorted[5]).collect::<Vec<_>>(), &sorted[5..]);
+
+        index.remove(&items[0]);
+        assert!(!index.contains(&items[0]));
+        index.clear();
+        assert_eq!(index.iter().count(), 0);
+        assert_eq!(index.merkle_root(), Hash::zero());
+    }
+
+    #[test]
+    fn test_proof_key_set_proofs() {
+        let db = MemoryDB::new();
+        let mut fork = db.fork();
+        let mut index = ProofKeySetIndex::new(IDX_NAME, &mut fork);
+        let members = (0u8..50).map(|i| hash(&[i])).collect::<Vec<_>>();
+        for member in &members {
+            index.insert(*member);
+        }
+        let merkle_root = index.merkle_root();
+
+        for member in &members {
+            let proof = index.get_proof(member);
+            assert_eq!(proof.merkle_root(), merkle_root);
+            assert!(proof.validate(member, merkle_root).unwrap());
+            assert!(to_string(&proof).unwrap().len() > 0);
+        }
+
+        let stranger = hash(&[100]);
+        let proof = index.get_proof(&stranger);
+        assert!(!proof.validate(&stranger, merkle_root).unwrap());
+        assert!(proof.validate(&stranger, hash(&[])).is_err());
+    }
+
+    #[test]
+    fn test_proof_key_set_root_matches_proof_map() {
+        let db = MemoryDB::new();
+        let mut fork = db.fork();
+        let items = (0u8..10).map(|i| hash(&[i])).collect::<Vec<_>>();
+        let set_root = {
+            let mut index = ProofKeySetIndex::new("set", &mut fork);
+            for item in &items {
+                index.insert(*item);
+            }
+            index.merkle_root()
+        };
+        let map_root = {
+            let mut index = ProofMapIndex::new("map", &mut fork);
+            for item in &items {
+                index.put(item, ());
+            }
+            index.merkle_root()
+        };
+        assert_eq!(set_root, map_root);
+    }
+}
@@ -21,8 +21,9 @@ use crypto::{Hash, hash, HashStream};
 use super::{BaseIndex, BaseIndexIter, Snapshot, Fork, StorageValue, StorageKey};
 use super::indexes_metadata::IndexType;
 use self::key::ProofListKey;
+use self::proof::consistency_proof_keys;
 
-pub use self::proof::{ListProof, ListProofError};
+pub use self::proof::{ListProof, ListProofError, ListConsistencyProof};
 
 #[cfg(test)]
 mod tests;
@@ -397,6 +398,46 @@ where
         self.construct_proof(self.root_key(), from, to)
     }
 
+    /// Returns the proof that the first `old_len` elements of the list were not changed since
+    /// the list had `old_len` elements, i.e., that the list of length `old_len` is a prefix
+    /// of the current list.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `old_len` is greater than the current length of the list.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ProofListIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = ProofListIndex::new(name, &mut fork);
+    ///
+    /// index.extend([1, 2, 3].iter().cloned());
+    /// let old_merkle_root = index.merkle_root();
+    /// index.extend([4, 5].iter().cloned());
+    ///
+    /// let proof = index.get_consistency_proof(3);
+    /// assert!(proof.validate(old_merkle_root, 3, index.merkle_root(), 5).is_ok());
+    /// ```
+    pub fn get_consistency_proof(&self, old_len: u64) -> ListConsistencyProof {
+        if old_len > self.len() {
+            panic!(
+                "Illegal old length: the len is {} but the old length is {}",
+                self.len(),
+                old_len
+            );
+        }
+        let hashes = consistency_proof_keys(old_len, self.len())
+            .into_iter()
+            .map(|key| self.get_branch_unchecked(key))
+            .collect();
+        ListConsistencyProof::new(hashes)
+    }
+
     /// Returns an iterator over the list. The iterator element type is V.
     ///
     /// # Examples
@@ -437,6 +478,53 @@ where
     pub fn iter_from(&self, from: u64) -> ProofListIndexIter<V> {
         ProofListIndexIter { base_iter: self.base.iter_from(&0u8, &ProofListKey::leaf(from)) }
     }
+
+    /// Returns an iterator over the list in reverse order. The iterator element type is V.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ProofListIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = ProofListIndex::new(name, &mut fork);
+    /// index.extend([1u8, 2, 3, 4, 5].iter().cloned());
+    ///
+    /// let latest = index.iter_rev().take(2).collect::<Vec<_>>();
+    /// assert_eq!(vec![5, 4], latest);
+    /// ```
+    pub fn iter_rev(&self) -> ProofListIndexIter<V> {
+        ProofListIndexIter { base_iter: self.base.iter_rev(&0u8) }
+    }
+
+    /// Returns an iterator over the items of the list with positions in the range `[from, to)`.
+    /// The iterator element type is V.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ProofListIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = ProofListIndex::new(name, &mut fork);
+    /// index.extend([1u8, 2, 3, 4, 5].iter().cloned());
+    ///
+    /// let items = index.range(1, 3).collect::<Vec<_>>();
+    /// assert_eq!(vec![2, 3], items);
+    /// ```
+    pub fn range(&self, from: u64, to: u64) -> ProofListIndexIter<V> {
+        ProofListIndexIter {
+            base_iter: self.base.range(
+                &0u8,
+                &ProofListKey::leaf(from),
+                &ProofListKey::leaf(to),
+            ),
+        }
+    }
 }
 
 impl<'a, V> ProofListIndex<&'a mut Fork, V>
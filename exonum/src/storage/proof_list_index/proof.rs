@@ -45,6 +45,20 @@ pub enum ListProofError {
     UnexpectedBranch,
     /// The hash of the proof is not equal to the trusted root hash.
     UnmatchedRootHash,
+    /// The number of hashes in the consistency proof does not correspond to the lengths
+    /// of the lists, or the old list is longer than the new one.
+    UnmatchedLength,
+}
+
+/// A proof that a proof list of a certain length is a prefix of the same list
+/// of a greater length, i.e., that the elements of the shorter list were not changed.
+///
+/// The proof consists of the hashes of the complete subtrees that cover the old list,
+/// followed by the hashes of the complete subtrees that cover the appended elements.
+/// These hashes are enough to restore the Merkle root hashes of both lists.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListConsistencyProof {
+    hashes: Vec<Hash>,
 }
 
 impl<V: StorageValue> ListProof<V> {
@@ -92,6 +106,102 @@ impl<V: StorageValue> ListProof<V> {
     }
 }
 
+impl ListConsistencyProof {
+    pub(crate) fn new(hashes: Vec<Hash>) -> Self {
+        ListConsistencyProof { hashes }
+    }
+
+    /// Returns the hashes of the subtrees included into the proof.
+    pub fn hashes(&self) -> &[Hash] {
+        &self.hashes
+    }
+
+    /// Verifies the correctness of the proof by the trusted Merkle root hashes and the numbers of
+    /// elements in the old and the new lists.
+    ///
+    /// If the proof is valid, the list with the `old_len` elements and the `old_merkle_root` hash
+    /// is a prefix of the list with the `new_len` elements and the `new_merkle_root` hash.
+    /// Otherwise, `Err` is returned.
+    pub fn validate(
+        &self,
+        old_merkle_root: Hash,
+        old_len: u64,
+        new_merkle_root: Hash,
+        new_len: u64,
+    ) -> Result<(), ListProofError> {
+        if old_len > new_len {
+            return Err(ListProofError::UnmatchedLength);
+        }
+        let keys = consistency_proof_keys(old_len, new_len);
+        if keys.len() != self.hashes.len() {
+            return Err(ListProofError::UnmatchedLength);
+        }
+
+        let old_count = consistency_proof_keys(old_len, old_len).len();
+        let blocks = keys.into_iter()
+            .zip(self.hashes.iter().cloned())
+            .collect::<Vec<_>>();
+        if root_from_blocks(&blocks[..old_count], old_len) != old_merkle_root ||
+            root_from_blocks(&blocks, new_len) != new_merkle_root
+        {
+            return Err(ListProofError::UnmatchedRootHash);
+        }
+        Ok(())
+    }
+}
+
+// Returns the keys of the complete subtrees that cover the first `old_len` elements
+// of the list, followed by the keys of the complete subtrees that cover the rest
+// of the `new_len` elements.
+pub(crate) fn consistency_proof_keys(old_len: u64, new_len: u64) -> Vec<ProofListKey> {
+    fn push_blocks(keys: &mut Vec<ProofListKey>, mut start: u64, end: u64) {
+        while start < end {
+            // The largest aligned subtree that starts at `start` and fits into the range.
+            let mut size = 1u64 << (63 - (end - start).leading_zeros());
+            if start != 0 {
+                size = ::std::cmp::min(size, 1u64 << start.trailing_zeros());
+            }
+            let height = size.trailing_zeros();
+            keys.push(ProofListKey::new(height as u8 + 1, start >> height));
+            start += size;
+        }
+    }
+
+    let mut keys = Vec::new();
+    push_blocks(&mut keys, 0, old_len);
+    push_blocks(&mut keys, old_len, new_len);
+    keys
+}
+
+// Restores the Merkle root hash of the list with `len` elements from the hashes of the
+// complete subtrees that cover the list from left to right.
+fn root_from_blocks(blocks: &[(ProofListKey, Hash)], len: u64) -> Hash {
+    fn node_hash(
+        key: ProofListKey,
+        len: u64,
+        blocks: &[(ProofListKey, Hash)],
+        pos: &mut usize,
+    ) -> Hash {
+        let (block_key, block_hash) = blocks[*pos];
+        if block_key.height() == key.height() && block_key.index() == key.index() {
+            *pos += 1;
+            return block_hash;
+        }
+        let left = node_hash(key.left(), len, blocks, pos);
+        if key.right().first_left_leaf_index() < len {
+            pair_hash(&left, &node_hash(key.right(), len, blocks, pos))
+        } else {
+            hash(left.as_ref())
+        }
+    }
+
+    if len == 0 {
+        return Hash::default();
+    }
+    let height = len.next_power_of_two().trailing_zeros() as u8 + 1;
+    node_hash(ProofListKey::new(height, 0), len, blocks, &mut 0)
+}
+
 impl<V: Serialize> Serialize for ListProof<V> {
     fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
     where
@@ -18,7 +18,7 @@ use crypto::{CryptoHash, Hash, hash};
 use storage::Database;
 use encoding::serialize::json::reexport::{to_string, from_str};
 use encoding::serialize::reexport::Serialize;
-use super::{ProofListIndex, ListProof, pair_hash};
+use super::{ProofListIndex, ListProof, ListConsistencyProof, pair_hash};
 use self::ListProof::*;
 
 const IDX_NAME: &'static str = "idx_name";
@@ -533,6 +533,60 @@ fn same_merkle_root(db1: Box<Database>, db2: Box<Database>) {
     assert_eq!(i1.merkle_root(), i2.merkle_root());
 }
 
+fn consistency_proofs(db: Box<Database>) {
+    let values = random_values(40);
+    let mut fork = db.fork();
+    let mut index = ProofListIndex::new(IDX_NAME, &mut fork);
+
+    let mut roots = vec![index.merkle_root()];
+    for value in &values {
+        index.push(value.clone());
+        let new_len = index.len();
+        let new_root = index.merkle_root();
+        roots.push(new_root);
+
+        for old_len in 0..new_len + 1 {
+            let proof = index.get_consistency_proof(old_len);
+            let old_root = roots[old_len as usize];
+            assert!(proof.validate(old_root, old_len, new_root, new_len).is_ok());
+
+            let json_representation = to_string(&proof).unwrap();
+            let deserialized_proof: ListConsistencyProof = from_str(&json_representation).unwrap();
+            assert_eq!(deserialized_proof, proof);
+        }
+    }
+
+    let proof = index.get_consistency_proof(13);
+    let (old_root, new_root) = (roots[13], index.merkle_root());
+    assert!(proof.validate(old_root, 13, new_root, 40).is_ok());
+    assert!(proof.validate(roots[12], 13, new_root, 40).is_err());
+    assert!(proof.validate(old_root, 13, roots[39], 40).is_err());
+    assert!(proof.validate(old_root, 14, new_root, 40).is_err());
+    assert!(proof.validate(old_root, 13, new_root, 39).is_err());
+    assert!(proof.validate(new_root, 40, old_root, 13).is_err());
+
+    // The history is rewritten, so the old roots are not consistent with the new one.
+    index.set(5, vec![1, 2, 3]);
+    let new_root = index.merkle_root();
+    for old_len in 6..40 {
+        let proof = index.get_consistency_proof(old_len);
+        let old_root = roots[old_len as usize];
+        assert!(proof.validate(old_root, old_len, new_root, 40).is_err());
+    }
+    // The prefix before the changed element is still consistent.
+    let proof = index.get_consistency_proof(5);
+    assert!(proof.validate(roots[5], 5, new_root, 40).is_ok());
+}
+
+fn consistency_proof_illegal_length(db: Box<Database>) {
+    let mut fork = db.fork();
+    let mut index = ProofListIndex::new(IDX_NAME, &mut fork);
+    for i in 0u8..4 {
+        index.push(vec![i]);
+    }
+    index.get_consistency_proof(5);
+}
+
 #[derive(Serialize)]
 struct ProofInfo<'a, V: Serialize + 'a> {
     merkle_root: Hash,
@@ -642,6 +696,23 @@ mod memorydb_tests {
         super::simple_merkle_root(db);
     }
 
+    #[test]
+    fn test_consistency_proofs() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let path = dir.path();
+        let db = create_database(path);
+        super::consistency_proofs(db);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_consistency_proof_illegal_length() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let path = dir.path();
+        let db = create_database(path);
+        super::consistency_proof_illegal_length(db);
+    }
+
     #[test]
     fn test_same_merkle_root() {
         let dir1 = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
@@ -755,6 +826,23 @@ mod rocksdb_tests {
         super::simple_merkle_root(db);
     }
 
+    #[test]
+    fn test_consistency_proofs() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let path = dir.path();
+        let db = create_database(path);
+        super::consistency_proofs(db);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_consistency_proof_illegal_length() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let path = dir.path();
+        let db = create_database(path);
+        super::consistency_proof_illegal_length(db);
+    }
+
     #[test]
     fn test_same_merkle_root() {
         let dir1 = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
@@ -0,0 +1,299 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::mem;
+use std::thread;
+
+use crypto::{CryptoHash, Hash};
+use super::super::{Fork, StorageKey, StorageValue};
+use super::{MapProof, ProofMapIndex};
+use super::key::{BitsRange, ChildKind, ProofMapKey, ProofPath, PROOF_PATH_SIZE};
+use super::node::{BranchNode, Node};
+
+/// A `ProofMapIndex` with deferred recomputation of the hashes.
+///
+/// Unlike [`ProofMapIndex::put`] and [`ProofMapIndex::remove`], the methods of this struct
+/// only update the structure of the Merkle Patricia tree and mark the affected nodes as dirty.
+/// The hashes of the dirty nodes are recomputed once, when [`merkle_root`], [`get_proof`]
+/// or [`flush`] is called, or when the struct is dropped. Since the struct mutably borrows
+/// the index (and hence the fork), the fork always contains the recomputed hashes
+/// when the patch is taken. The resulting roots, proofs and stored nodes are identical
+/// to the ones produced by the immediate updates. The hashes are not recomputed
+/// if the struct is dropped while unwinding after a panic.
+///
+/// This struct is created by the [`deferred`] method on [`ProofMapIndex`].
+///
+/// [`ProofMapIndex::put`]: struct.ProofMapIndex.html#method.put
+/// [`ProofMapIndex::remove`]: struct.ProofMapIndex.html#method.remove
+/// [`merkle_root`]: #method.merkle_root
+/// [`get_proof`]: #method.get_proof
+/// [`flush`]: #method.flush
+/// [`deferred`]: struct.ProofMapIndex.html#method.deferred
+/// [`ProofMapIndex`]: struct.ProofMapIndex.html
+pub struct DeferredProofMapIndex<'i, 'a: 'i, K: 'i, V: 'i>
+where
+    K: ProofMapKey,
+    V: StorageValue,
+{
+    index: &'i mut ProofMapIndex<&'a mut Fork, K, V>,
+    dirty: BTreeSet<(u16, Vec<u8>)>,
+}
+
+// Key of a node in the set of dirty nodes. The nodes are ordered by the length of their paths,
+// so that the children go after their parents.
+fn dirty_key(path: &ProofPath) -> (u16, Vec<u8>) {
+    let mut bytes = vec![0; PROOF_PATH_SIZE];
+    path.write(&mut bytes);
+    (path.end(), bytes)
+}
+
+impl<'i, 'a, K, V> DeferredProofMapIndex<'i, 'a, K, V>
+where
+    K: ProofMapKey,
+    V: StorageValue,
+{
+    pub(super) fn new(index: &'i mut ProofMapIndex<&'a mut Fork, K, V>) -> Self {
+        DeferredProofMapIndex {
+            index,
+            dirty: BTreeSet::new(),
+        }
+    }
+
+    fn mark_dirty(&mut self, path: &ProofPath) {
+        self.dirty.insert(dirty_key(path));
+    }
+
+    fn get_branch(&self, path: &ProofPath) -> BranchNode {
+        match self.index.get_node_unchecked(path) {
+            Node::Branch(branch) => branch,
+            Node::Leaf(_) => unreachable!("Expected a branch node"),
+        }
+    }
+
+    /// Returns a value corresponding to the key.
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.index.get(key)
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    pub fn contains(&self, key: &K) -> bool {
+        self.index.contains(key)
+    }
+
+    /// Recomputes the hashes and returns the Merkle root hash of the proof map.
+    pub fn merkle_root(&mut self) -> Hash {
+        self.flush();
+        self.index.merkle_root()
+    }
+
+    /// Recomputes the hashes and returns the proof of existence or non-existence
+    /// for the specified key.
+    pub fn get_proof(&mut self, key: &K) -> MapProof<V> {
+        self.flush();
+        self.index.get_proof(key)
+    }
+
+    /// Inserts the key-value pair into the proof map without recomputing the hashes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ProofMapIndex};
+    /// use exonum::crypto::hash;
+    ///
+    /// let db = MemoryDB::new();
+    /// let mut fork = db.fork();
+    /// let mut index = ProofMapIndex::new("name", &mut fork);
+    /// {
+    ///     let mut deferred = index.deferred();
+    ///     for i in 0..100u8 {
+    ///         deferred.put(&hash(&[i]), u64::from(i));
+    ///     }
+    /// }
+    /// assert_eq!(index.get(&hash(&[1])), Some(1));
+    /// ```
+    pub fn put(&mut self, key: &K, value: V) {
+        let path = ProofPath::new(key);
+        let mut node_path = match self.index.get_root_key() {
+            Some(root_path) => root_path,
+            None => {
+                self.index.base.put(&path, value);
+                return;
+            }
+        };
+
+        let mut parent: Option<(ProofPath, BranchNode)> = None;
+        loop {
+            let i = node_path.common_prefix_len(&path);
+            if i < node_path.len() {
+                // Inserts a new branch above the current node.
+                let branch_path = path.prefix(i);
+                let mut branch = BranchNode::empty();
+                branch.set_child_path(path.bit(i), &path);
+                match parent {
+                    Some((parent_path, mut parent_branch)) => {
+                        let kind = path.bit(parent_path.len());
+                        branch.set_child(
+                            node_path.bit(i),
+                            &node_path,
+                            parent_branch.child_hash(kind),
+                        );
+                        parent_branch.set_child_path(kind, &branch_path);
+                        self.index.base.put(&parent_path, parent_branch);
+                    }
+                    None => {
+                        // The hash of the former root is not stored anywhere.
+                        branch.set_child_path(node_path.bit(i), &node_path);
+                        self.mark_dirty(&node_path);
+                    }
+                }
+                self.index.base.put(&branch_path, branch);
+                self.index.base.put(&path, value);
+                self.mark_dirty(&branch_path);
+                self.mark_dirty(&path);
+                return;
+            }
+
+            if node_path.is_leaf() {
+                // Updates the value of an existing leaf.
+                self.index.base.put(&path, value);
+                self.mark_dirty(&path);
+                return;
+            }
+
+            let branch = self.get_branch(&node_path);
+            self.mark_dirty(&node_path);
+            let child_path = branch.child_path(path.bit(node_path.len()));
+            parent = Some((node_path, branch));
+            node_path = child_path;
+        }
+    }
+
+    /// Removes the key from the proof map without recomputing the hashes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ProofMapIndex};
+    /// use exonum::crypto::{hash, Hash};
+    ///
+    /// let db = MemoryDB::new();
+    /// let mut fork = db.fork();
+    /// let mut index = ProofMapIndex::new("name", &mut fork);
+    /// index.put(&hash(&[1]), 1u64);
+    /// {
+    ///     let mut deferred = index.deferred();
+    ///     deferred.remove(&hash(&[1]));
+    ///     assert!(!deferred.contains(&hash(&[1])));
+    /// }
+    /// assert_eq!(index.merkle_root(), Hash::zero());
+    /// ```
+    pub fn remove(&mut self, key: &K) {
+        let path = ProofPath::new(key);
+        let mut node_path = match self.index.get_root_key() {
+            Some(root_path) => root_path,
+            None => return,
+        };
+
+        let mut ancestors: Vec<(ProofPath, BranchNode)> = Vec::new();
+        loop {
+            if node_path.common_prefix_len(&path) < node_path.len() {
+                return;
+            }
+            if node_path.is_leaf() {
+                break;
+            }
+            let branch = self.get_branch(&node_path);
+            let child_path = branch.child_path(path.bit(node_path.len()));
+            ancestors.push((node_path, branch));
+            node_path = child_path;
+        }
+
+        self.index.base.remove(&path);
+        // The sibling of the removed leaf takes the place of their parent.
+        if let Some((parent_path, parent_branch)) = ancestors.pop() {
+            let kind = !path.bit(parent_path.len());
+            self.index.base.remove(&parent_path);
+            if let Some(&mut (ref grand_path, ref mut grand_branch)) = ancestors.last_mut() {
+                grand_branch.set_child(
+                    path.bit(grand_path.len()),
+                    &parent_branch.child_path(kind),
+                    parent_branch.child_hash(kind),
+                );
+                self.index.base.put(grand_path, grand_branch.clone());
+            }
+        }
+        for &(ref ancestor_path, _) in &ancestors {
+            self.mark_dirty(ancestor_path);
+        }
+    }
+
+    /// Recomputes the hashes of the dirty nodes.
+    ///
+    /// The nodes are processed starting from the deepest ones, so each node is hashed
+    /// and written once regardless of the number of updates below it.
+    pub fn flush(&mut self) {
+        let dirty = mem::replace(&mut self.dirty, BTreeSet::new());
+        for &(_, ref raw_path) in dirty.iter().rev() {
+            let path = ProofPath::read(raw_path);
+            if path.is_leaf() {
+                continue;
+            }
+            // The node could be removed after it was marked as dirty.
+            let mut branch: BranchNode = match self.index.base.get(&path) {
+                Some(branch) => branch,
+                None => continue,
+            };
+            for &kind in &[ChildKind::Left, ChildKind::Right] {
+                let child_path = branch.child_path(kind);
+                if dirty.contains(&dirty_key(&child_path)) {
+                    let hash = match self.index.get_node_unchecked(&child_path) {
+                        Node::Leaf(value) => value.hash(),
+                        Node::Branch(child) => child.hash(),
+                    };
+                    branch.set_child_hash(kind, &hash);
+                }
+            }
+            self.index.base.put(&path, branch);
+        }
+    }
+}
+
+impl<'i, 'a, K, V> Drop for DeferredProofMapIndex<'i, 'a, K, V>
+where
+    K: ProofMapKey,
+    V: StorageValue,
+{
+    fn drop(&mut self) {
+        // The tree may be in an intermediate state if an update has panicked,
+        // and the changes are going to be rolled back anyway.
+        if !thread::panicking() {
+            self.flush();
+        }
+    }
+}
+
+impl<'i, 'a, K, V> fmt::Debug for DeferredProofMapIndex<'i, 'a, K, V>
+where
+    K: ProofMapKey,
+    V: StorageValue,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DeferredProofMapIndex")
+            .field("dirty_nodes", &self.dirty.len())
+            .finish()
+    }
+}
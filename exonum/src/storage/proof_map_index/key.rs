@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::cmp::min;
+use std::cmp::{min, Ordering};
 
 use crypto::{Hash, PublicKey, HASH_SIZE};
 use super::super::StorageKey;
@@ -259,6 +259,33 @@ impl PartialEq for ProofPath {
     }
 }
 
+/// Compares paths in the order of the tree traversal: a path goes before its extensions,
+/// and the left subtree goes before the right one.
+///
+/// Paths with different start positions are not comparable.
+impl PartialOrd for ProofPath {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self.start() != other.start() {
+            return None;
+        }
+
+        let common_len = self.common_prefix_len(other);
+        let ordering = if common_len == self.len() && common_len == other.len() {
+            Ordering::Equal
+        } else if common_len == self.len() {
+            Ordering::Less
+        } else if common_len == other.len() {
+            Ordering::Greater
+        } else {
+            match self.bit(common_len) {
+                ChildKind::Left => Ordering::Less,
+                ChildKind::Right => Ordering::Greater,
+            }
+        };
+        Some(ordering)
+    }
+}
+
 impl ::std::fmt::Debug for ProofPath {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
         // 8 bits + '|' symbol per byte.
@@ -436,6 +463,21 @@ fn test_proof_path_common_prefix_len() {
     assert_eq!(b5.prefix(0).common_prefix_len(&b3), 0);
 }
 
+#[test]
+fn test_proof_path_ordering() {
+    let b1 = ProofPath::new(&[0b0000_0010; 32]);
+    let b2 = ProofPath::new(&[0b0000_0001; 32]);
+    let b3 = ProofPath::new(&[0b0000_0011; 32]);
+    assert_eq!(b1.partial_cmp(&b2), Some(Ordering::Less));
+    assert_eq!(b2.partial_cmp(&b3), Some(Ordering::Less));
+    assert_eq!(b3.partial_cmp(&b1), Some(Ordering::Greater));
+    assert_eq!(b1.partial_cmp(&b1), Some(Ordering::Equal));
+    // A prefix goes before its extensions, but after the left neighbor subtree.
+    assert_eq!(b2.prefix(1).partial_cmp(&b2), Some(Ordering::Less));
+    assert_eq!(b2.prefix(1).partial_cmp(&b1), Some(Ordering::Greater));
+    assert_eq!(b1.suffix(1).partial_cmp(&b2), None);
+}
+
 #[test]
 fn test_proof_path_is_leaf() {
     let b = ProofPath::from_raw(*b"\x01qwertyuiopasdfghjklzxcvbnm123456\x00");
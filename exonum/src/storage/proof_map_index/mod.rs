@@ -25,12 +25,16 @@ use self::node::{BranchNode, Node};
 
 pub use self::key::{KEY_SIZE as PROOF_MAP_KEY_SIZE, ProofMapKey, ProofPath};
 pub use self::proof::{BranchProofNode, MapProof, ProofNode};
+pub use self::multiproof::MapMultiProof;
+pub use self::deferred::DeferredProofMapIndex;
 
 #[cfg(test)]
 mod tests;
 mod key;
 mod node;
 mod proof;
+mod multiproof;
+mod deferred;
 
 /// A Merkelized version of a map that provides proofs of existence or non-existence for the map
 /// keys.
@@ -181,6 +185,14 @@ where
         }
     }
 
+    pub(crate) fn from_base(base: BaseIndex<T>) -> Self {
+        ProofMapIndex {
+            base,
+            _k: PhantomData,
+            _v: PhantomData,
+        }
+    }
+
     fn get_root_key(&self) -> Option<ProofPath> {
         self.base.iter::<_, ProofPath, _>(&()).next().map(
             |(k, _): (ProofPath, ())| k,
@@ -428,6 +440,96 @@ where
         }
     }
 
+    // Collects the hashes of the children of the branch that do not contain any of the searched
+    // paths and descends into the other children.
+    fn construct_multiproof(
+        &self,
+        branch: &BranchNode,
+        searched_paths: &[ProofPath],
+        proof: &mut Vec<(ProofPath, Hash)>,
+    ) {
+        for &kind in &[ChildKind::Left, ChildKind::Right] {
+            let child_path = branch.child_path(kind);
+            let child_searched_paths = searched_paths
+                .iter()
+                .filter(|path| path.starts_with(&child_path))
+                .cloned()
+                .collect::<Vec<_>>();
+
+            if child_searched_paths.is_empty() {
+                proof.push((child_path, *branch.child_hash(kind)));
+            } else if !child_path.is_leaf() {
+                match self.get_node_unchecked(&child_path) {
+                    Node::Branch(child_branch) => {
+                        self.construct_multiproof(&child_branch, &child_searched_paths, proof)
+                    }
+                    Node::Leaf(_) => unreachable!("Something went wrong!"),
+                }
+            }
+            // A leaf child that matches a searched path is revealed as a proof entry.
+        }
+    }
+
+    /// Returns the combined proof of existence or non-existence for the specified keys.
+    ///
+    /// The proof contains each distinct key once, ordered by its position in the tree. Unlike
+    /// separate proofs for each key, the parts of the tree shared by several keys are
+    /// included only once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ProofMapIndex};
+    /// use exonum::crypto::hash;
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let mut fork = db.fork();
+    /// let mut index = ProofMapIndex::new(name, &mut fork);
+    ///
+    /// let (h1, h2, h3) = (hash(&[1]), hash(&[2]), hash(&[3]));
+    /// index.put(&h1, 100u64);
+    /// index.put(&h2, 200u64);
+    ///
+    /// let proof = index.get_multiproof(vec![h1, h3]);
+    /// let entries = proof.validate(index.merkle_root()).unwrap();
+    /// assert_eq!(entries.len(), 2);
+    /// assert!(entries.contains(&(&h1, Some(&100))));
+    /// assert!(entries.contains(&(&h3, None)));
+    /// ```
+    pub fn get_multiproof<I>(&self, keys: I) -> MapMultiProof<K, V>
+    where
+        I: IntoIterator<Item = K>,
+    {
+        let mut keys = keys.into_iter()
+            .map(|key| (ProofPath::new(&key), key))
+            .collect::<Vec<_>>();
+        keys.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap());
+        keys.dedup_by(|x, y| x.0 == y.0);
+
+        let searched_paths = keys.iter().map(|&(path, _)| path).collect::<Vec<_>>();
+        let mut proof = Vec::new();
+        match self.get_root_node() {
+            Some((root_path, Node::Leaf(root_value))) => {
+                if !searched_paths.contains(&root_path) {
+                    proof.push((root_path, root_value.hash()));
+                }
+            }
+            Some((_, Node::Branch(branch))) => {
+                self.construct_multiproof(&branch, &searched_paths, &mut proof);
+            }
+            None => {}
+        }
+
+        let entries = keys.into_iter()
+            .map(|(path, key)| {
+                let value = self.base.get(&path);
+                (key, value)
+            })
+            .collect();
+        MapMultiProof::new(entries, proof)
+    }
+
     /// Returns an iterator over the entries of the map in ascending order. The iterator element
     /// type is (K, V).
     ///
@@ -576,6 +678,62 @@ where
             base_iter: self.base.iter_from(&LEAF_KEY_PREFIX, &ProofPath::new(from)),
         }
     }
+
+    /// Returns an iterator over the entries of the map in descending order. The iterator element
+    /// type is (K, V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ProofMapIndex};
+    /// use exonum::crypto::Hash;
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let snapshot = db.snapshot();
+    /// let index: ProofMapIndex<_, Hash, u8> = ProofMapIndex::new(name, &snapshot);
+    ///
+    /// for val in index.iter_rev() {
+    ///     println!("{:?}", val);
+    /// }
+    /// ```
+    pub fn iter_rev(&self) -> ProofMapIndexIter<K, V> {
+        ProofMapIndexIter {
+            base_iter: self.base.iter_rev(&LEAF_KEY_PREFIX),
+            _k: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the entries of the map with the keys in the range `[from, to)`
+    /// in ascending order. The iterator element type is (K, V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ProofMapIndex};
+    /// use exonum::crypto::Hash;
+    ///
+    /// let db = MemoryDB::new();
+    /// let name = "name";
+    /// let snapshot = db.snapshot();
+    /// let index: ProofMapIndex<_, Hash, u8> = ProofMapIndex::new(name, &snapshot);
+    ///
+    /// let from = Hash::new([0; 32]);
+    /// let to = Hash::new([0x80; 32]);
+    /// for val in index.range(&from, &to) {
+    ///     println!("{:?}", val);
+    /// }
+    /// ```
+    pub fn range(&self, from: &K, to: &K) -> ProofMapIndexIter<K, V> {
+        ProofMapIndexIter {
+            base_iter: self.base.range(
+                &LEAF_KEY_PREFIX,
+                &ProofPath::new(from),
+                &ProofPath::new(to),
+            ),
+            _k: PhantomData,
+        }
+    }
 }
 
 impl<'a, K, V> ProofMapIndex<&'a mut Fork, K, V>
@@ -853,6 +1011,36 @@ where
     pub fn clear(&mut self) {
         self.base.clear()
     }
+
+    /// Returns a view of the proof map that defers the recomputation of the hashes.
+    ///
+    /// Updates made through the view only mark the affected nodes of the tree as dirty;
+    /// their hashes are recomputed once when the Merkle root is requested or the view
+    /// is dropped. This makes batch updates considerably cheaper, since the nodes close
+    /// to the root are hashed once per batch rather than once per update.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ProofMapIndex};
+    /// use exonum::crypto::hash;
+    ///
+    /// let db = MemoryDB::new();
+    /// let mut fork = db.fork();
+    /// let mut index = ProofMapIndex::new("name", &mut fork);
+    ///
+    /// let root = {
+    ///     let mut deferred = index.deferred();
+    ///     for i in 0..10u8 {
+    ///         deferred.put(&hash(&[i]), i);
+    ///     }
+    ///     deferred.merkle_root()
+    /// };
+    /// assert_eq!(root, index.merkle_root());
+    /// ```
+    pub fn deferred<'i>(&'i mut self) -> DeferredProofMapIndex<'i, 'a, K, V> {
+        DeferredProofMapIndex::new(self)
+    }
 }
 
 impl<'a, T, K, V> ::std::iter::IntoIterator for &'a ProofMapIndex<T, K, V>
@@ -0,0 +1,212 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+
+use crypto::{Hash, HashStream, CryptoHash};
+use super::super::{Error, StorageValue};
+use super::key::{BitsRange, ChildKind, ProofMapKey, ProofPath};
+use super::node::BranchNode;
+
+/// A proof of existence or non-existence for several keys of a `ProofMapIndex` at once.
+///
+/// The proof consists of the requested entries and of a list of `(ProofPath, Hash)` pairs
+/// for the subtrees of the Merkle Patricia tree that do not contain any requested key.
+/// The pairs are listed in the ascending order of paths, so the proof can be checked
+/// in a single pass. Parts of the tree shared by several keys are included only once.
+///
+/// This struct is created by the [`get_multiproof`] method on [`ProofMapIndex`].
+///
+/// [`get_multiproof`]: struct.ProofMapIndex.html#method.get_multiproof
+/// [`ProofMapIndex`]: struct.ProofMapIndex.html
+#[derive(Debug, Serialize)]
+pub struct MapMultiProof<K, V> {
+    entries: Vec<(K, Option<V>)>,
+    proof: Vec<(ProofPath, Hash)>,
+}
+
+impl<K, V> MapMultiProof<K, V>
+where
+    K: ProofMapKey,
+    V: StorageValue,
+{
+    pub(crate) fn new(entries: Vec<(K, Option<V>)>, proof: Vec<(ProofPath, Hash)>) -> Self {
+        MapMultiProof { entries, proof }
+    }
+
+    /// Returns the requested entries together with their values, if any.
+    pub fn entries(&self) -> &[(K, Option<V>)] {
+        &self.entries
+    }
+
+    /// Returns the hashes of the subtrees which do not contain any requested key.
+    pub fn proof(&self) -> &[(ProofPath, Hash)] {
+        &self.proof
+    }
+
+    /// Checks the internal consistency of the proof and recomputes the Merkle root hash.
+    ///
+    /// If the proof is consistent, returns the recomputed root hash together with every
+    /// requested key and its value (`None` for the keys proven to be absent from the map).
+    /// The caller is responsible for comparing the returned hash with a trusted one;
+    /// see also [`validate`].
+    ///
+    /// [`validate`]: #method.validate
+    pub fn check(&self) -> Result<(Hash, Vec<(&K, Option<&V>)>), Error> {
+        for window in self.proof.windows(2) {
+            let (prev, next) = (&window[0].0, &window[1].0);
+            if prev.partial_cmp(next) != Some(Ordering::Less) || next.starts_with(prev) {
+                return Err(Error::new(format!(
+                    "Proof paths are not ordered or embedded into each other: \
+                     {:?}, {:?}",
+                    prev,
+                    next
+                )));
+            }
+        }
+
+        let mut key_paths = Vec::with_capacity(self.entries.len());
+        for &(ref key, _) in &self.entries {
+            let key_path = ProofPath::new(key);
+            if let Some(&(ref path, _)) = self.proof.iter().find(|&&(ref path, _)| {
+                key_path.starts_with(path)
+            })
+            {
+                return Err(Error::new(format!(
+                    "Proof path {:?} covers the requested key {:?}",
+                    path,
+                    key_path
+                )));
+            }
+            if key_paths.contains(&key_path) {
+                return Err(Error::new(
+                    format!("Duplicate requested key {:?}", key_path),
+                ));
+            }
+            key_paths.push(key_path);
+        }
+
+        let mut nodes = self.proof.clone();
+        nodes.extend(self.entries.iter().zip(&key_paths).filter_map(
+            |(&(_, ref value), path)| value.as_ref().map(|value| (*path, value.hash())),
+        ));
+        nodes.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap());
+
+        let merkle_root = collect(&nodes)?;
+        let entries = self.entries
+            .iter()
+            .map(|&(ref key, ref value)| (key, value.as_ref()))
+            .collect();
+        Ok((merkle_root, entries))
+    }
+
+    /// Verifies the correctness of the proof by the trusted root hash.
+    ///
+    /// If the proof is valid, returns every requested key with `Some(&V)` for the keys
+    /// that exist in the map and `None` for the keys that do not exist.
+    /// If the proof is invalid, `Err` is returned.
+    pub fn validate(&self, merkle_root: Hash) -> Result<Vec<(&K, Option<&V>)>, Error> {
+        let (proof_hash, entries) = self.check()?;
+        if proof_hash != merkle_root {
+            return Err(Error::new(format!(
+                "The proof doesn't match the expected hash! \
+                 Expected: {:?} , from proof: {:?}",
+                merkle_root,
+                proof_hash
+            )));
+        }
+        Ok(entries)
+    }
+}
+
+fn common_prefix(x: &ProofPath, y: &ProofPath) -> ProofPath {
+    x.prefix(x.common_prefix_len(y))
+}
+
+fn hash_branch(left: &(ProofPath, Hash), right: &(ProofPath, Hash)) -> Hash {
+    let mut branch = BranchNode::empty();
+    branch.set_child(ChildKind::Left, &left.0, &left.1);
+    branch.set_child(ChildKind::Right, &right.0, &right.1);
+    branch.hash()
+}
+
+// Replaces two last nodes of the contour with their parent branch and returns
+// the common prefix of the two new last nodes, if any.
+fn fold(contour: &mut Vec<(ProofPath, Hash)>, last_prefix: ProofPath) -> Option<ProofPath> {
+    let right = contour.pop().unwrap();
+    let left = contour.pop().unwrap();
+    contour.push((last_prefix, hash_branch(&left, &right)));
+
+    if contour.len() > 1 {
+        let penultimate = &contour[contour.len() - 2];
+        Some(common_prefix(&penultimate.0, &last_prefix))
+    } else {
+        None
+    }
+}
+
+// Restores the Merkle root hash from the nodes sorted in the ascending order of their paths.
+fn collect(nodes: &[(ProofPath, Hash)]) -> Result<Hash, Error> {
+    for window in nodes.windows(2) {
+        if window[1].0.starts_with(&window[0].0) {
+            return Err(Error::new(format!(
+                "Proof is inconsistent with itself: {:?} is embedded into {:?}",
+                window[1].0,
+                window[0].0
+            )));
+        }
+    }
+
+    match nodes.len() {
+        0 => Ok(Hash::zero()),
+        1 => {
+            let (ref path, ref hash) = nodes[0];
+            if !path.is_leaf() {
+                return Err(Error::new(
+                    format!("A single proof node {:?} is not a leaf", path),
+                ));
+            }
+            Ok(
+                HashStream::new()
+                    .update(path.as_bytes())
+                    .update(hash.as_ref())
+                    .hash(),
+            )
+        }
+        _ => {
+            let mut contour = Vec::with_capacity(nodes.len());
+            let mut last_prefix = common_prefix(&nodes[0].0, &nodes[1].0);
+            contour.push(nodes[0]);
+            contour.push(nodes[1]);
+
+            for node in nodes.iter().skip(2) {
+                let new_prefix = common_prefix(&contour.last().unwrap().0, &node.0);
+                while contour.len() > 1 && new_prefix.len() < last_prefix.len() {
+                    if let Some(prefix) = fold(&mut contour, last_prefix) {
+                        last_prefix = prefix;
+                    }
+                }
+                contour.push(*node);
+                last_prefix = new_prefix;
+            }
+
+            while contour.len() > 1 {
+                if let Some(prefix) = fold(&mut contour, last_prefix) {
+                    last_prefix = prefix;
+                }
+            }
+            Ok(contour[0].1)
+        }
+    }
+}
@@ -16,11 +16,12 @@ use std::collections::HashSet;
 
 use rand::{self, thread_rng, Rng};
 use crypto::{hash, Hash, HashStream};
-use storage::db::Database;
+use storage::db::{Database, Fork, Snapshot};
 use encoding::serialize::json::reexport::to_string;
 use encoding::serialize::reexport::{Serialize, Serializer};
 use super::{ProofMapIndex, ProofPath};
 use super::proof::MapProof;
+use super::multiproof::MapMultiProof;
 use super::key::{KEY_SIZE, LEAF_KEY_PREFIX};
 
 const IDX_NAME: &'static str = "idx_name";
@@ -522,6 +523,69 @@ fn fuzz_insert_after_delete(db: Box<Database>) {
     assert_eq!(index.merkle_root(), saved_hash);
 }
 
+fn index_entries(fork: &Fork) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let mut iter = fork.iter(IDX_NAME, &[]);
+    let mut entries = Vec::new();
+    while let Some((key, value)) = iter.next() {
+        entries.push((key.to_vec(), value.to_vec()));
+    }
+    entries
+}
+
+fn fuzz_deferred(db1: Box<Database>, db2: Box<Database>) {
+    let data = generate_fully_random_data_keys(200);
+    let mut rng = rand::thread_rng();
+    let mut storage1 = db1.fork();
+    let mut storage2 = db2.fork();
+
+    {
+        let mut index1 = ProofMapIndex::new(IDX_NAME, &mut storage1);
+        let mut index2 = ProofMapIndex::new(IDX_NAME, &mut storage2);
+        let mut deferred = index2.deferred();
+        for item in &data[0..100] {
+            index1.put(&item.0, item.1.clone());
+            deferred.put(&item.0, item.1.clone());
+        }
+        assert_eq!(deferred.merkle_root(), index1.merkle_root());
+
+        // Mix insertions, updates and removals between the recomputations.
+        for round in 0..10 {
+            for _ in 0..50 {
+                let item = &data[rng.gen_range(0, data.len())];
+                if rng.gen() {
+                    let value = if rng.gen() { item.1.clone() } else { vec![round] };
+                    index1.put(&item.0, value.clone());
+                    deferred.put(&item.0, value);
+                } else {
+                    index1.remove(&item.0);
+                    deferred.remove(&item.0);
+                }
+            }
+            let root = index1.merkle_root();
+            assert_eq!(deferred.merkle_root(), root);
+            for item in data.iter().take(20) {
+                let proof = deferred.get_proof(&item.0);
+                assert_eq!(proof.merkle_root(), root);
+                assert_eq!(
+                    proof.validate(&item.0, root).unwrap(),
+                    index1.get(&item.0).as_ref()
+                );
+            }
+        }
+
+        // Pending updates are flushed when the view is dropped.
+        for item in &data {
+            index1.remove(&item.0);
+            deferred.remove(&item.0);
+        }
+        for item in &data[100..150] {
+            index1.put(&item.0, item.1.clone());
+            deferred.put(&item.0, item.1.clone());
+        }
+    }
+    assert_eq!(index_entries(&storage2), index_entries(&storage1));
+}
+
 fn iter(db: Box<Database>) {
     let mut fork = db.fork();
     let mut map_index = ProofMapIndex::new(IDX_NAME, &mut fork);
@@ -597,6 +661,111 @@ fn iter(db: Box<Database>) {
     );
 }
 
+fn build_multiproof_in_empty_tree(db: Box<Database>) {
+    let mut storage = db.fork();
+    let table: ProofMapIndex<_, [u8; 32], Vec<u8>> = ProofMapIndex::new(IDX_NAME, &mut storage);
+
+    let proof = table.get_multiproof(vec![[244; 32], [1; 32]]);
+    assert!(proof.proof().is_empty());
+    let entries = proof.validate(table.merkle_root()).unwrap();
+    assert_eq!(entries, vec![(&[244; 32], None), (&[1; 32], None)]);
+}
+
+fn build_multiproof_in_leaf_tree(db: Box<Database>) {
+    let mut storage = db.fork();
+    let mut table = ProofMapIndex::new(IDX_NAME, &mut storage);
+    let root_key = [230u8; 32];
+    let root_val = vec![2];
+    table.put(&root_key, root_val.clone());
+    let table_root = table.merkle_root();
+
+    let proof = table.get_multiproof(vec![[244; 32]]);
+    assert_eq!(proof.proof(), &[(ProofPath::new(&root_key), hash(&root_val))]);
+    let entries = proof.validate(table_root).unwrap();
+    assert_eq!(entries, vec![(&[244; 32], None)]);
+
+    let proof = table.get_multiproof(vec![root_key, [244; 32], root_key]);
+    assert!(proof.proof().is_empty());
+    let (merkle_root, entries) = proof.check().unwrap();
+    assert_eq!(merkle_root, table_root);
+    assert_eq!(entries.len(), 2);
+    assert!(entries.contains(&(&root_key, Some(&root_val))));
+    assert!(entries.contains(&(&[244; 32], None)));
+}
+
+fn fuzz_insert_build_multiproofs(db: Box<Database>) {
+    let data = generate_fully_random_data_keys(200);
+    let mut storage = db.fork();
+    let mut table = ProofMapIndex::new(IDX_NAME, &mut storage);
+    for item in data.iter().take(100) {
+        table.put(&item.0, item.1.clone());
+    }
+    let table_merkle_root = table.merkle_root();
+
+    // Request a mix of present keys (the first half of data) and absent keys (the second one).
+    let mut rng = rand::thread_rng();
+    let mut requested = data.iter()
+        .take(20)
+        .chain(data.iter().skip(100).take(20))
+        .collect::<Vec<_>>();
+    rng.shuffle(&mut requested);
+
+    let proof = table.get_multiproof(requested.iter().map(|item| item.0));
+    let entries = proof.validate(table_merkle_root).unwrap();
+    assert_eq!(entries.len(), requested.len());
+    for item in &data[..20] {
+        assert!(entries.contains(&(&item.0, Some(&item.1))));
+    }
+    for item in &data[100..120] {
+        assert!(entries.contains(&(&item.0, None)));
+    }
+
+    let json_representation = to_string(&proof).unwrap();
+    assert!(json_representation.len() > 0);
+
+    for item in data.iter().take(10) {
+        let proof = table.get_multiproof(vec![item.0]);
+        let entries = proof.validate(table_merkle_root).unwrap();
+        assert_eq!(entries, vec![(&item.0, Some(&item.1))]);
+    }
+
+    // If all the keys are requested, no hashes are needed.
+    let proof = table.get_multiproof(data.iter().map(|item| item.0));
+    assert!(proof.proof().is_empty());
+    let entries = proof.validate(table_merkle_root).unwrap();
+    assert_eq!(entries.iter().filter(|entry| entry.1.is_some()).count(), 100);
+}
+
+fn invalid_multiproofs(db: Box<Database>) {
+    let data = generate_fully_random_data_keys(50);
+    let mut storage = db.fork();
+    let mut table = ProofMapIndex::new(IDX_NAME, &mut storage);
+    for item in &data {
+        table.put(&item.0, item.1.clone());
+    }
+    let table_merkle_root = table.merkle_root();
+
+    let proof = table.get_multiproof(vec![data[0].0, data[1].0]);
+    assert!(proof.validate(table_merkle_root).is_ok());
+    assert!(proof.validate(hash(&[1, 2, 3])).is_err());
+
+    // A proof that hides one of the present keys behind a leaf hash is rejected.
+    let mut leaves = proof.proof().to_vec();
+    leaves.push((ProofPath::new(&data[0].0), hash(&data[0].1)));
+    leaves.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap());
+    let forged = MapMultiProof::new(
+        vec![(data[0].0, None), (data[1].0, Some(data[1].1.clone()))],
+        leaves,
+    );
+    assert!(forged.check().is_err());
+
+    // A proof with unordered paths is rejected.
+    let mut paths = proof.proof().to_vec();
+    paths.reverse();
+    let forged = MapMultiProof::new(proof.entries().to_vec(), paths);
+    assert!(forged.check().is_err());
+}
+
 fn bytes_to_hex<T: AsRef<[u8]> + ?Sized>(bytes: &T) -> String {
     let strings: Vec<String> = bytes
         .as_ref()
@@ -776,6 +945,49 @@ mod memorydb_tests {
         super::fuzz_insert_after_delete(db);
     }
 
+    #[test]
+    fn test_fuzz_deferred() {
+        let dir1 = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let path1 = dir1.path();
+        let db1 = create_database(path1);
+        let dir2 = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let path2 = dir2.path();
+        let db2 = create_database(path2);
+        super::fuzz_deferred(db1, db2);
+    }
+
+    #[test]
+    fn test_build_multiproof_in_empty_tree() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let path = dir.path();
+        let db = create_database(path);
+        super::build_multiproof_in_empty_tree(db);
+    }
+
+    #[test]
+    fn test_build_multiproof_in_leaf_tree() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let path = dir.path();
+        let db = create_database(path);
+        super::build_multiproof_in_leaf_tree(db);
+    }
+
+    #[test]
+    fn test_fuzz_insert_build_multiproofs() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let path = dir.path();
+        let db = create_database(path);
+        super::fuzz_insert_build_multiproofs(db);
+    }
+
+    #[test]
+    fn test_invalid_multiproofs() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let path = dir.path();
+        let db = create_database(path);
+        super::invalid_multiproofs(db);
+    }
+
     #[test]
     fn test_iter() {
         let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
@@ -939,6 +1151,49 @@ mod rocksdb_tests {
         super::fuzz_insert_after_delete(db);
     }
 
+    #[test]
+    fn test_fuzz_deferred() {
+        let dir1 = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let path1 = dir1.path();
+        let db1 = create_database(path1);
+        let dir2 = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let path2 = dir2.path();
+        let db2 = create_database(path2);
+        super::fuzz_deferred(db1, db2);
+    }
+
+    #[test]
+    fn test_build_multiproof_in_empty_tree() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let path = dir.path();
+        let db = create_database(path);
+        super::build_multiproof_in_empty_tree(db);
+    }
+
+    #[test]
+    fn test_build_multiproof_in_leaf_tree() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let path = dir.path();
+        let db = create_database(path);
+        super::build_multiproof_in_leaf_tree(db);
+    }
+
+    #[test]
+    fn test_fuzz_insert_build_multiproofs() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let path = dir.path();
+        let db = create_database(path);
+        super::fuzz_insert_build_multiproofs(db);
+    }
+
+    #[test]
+    fn test_invalid_multiproofs() {
+        let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
+        let path = dir.path();
+        let db = create_database(path);
+        super::invalid_multiproofs(db);
+    }
+
     #[test]
     fn test_iter() {
         let dir = TempDir::new(super::gen_tempdir_name().as_str()).unwrap();
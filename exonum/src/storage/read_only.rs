@@ -0,0 +1,365 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An implementation of the read-only mode of `RocksDB` database.
+
+use exonum_profiler::ProfilerSpan;
+use rocksdb_sys as ffi;
+use rocksdb::utils::get_cf_names;
+
+use std::collections::HashMap;
+use std::collections::Bound::{self, Included, Excluded, Unbounded};
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::marker::PhantomData;
+use std::os::raw::{c_char, c_int, c_void};
+use std::path::Path;
+use std::ptr;
+use std::slice;
+use std::sync::Arc;
+
+use storage::{self, Database, DbOptions, Iter, Iterator, Patch, Snapshot};
+
+/// Database implementation on the top of `RocksDB` backend opened in the read-only mode.
+///
+/// Unlike [`RocksDB`], a read-only database does not lock the database directory, so it can be
+/// opened while the directory is used by a running node, for example, to inspect
+/// the blockchain data with an offline tool. The database contains the data persisted
+/// at the moment it is opened, including the part of the write-ahead log that is not flushed
+/// yet. The later changes become visible after the database is reopened.
+///
+/// Snapshots of the database are created as usual, while [`merge`] and [`merge_sync`]
+/// always return an error.
+///
+/// The database is used by [`Run::db_helper`] if the [`read_only`] option is set.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate exonum;
+/// # extern crate tempdir;
+/// use exonum::storage::{Database, DbOptions, MapIndex, ReadOnlyRocksDB, RocksDB};
+/// # use tempdir::TempDir;
+///
+/// # fn main() {
+/// # let dir = TempDir::new("exonum").unwrap();
+/// # let path = dir.path();
+/// let db = RocksDB::open(path, &DbOptions::default()).unwrap();
+/// let mut fork = db.fork();
+/// MapIndex::new("index", &mut fork).put(&1u8, 2u8);
+/// db.merge(fork.into_patch()).unwrap();
+///
+/// // The database is still open for writing.
+/// let read_only = ReadOnlyRocksDB::open(path, &DbOptions::default()).unwrap();
+/// let snapshot = read_only.snapshot();
+/// let index: MapIndex<_, u8, u8> = MapIndex::new("index", &snapshot);
+/// assert_eq!(index.get(&1), Some(2));
+/// assert!(read_only.merge(read_only.fork().into_patch()).is_err());
+/// # }
+/// ```
+///
+/// [`RocksDB`]: struct.RocksDB.html
+/// [`merge`]: trait.Database.html#tymethod.merge
+/// [`merge_sync`]: trait.Database.html#tymethod.merge_sync
+/// [`Run::db_helper`]: ../helpers/fabric/struct.Run.html#method.db_helper
+/// [`read_only`]: struct.DbOptions.html#structfield.read_only
+pub struct ReadOnlyRocksDB {
+    db: Arc<RawDB>,
+}
+
+/// Handles of an open `RocksDB` instance.
+struct RawDB {
+    db: *mut ffi::rocksdb_t,
+    cfs: HashMap<String, *mut ffi::rocksdb_column_family_handle_t>,
+    read_opts: *mut ffi::rocksdb_readoptions_t,
+}
+
+// `RocksDB` instances can be used from multiple threads, and the read options
+// are never changed after the database is opened.
+unsafe impl Send for RawDB {}
+unsafe impl Sync for RawDB {}
+
+/// A snapshot of a `ReadOnlyRocksDB`. Since the database never changes, the snapshot
+/// just refers to the database.
+struct ReadOnlySnapshot {
+    db: Arc<RawDB>,
+}
+
+/// An iterator over the entries of a `ReadOnlyRocksDB`.
+struct ReadOnlyIterator<'a> {
+    // Null for the absent column families.
+    raw: *mut ffi::rocksdb_iterator_t,
+    reverse: bool,
+    // Whether the current entry is already returned by `next`.
+    advance: bool,
+    _snapshot: PhantomData<&'a ReadOnlySnapshot>,
+}
+
+fn read_only_error() -> storage::Error {
+    storage::Error::new("Database is opened in the read-only mode")
+}
+
+unsafe fn take_error(err: *mut c_char) -> storage::Error {
+    let message = CStr::from_ptr(err).to_string_lossy().into_owned();
+    ffi::rocksdb_free(err as *mut c_void);
+    storage::Error::new(message)
+}
+
+impl ReadOnlyRocksDB {
+    /// Opens a database stored in the specified path in the read-only mode.
+    ///
+    /// Only the `max_open_files` option is taken into account; the database
+    /// is never created if it is missing.
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        options: &DbOptions,
+    ) -> storage::Result<ReadOnlyRocksDB> {
+        let path = path.as_ref();
+        let mut cf_names = get_cf_names(path)?;
+        if !cf_names.iter().any(|name| name == "default") {
+            cf_names.push("default".to_owned());
+        }
+        let c_path = CString::new(path.to_string_lossy().as_bytes()).map_err(|_| {
+            storage::Error::new("Database path contains a null byte")
+        })?;
+        let c_names = cf_names
+            .iter()
+            .map(|name| CString::new(name.as_bytes()).unwrap())
+            .collect::<Vec<_>>();
+        let name_ptrs = c_names.iter().map(|name| name.as_ptr()).collect::<Vec<_>>();
+        let mut handles = vec![ptr::null_mut(); cf_names.len()];
+
+        let _p = ProfilerSpan::new("ReadOnlyRocksDB::open");
+        unsafe {
+            let opts = ffi::rocksdb_options_create();
+            ffi::rocksdb_options_set_max_open_files(opts, options.max_open_files.unwrap_or(-1));
+            let cf_opts = vec![opts as *const _; cf_names.len()];
+            let mut err = ptr::null_mut();
+            let db = ffi::rocksdb_open_for_read_only_column_families(
+                opts,
+                c_path.as_ptr(),
+                cf_names.len() as c_int,
+                name_ptrs.as_ptr(),
+                cf_opts.as_ptr(),
+                handles.as_mut_ptr(),
+                0,
+                &mut err,
+            );
+            // The options are copied by `RocksDB` on open.
+            ffi::rocksdb_options_destroy(opts);
+            if !err.is_null() {
+                return Err(take_error(err));
+            }
+
+            let db = RawDB {
+                db,
+                cfs: cf_names.into_iter().zip(handles).collect(),
+                read_opts: ffi::rocksdb_readoptions_create(),
+            };
+            Ok(ReadOnlyRocksDB { db: Arc::new(db) })
+        }
+    }
+}
+
+impl Database for ReadOnlyRocksDB {
+    fn snapshot(&self) -> Box<Snapshot> {
+        Box::new(ReadOnlySnapshot { db: Arc::clone(&self.db) })
+    }
+
+    fn merge(&self, _: Patch) -> storage::Result<()> {
+        Err(read_only_error())
+    }
+
+    fn merge_sync(&self, _: Patch) -> storage::Result<()> {
+        Err(read_only_error())
+    }
+}
+
+impl ReadOnlySnapshot {
+    fn raw_iter(&self, name: &str) -> *mut ffi::rocksdb_iterator_t {
+        match self.db.cfs.get(name) {
+            Some(&cf) => unsafe {
+                ffi::rocksdb_create_iterator_cf(self.db.db, self.db.read_opts, cf)
+            },
+            None => ptr::null_mut(),
+        }
+    }
+}
+
+impl Snapshot for ReadOnlySnapshot {
+    fn get(&self, name: &str, key: &[u8]) -> Option<Vec<u8>> {
+        let _p = ProfilerSpan::new("ReadOnlySnapshot::get");
+        let cf = *self.db.cfs.get(name)?;
+        unsafe {
+            let mut len = 0;
+            let mut err = ptr::null_mut();
+            let value = ffi::rocksdb_get_cf(
+                self.db.db,
+                self.db.read_opts,
+                cf,
+                key.as_ptr() as *const c_char,
+                key.len(),
+                &mut len,
+                &mut err,
+            );
+            if !err.is_null() {
+                panic!("{}", take_error(err));
+            }
+            if value.is_null() {
+                return None;
+            }
+            let result = slice::from_raw_parts(value as *const u8, len).to_vec();
+            ffi::rocksdb_free(value as *mut c_void);
+            Some(result)
+        }
+    }
+
+    fn iter<'a>(&'a self, name: &str, from: &[u8]) -> Iter<'a> {
+        let _p = ProfilerSpan::new("ReadOnlySnapshot::iter");
+        let raw = self.raw_iter(name);
+        if !raw.is_null() {
+            unsafe { ffi::rocksdb_iter_seek(raw, from.as_ptr() as *const c_char, from.len()) };
+        }
+        Box::new(ReadOnlyIterator {
+            raw,
+            reverse: false,
+            advance: false,
+            _snapshot: PhantomData,
+        })
+    }
+
+    fn iter_rev<'a>(&'a self, name: &str, to: Bound<&[u8]>) -> Iter<'a> {
+        let _p = ProfilerSpan::new("ReadOnlySnapshot::iter_rev");
+        let mut iter = ReadOnlyIterator {
+            raw: self.raw_iter(name),
+            reverse: true,
+            advance: false,
+            _snapshot: PhantomData,
+        };
+        if iter.raw.is_null() {
+            return Box::new(iter);
+        }
+
+        unsafe {
+            match to {
+                Included(key) | Excluded(key) => {
+                    ffi::rocksdb_iter_seek(iter.raw, key.as_ptr() as *const c_char, key.len());
+                    // Seeking goes to the first key that is not less than the bound, so if there
+                    // is no such key, the iteration should start from the last entry.
+                    if ffi::rocksdb_iter_valid(iter.raw) == 0 {
+                        ffi::rocksdb_iter_seek_to_last(iter.raw);
+                    }
+                }
+                Unbounded => ffi::rocksdb_iter_seek_to_last(iter.raw),
+            }
+        }
+        let skip_first = match (iter.current().map(|(key, _)| key), to) {
+            (Some(key), Included(to)) => key > to,
+            (Some(key), Excluded(to)) => key >= to,
+            _ => false,
+        };
+        if skip_first {
+            unsafe { ffi::rocksdb_iter_prev(iter.raw) };
+        }
+        Box::new(iter)
+    }
+}
+
+impl<'a> ReadOnlyIterator<'a> {
+    fn is_valid(&self) -> bool {
+        !self.raw.is_null() && unsafe { ffi::rocksdb_iter_valid(self.raw) != 0 }
+    }
+
+    fn current(&self) -> Option<(&[u8], &[u8])> {
+        if !self.is_valid() {
+            return None;
+        }
+        unsafe {
+            let mut key_len = 0;
+            let key = ffi::rocksdb_iter_key(self.raw, &mut key_len);
+            let mut value_len = 0;
+            let value = ffi::rocksdb_iter_value(self.raw, &mut value_len);
+            Some((
+                slice::from_raw_parts(key as *const u8, key_len),
+                slice::from_raw_parts(value as *const u8, value_len),
+            ))
+        }
+    }
+
+    fn skip_returned(&mut self) {
+        if self.advance {
+            self.advance = false;
+            unsafe {
+                if self.reverse {
+                    ffi::rocksdb_iter_prev(self.raw);
+                } else {
+                    ffi::rocksdb_iter_next(self.raw);
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for ReadOnlyIterator<'a> {
+    fn next(&mut self) -> Option<(&[u8], &[u8])> {
+        let _p = ProfilerSpan::new("ReadOnlyIterator::next");
+        self.skip_returned();
+        self.advance = self.is_valid();
+        self.current()
+    }
+
+    fn peek(&mut self) -> Option<(&[u8], &[u8])> {
+        let _p = ProfilerSpan::new("ReadOnlyIterator::peek");
+        self.skip_returned();
+        self.current()
+    }
+}
+
+impl<'a> Drop for ReadOnlyIterator<'a> {
+    fn drop(&mut self) {
+        if !self.raw.is_null() {
+            unsafe { ffi::rocksdb_iter_destroy(self.raw) };
+        }
+    }
+}
+
+impl Drop for RawDB {
+    fn drop(&mut self) {
+        unsafe {
+            for &cf in self.cfs.values() {
+                ffi::rocksdb_column_family_handle_destroy(cf);
+            }
+            ffi::rocksdb_readoptions_destroy(self.read_opts);
+            ffi::rocksdb_close(self.db);
+        }
+    }
+}
+
+impl From<ReadOnlyRocksDB> for Arc<Database> {
+    fn from(db: ReadOnlyRocksDB) -> Arc<Database> {
+        Arc::from(Box::new(db) as Box<Database>)
+    }
+}
+
+impl fmt::Debug for ReadOnlyRocksDB {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ReadOnlyRocksDB(..)")
+    }
+}
+
+impl fmt::Debug for ReadOnlySnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ReadOnlySnapshot(..)")
+    }
+}
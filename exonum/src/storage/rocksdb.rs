@@ -15,7 +15,7 @@
 //! An implementation of `RocksDB` database.
 
 use exonum_profiler::ProfilerSpan;
-use rocksdb::{self, Options as RocksDbOptions, WriteBatch, DBIterator};
+use rocksdb::{self, Options as RocksDbOptions, WriteBatch, DBIterator, DBCompressionType};
 use rocksdb::utils::get_cf_names;
 
 use std::mem;
@@ -24,8 +24,9 @@ use std::path::Path;
 use std::fmt;
 use std::error::Error;
 use std::iter::Peekable;
+use std::collections::Bound::{self, Included, Excluded, Unbounded};
 
-use storage::{self, DbOptions, Database, Iterator, Iter, Snapshot, Patch};
+use storage::{self, CompressionType, DbOptions, Database, Iterator, Iter, Snapshot, Patch};
 use storage::db::Change;
 
 pub use rocksdb::WriteOptions as RocksDBWriteOptions;
@@ -40,6 +41,7 @@ impl From<rocksdb::Error> for storage::Error {
 /// Database implementation on the top of `RocksDB` backend.
 pub struct RocksDB {
     db: Arc<rocksdb::DB>,
+    options: DbOptions,
 }
 
 impl DbOptions {
@@ -47,10 +49,39 @@ impl DbOptions {
         let mut defaults = RocksDbOptions::default();
         defaults.create_if_missing(self.create_if_missing);
         defaults.set_max_open_files(self.max_open_files.unwrap_or(-1));
+        if let Some(compression_type) = self.compression_type {
+            defaults.set_compression_type(compression_type.into());
+        }
+        if let Some(size) = self.write_buffer_size {
+            defaults.set_write_buffer_size(size);
+        }
+        if self.block_cache_size.is_some() || self.bloom_filter_bits_per_key.is_some() {
+            let mut block_opts = RocksBlockOptions::default();
+            if let Some(size) = self.block_cache_size {
+                block_opts.set_lru_cache(size);
+            }
+            if let Some(bits_per_key) = self.bloom_filter_bits_per_key {
+                block_opts.set_bloom_filter(bits_per_key, true);
+            }
+            defaults.set_block_based_table_factory(&block_opts);
+        }
         defaults
     }
 }
 
+impl From<CompressionType> for DBCompressionType {
+    fn from(compression_type: CompressionType) -> DBCompressionType {
+        match compression_type {
+            CompressionType::None => DBCompressionType::None,
+            CompressionType::Snappy => DBCompressionType::Snappy,
+            CompressionType::Zlib => DBCompressionType::Zlib,
+            CompressionType::Bz2 => DBCompressionType::Bz2,
+            CompressionType::Lz4 => DBCompressionType::Lz4,
+            CompressionType::Lz4hc => DBCompressionType::Lz4hc,
+        }
+    }
+}
+
 /// A snapshot of a `RocksDB`.
 pub struct RocksDBSnapshot {
     snapshot: rocksdb::Snapshot<'static>,
@@ -75,7 +106,10 @@ impl RocksDB {
                 rocksdb::DB::open(&options.to_rocksdb(), path)?
             }
         };
-        Ok(RocksDB { db: Arc::new(db) })
+        Ok(RocksDB {
+            db: Arc::new(db),
+            options: *options,
+        })
     }
 
     fn do_merge(&self, patch: Patch, w_opts: &RocksDBWriteOptions) -> storage::Result<()> {
@@ -86,7 +120,7 @@ impl RocksDB {
                 Some(cf) => cf,
                 None => {
                     self.db
-                        .create_cf(&cf_name, &DbOptions::default().to_rocksdb())
+                        .create_cf(&cf_name, &self.options.to_rocksdb())
                         .unwrap()
                 }
             };
@@ -111,7 +145,8 @@ impl Database for RocksDB {
     }
 
     fn merge(&self, patch: Patch) -> storage::Result<()> {
-        let w_opts = RocksDBWriteOptions::default();
+        let mut w_opts = RocksDBWriteOptions::default();
+        w_opts.set_sync(self.options.wal_sync);
         self.do_merge(patch, &w_opts)
     }
 
@@ -152,6 +187,41 @@ impl Snapshot for RocksDBSnapshot {
             value: None,
         })
     }
+
+    fn iter_rev<'a>(&'a self, name: &str, to: Bound<&[u8]>) -> Iter<'a> {
+        use rocksdb::{IteratorMode, Direction};
+        let _p = ProfilerSpan::new("RocksDBSnapshot::iter_rev");
+        let mut iter = match self._db.cf_handle(name) {
+            Some(cf) => {
+                let mode = match to {
+                    Included(key) | Excluded(key) => IteratorMode::From(key, Direction::Reverse),
+                    Unbounded => IteratorMode::End,
+                };
+                let iter = self.snapshot.iterator_cf(cf, mode).unwrap();
+                // Seeking goes to the first key that is not less than the bound, so if there is
+                // no such key, the iteration should start from the last entry.
+                if iter.valid() {
+                    iter
+                } else {
+                    self.snapshot.iterator_cf(cf, IteratorMode::End).unwrap()
+                }
+            }
+            None => self.snapshot.iterator(IteratorMode::End),
+        }.peekable();
+        let skip_first = match (iter.peek(), to) {
+            (Some(&(ref key, _)), Included(to)) => &key[..] > to,
+            (Some(&(ref key, _)), Excluded(to)) => &key[..] >= to,
+            _ => false,
+        };
+        if skip_first {
+            iter.next();
+        }
+        Box::new(RocksDBIterator {
+            iter,
+            key: None,
+            value: None,
+        })
+    }
 }
 
 impl Iterator for RocksDBIterator {
@@ -374,6 +374,49 @@ where
     pub fn iter_from(&self, from: u64) -> SparseListIndexIter<V> {
         SparseListIndexIter { base_iter: self.base.iter_from(&(), &from) }
     }
+
+    /// Returns an iterator over the list in reverse order. The iterator element type is (u64, V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, SparseListIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let mut fork = db.fork();
+    /// let mut index = SparseListIndex::new("name", &mut fork);
+    ///
+    /// index.extend([1, 2, 3, 4, 5].iter().cloned());
+    /// index.remove(3);
+    ///
+    /// let latest = index.iter_rev().take(2).collect::<Vec<_>>();
+    /// assert_eq!(vec![(4, 5), (2, 3)], latest);
+    /// ```
+    pub fn iter_rev(&self) -> SparseListIndexIter<V> {
+        SparseListIndexIter { base_iter: self.base.range_rev(&(), &0u64, &self.capacity()) }
+    }
+
+    /// Returns an iterator over the items of the list with indices in the range `[from, to)`.
+    /// The iterator element type is (u64, V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, SparseListIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let mut fork = db.fork();
+    /// let mut index = SparseListIndex::new("name", &mut fork);
+    ///
+    /// index.extend([1, 2, 3, 4, 5].iter().cloned());
+    /// index.remove(2);
+    ///
+    /// let items = index.range(1, 4).collect::<Vec<_>>();
+    /// assert_eq!(vec![(1, 2), (3, 4)], items);
+    /// ```
+    pub fn range(&self, from: u64, to: u64) -> SparseListIndexIter<V> {
+        SparseListIndexIter { base_iter: self.base.range(&(), &from, &to) }
+    }
 }
 
 
@@ -0,0 +1,452 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Statistics of the storage operations per index.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::collections::Bound;
+use std::collections::btree_map::Iter as BtmIter;
+use std::fmt;
+use std::mem;
+use std::ops::AddAssign;
+use std::sync::{Arc, Mutex};
+
+use super::{Change, Database, Iter, Patch, Result, Snapshot};
+
+/// Counts of the storage operations with an index.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexStats {
+    /// Number of reads from the index: lookups of the values and creations of iterators.
+    pub reads: u64,
+    /// Number of the written values.
+    pub writes: u64,
+    /// Number of the removed keys.
+    pub deletes: u64,
+    /// Total size in bytes of the written keys and values.
+    pub bytes_written: u64,
+}
+
+impl AddAssign for IndexStats {
+    fn add_assign(&mut self, other: IndexStats) {
+        self.reads += other.reads;
+        self.writes += other.writes;
+        self.deletes += other.deletes;
+        self.bytes_written += other.bytes_written;
+    }
+}
+
+/// Statistics of the storage operations per index name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatchStats {
+    indexes: BTreeMap<String, IndexStats>,
+}
+
+impl PatchStats {
+    /// Returns the statistics of the index with the given name.
+    pub fn get(&self, name: &str) -> Option<&IndexStats> {
+        self.indexes.get(name)
+    }
+
+    /// Returns an iterator over the statistics of the indexes ordered by the index names.
+    pub fn iter(&self) -> BtmIter<String, IndexStats> {
+        self.indexes.iter()
+    }
+
+    /// Returns the sum of the statistics of all indexes.
+    pub fn total(&self) -> IndexStats {
+        self.indexes.values().fold(IndexStats::default(), |mut acc, stats| {
+            acc += *stats;
+            acc
+        })
+    }
+
+    fn entry(&mut self, name: &str) -> &mut IndexStats {
+        if !self.indexes.contains_key(name) {
+            self.indexes.insert(name.to_owned(), IndexStats::default());
+        }
+        self.indexes.get_mut(name).unwrap()
+    }
+}
+
+impl<'a> AddAssign<&'a PatchStats> for PatchStats {
+    fn add_assign(&mut self, other: &PatchStats) {
+        for (name, stats) in other.iter() {
+            *self.entry(name) += *stats;
+        }
+    }
+}
+
+impl Patch {
+    /// Returns the statistics of the changes in the patch. The numbers of reads are zero;
+    /// they are counted by [`MeteredDB`].
+    ///
+    /// [`MeteredDB`]: struct.MeteredDB.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{Database, MemoryDB};
+    ///
+    /// let db = MemoryDB::new();
+    /// let mut fork = db.fork();
+    /// fork.put("index", vec![1], vec![2, 3]);
+    /// fork.remove("index", vec![4]);
+    ///
+    /// let stats = fork.into_patch().stats();
+    /// let index_stats = stats.get("index").unwrap();
+    /// assert_eq!(index_stats.writes, 1);
+    /// assert_eq!(index_stats.deletes, 1);
+    /// assert_eq!(index_stats.bytes_written, 3);
+    /// ```
+    pub fn stats(&self) -> PatchStats {
+        let mut stats = PatchStats::default();
+        for (name, changes) in self.iter() {
+            let index_stats = stats.entry(name);
+            for (key, change) in changes.iter() {
+                match *change {
+                    Change::Put(ref value) => {
+                        index_stats.writes += 1;
+                        index_stats.bytes_written += (key.len() + value.len()) as u64;
+                    }
+                    Change::Delete => index_stats.deletes += 1,
+                }
+            }
+        }
+        stats
+    }
+}
+
+/// Cumulative statistics of the patches merged into a database.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageStats {
+    /// Number of the merged patches.
+    pub patches: u64,
+    /// Statistics of the last merged patch.
+    pub last_patch: PatchStats,
+    /// Statistics of all the merged patches.
+    pub total: PatchStats,
+}
+
+impl StorageStats {
+    /// Adds the statistics of a merged patch.
+    pub fn record(&mut self, stats: PatchStats) {
+        self.patches += 1;
+        self.total += &stats;
+        self.last_patch = stats;
+    }
+}
+
+/// Statistics shared between a `MeteredDB` and its snapshots.
+#[derive(Debug, Default)]
+struct Meter {
+    // Reads accounted since the last merge.
+    reads: HashMap<String, u64>,
+    stats: StorageStats,
+}
+
+/// Database wrapper that counts the storage operations per index.
+///
+/// Each lookup of a value and each creation of an iterator in a snapshot of the database
+/// is counted as a read; the reads of a snapshot are accounted when it is dropped.
+/// Forks only pass to their snapshots the reads, which cannot be answered from the changes
+/// of the fork. The writes, deletes and written bytes are counted for each merged patch
+/// with [`Patch::stats`]. The statistics of a merged patch include the reads accounted
+/// since the previous merge, so the reads made while a block is executed are attributed
+/// to that block.
+///
+/// The statistics are collected for the nodes with the [`storage_stats`] database option.
+/// Without the wrapper, the storage operations are not counted at all.
+///
+/// # Examples
+///
+/// ```
+/// use exonum::storage::{Database, MapIndex, MemoryDB, MeteredDB};
+///
+/// let db = MeteredDB::new(Box::new(MemoryDB::new()));
+/// let mut fork = db.fork();
+/// MapIndex::new("index", &mut fork).put(&1_u64, 2_u64);
+/// db.merge(fork.into_patch()).unwrap();
+///
+/// let snapshot = db.snapshot();
+/// let index: MapIndex<_, u64, u64> = MapIndex::new("index", &snapshot);
+/// assert_eq!(index.get(&1), Some(2));
+/// drop(index);
+/// drop(snapshot);
+/// db.merge(db.fork().into_patch()).unwrap();
+///
+/// let stats = db.storage_stats().unwrap();
+/// assert_eq!(stats.patches, 2);
+/// assert_eq!(stats.last_patch.get("index").unwrap().reads, 1);
+/// assert_eq!(stats.total.get("index").unwrap().writes, 1);
+/// ```
+///
+/// [`Patch::stats`]: struct.Patch.html#method.stats
+/// [`storage_stats`]: struct.DbOptions.html#structfield.storage_stats
+pub struct MeteredDB {
+    db: Box<Database>,
+    meter: Arc<Mutex<Meter>>,
+}
+
+/// A snapshot of a `MeteredDB`.
+struct MeteredSnapshot {
+    snapshot: Box<Snapshot>,
+    reads: RefCell<HashMap<String, u64>>,
+    meter: Arc<Mutex<Meter>>,
+}
+
+impl MeteredDB {
+    /// Creates a wrapper counting the storage operations with the specified database.
+    pub fn new(db: Box<Database>) -> Self {
+        MeteredDB {
+            db,
+            meter: Arc::new(Mutex::new(Meter::default())),
+        }
+    }
+
+    fn do_merge(&self, patch: Patch, sync: bool) -> Result<()> {
+        let mut stats = patch.stats();
+        if sync {
+            self.db.merge_sync(patch)?;
+        } else {
+            self.db.merge(patch)?;
+        }
+
+        let mut meter = self.meter.lock().unwrap();
+        for (name, reads) in meter.reads.drain() {
+            stats.entry(&name).reads += reads;
+        }
+        for (name, index_stats) in stats.iter() {
+            metric!(&format!("storage.{}.reads", name), index_stats.reads);
+            metric!(&format!("storage.{}.writes", name), index_stats.writes);
+            metric!(&format!("storage.{}.deletes", name), index_stats.deletes);
+            metric!(
+                &format!("storage.{}.bytes_written", name),
+                index_stats.bytes_written
+            );
+        }
+        meter.stats.record(stats);
+        Ok(())
+    }
+}
+
+impl Database for MeteredDB {
+    fn snapshot(&self) -> Box<Snapshot> {
+        Box::new(MeteredSnapshot {
+            snapshot: self.db.snapshot(),
+            reads: RefCell::new(HashMap::new()),
+            meter: Arc::clone(&self.meter),
+        })
+    }
+
+    fn merge(&self, patch: Patch) -> Result<()> {
+        self.do_merge(patch, false)
+    }
+
+    fn merge_sync(&self, patch: Patch) -> Result<()> {
+        self.do_merge(patch, true)
+    }
+
+    fn storage_stats(&self) -> Option<StorageStats> {
+        Some(self.meter.lock().unwrap().stats.clone())
+    }
+}
+
+impl MeteredSnapshot {
+    fn count_read(&self, name: &str) {
+        let mut reads = self.reads.borrow_mut();
+        if let Some(count) = reads.get_mut(name) {
+            *count += 1;
+            return;
+        }
+        reads.insert(name.to_owned(), 1);
+    }
+}
+
+impl Snapshot for MeteredSnapshot {
+    fn get(&self, name: &str, key: &[u8]) -> Option<Vec<u8>> {
+        self.count_read(name);
+        self.snapshot.get(name, key)
+    }
+
+    fn contains(&self, name: &str, key: &[u8]) -> bool {
+        self.count_read(name);
+        self.snapshot.contains(name, key)
+    }
+
+    fn iter<'a>(&'a self, name: &str, from: &[u8]) -> Iter<'a> {
+        self.count_read(name);
+        self.snapshot.iter(name, from)
+    }
+
+    fn iter_rev<'a>(&'a self, name: &str, to: Bound<&[u8]>) -> Iter<'a> {
+        self.count_read(name);
+        self.snapshot.iter_rev(name, to)
+    }
+
+    fn range<'a>(&'a self, name: &str, from: &[u8], to: &[u8]) -> Iter<'a> {
+        self.count_read(name);
+        self.snapshot.range(name, from, to)
+    }
+
+    fn range_rev<'a>(&'a self, name: &str, from: &[u8], to: &[u8]) -> Iter<'a> {
+        self.count_read(name);
+        self.snapshot.range_rev(name, from, to)
+    }
+}
+
+impl Drop for MeteredSnapshot {
+    fn drop(&mut self) {
+        let reads = mem::replace(self.reads.get_mut(), HashMap::new());
+        if reads.is_empty() {
+            return;
+        }
+        // The lock can be poisoned if the snapshot is dropped while unwinding.
+        if let Ok(mut meter) = self.meter.lock() {
+            for (name, count) in reads {
+                *meter.reads.entry(name).or_insert(0) += count;
+            }
+        }
+    }
+}
+
+impl From<MeteredDB> for Arc<Database> {
+    fn from(db: MeteredDB) -> Arc<Database> {
+        Arc::from(Box::new(db) as Box<Database>)
+    }
+}
+
+impl fmt::Debug for MeteredDB {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MeteredDB").finish()
+    }
+}
+
+impl fmt::Debug for MeteredSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MeteredSnapshot(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use storage::{Database, MemoryDB, Snapshot};
+    use super::{IndexStats, MeteredDB, StorageStats};
+
+    #[test]
+    fn test_patch_stats() {
+        let db = MemoryDB::new();
+        let mut fork = db.fork();
+        fork.put("a", vec![1], vec![1, 2]);
+        fork.put("a", vec![2], vec![]);
+        fork.remove("b", vec![1]);
+        assert_eq!(fork.get("a", &[1]), Some(vec![1, 2]));
+        assert!(!fork.contains("c", &[1]));
+        fork.iter("c", &[]).next();
+
+        let mut other = db.fork();
+        other.put("b", vec![2, 3], vec![4]);
+        other.get("a", &[3]);
+        fork.merge(other.into_patch());
+
+        let stats = fork.into_patch().stats();
+        assert_eq!(
+            stats.get("a"),
+            Some(&IndexStats {
+                reads: 0,
+                writes: 2,
+                deletes: 0,
+                bytes_written: 4,
+            })
+        );
+        assert_eq!(
+            stats.get("b"),
+            Some(&IndexStats {
+                reads: 0,
+                writes: 1,
+                deletes: 1,
+                bytes_written: 3,
+            })
+        );
+        assert_eq!(stats.get("c"), None);
+        assert_eq!(stats.iter().count(), 2);
+        assert_eq!(
+            stats.total(),
+            IndexStats {
+                reads: 0,
+                writes: 3,
+                deletes: 1,
+                bytes_written: 7,
+            }
+        );
+
+        let mut storage_stats = StorageStats::default();
+        storage_stats.record(stats.clone());
+        storage_stats.record(stats.clone());
+        assert_eq!(storage_stats.patches, 2);
+        assert_eq!(storage_stats.last_patch, stats);
+        assert_eq!(storage_stats.total.get("b").unwrap().bytes_written, 6);
+    }
+
+    #[test]
+    fn test_metered_db() {
+        let db = MeteredDB::new(Box::new(MemoryDB::new()));
+        assert_eq!(db.storage_stats(), Some(StorageStats::default()));
+
+        let mut fork = db.fork();
+        fork.put("a", vec![1], vec![1, 2]);
+        // Answered from the changes of the fork.
+        assert_eq!(fork.get("a", &[1]), Some(vec![1, 2]));
+        assert!(!fork.contains("a", &[2]));
+        db.merge(fork.into_patch()).unwrap();
+
+        let stats = db.storage_stats().unwrap();
+        assert_eq!(stats.patches, 1);
+        assert_eq!(
+            stats.last_patch.get("a"),
+            Some(&IndexStats {
+                reads: 1,
+                writes: 1,
+                deletes: 0,
+                bytes_written: 3,
+            })
+        );
+
+        // Reads of the snapshots are accounted when the snapshots are dropped.
+        let snapshot = db.snapshot();
+        assert_eq!(snapshot.get("a", &[1]), Some(vec![1, 2]));
+        snapshot.iter("b", &[]).next();
+        snapshot.range("b", &[], &[1]).next();
+        db.merge_sync(db.fork().into_patch()).unwrap();
+        assert_eq!(db.storage_stats().unwrap().last_patch.iter().count(), 0);
+        drop(snapshot);
+
+        let mut fork = db.fork();
+        fork.remove("a", vec![1]);
+        db.merge(fork.into_patch()).unwrap();
+        let stats = db.storage_stats().unwrap();
+        assert_eq!(stats.patches, 3);
+        assert_eq!(
+            stats.last_patch.get("a"),
+            Some(&IndexStats {
+                reads: 1,
+                writes: 0,
+                deletes: 1,
+                bytes_written: 0,
+            })
+        );
+        assert_eq!(stats.last_patch.get("b").unwrap().reads, 2);
+        assert_eq!(stats.total.get("a").unwrap().reads, 2);
+    }
+}
@@ -12,7 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::{Database, Snapshot, Fork};
+use std::time::Duration;
+
+use super::{Database, Snapshot, Fork, ListIndex, MapIndex, ProofMapIndex, OperationsLimitExceeded,
+            TimeLimitExceeded, index_info};
 
 const IDX_NAME: &'static str = "idx_name";
 
@@ -172,6 +175,322 @@ fn changelog<T: Database>(db: T) {
     assert_eq!(fork.get(IDX_NAME, &[4]), None);
 }
 
+fn nested_savepoints<T: Database>(db: T) {
+    let mut fork = db.fork();
+    fork.put(IDX_NAME, vec![1], vec![1]);
+
+    let outer = fork.checkpoint();
+    fork.put(IDX_NAME, vec![2], vec![2]);
+
+    let middle = fork.checkpoint();
+    fork.put(IDX_NAME, vec![1], vec![10]);
+    fork.remove(IDX_NAME, vec![2]);
+
+    let inner = fork.checkpoint();
+    fork.put(IDX_NAME, vec![3], vec![3]);
+    assert_eq!(fork.get(IDX_NAME, &[3]), Some(vec![3]));
+
+    // Rolling back the inner savepoint does not affect the enclosing ones.
+    fork.rollback_to(inner);
+    assert_eq!(fork.get(IDX_NAME, &[1]), Some(vec![10]));
+    assert_eq!(fork.get(IDX_NAME, &[2]), None);
+    assert_eq!(fork.get(IDX_NAME, &[3]), None);
+
+    // Released changes become a part of the enclosing savepoint.
+    fork.put(IDX_NAME, vec![4], vec![4]);
+    fork.release(middle);
+    assert_eq!(fork.get(IDX_NAME, &[4]), Some(vec![4]));
+
+    fork.rollback_to(outer);
+    assert_eq!(fork.get(IDX_NAME, &[1]), Some(vec![1]));
+    assert_eq!(fork.get(IDX_NAME, &[2]), None);
+    assert_eq!(fork.get(IDX_NAME, &[4]), None);
+
+    // Rolling back an outer savepoint discards the nested ones.
+    let outer = fork.checkpoint();
+    fork.put(IDX_NAME, vec![2], vec![20]);
+    fork.checkpoint();
+    fork.put(IDX_NAME, vec![3], vec![30]);
+    fork.checkpoint();
+    fork.rollback_to(outer);
+    assert_eq!(fork.get(IDX_NAME, &[2]), None);
+    assert_eq!(fork.get(IDX_NAME, &[3]), None);
+
+    // `commit` and `rollback` work with the latest savepoint.
+    fork.checkpoint();
+    fork.put(IDX_NAME, vec![2], vec![2]);
+    fork.checkpoint();
+    fork.put(IDX_NAME, vec![3], vec![3]);
+    fork.commit();
+    fork.rollback();
+    assert_eq!(fork.get(IDX_NAME, &[2]), None);
+    assert_eq!(fork.get(IDX_NAME, &[3]), None);
+
+    db.merge(fork.into_patch()).unwrap();
+    let snapshot = db.snapshot();
+    assert_eq!(snapshot.get(IDX_NAME, &[1]), Some(vec![1]));
+    assert_eq!(snapshot.get(IDX_NAME, &[2]), None);
+}
+
+fn rollback_remove_by_prefix<T: Database>(db: T) {
+    let mut fork = db.fork();
+    fork.put(IDX_NAME, vec![1, 1], vec![1]);
+    db.merge(fork.into_patch()).unwrap();
+
+    let mut fork = db.fork();
+    fork.put(IDX_NAME, vec![1, 2], vec![2]);
+    fork.put(IDX_NAME, vec![2, 1], vec![3]);
+
+    fork.checkpoint();
+    fork.remove_by_prefix(IDX_NAME, Some(&vec![1]));
+    assert_eq!(fork.get(IDX_NAME, &[1, 1]), None);
+    assert_eq!(fork.get(IDX_NAME, &[1, 2]), None);
+    fork.remove_by_prefix(IDX_NAME, None);
+    assert_eq!(fork.get(IDX_NAME, &[2, 1]), None);
+    fork.rollback();
+
+    assert_eq!(fork.get(IDX_NAME, &[1, 1]), Some(vec![1]));
+    assert_eq!(fork.get(IDX_NAME, &[1, 2]), Some(vec![2]));
+    assert_eq!(fork.get(IDX_NAME, &[2, 1]), Some(vec![3]));
+}
+
+fn fork_iter_rev<T: Database>(db: T) {
+    use std::collections::Bound::*;
+
+    let mut fork = db.fork();
+    fork.put(IDX_NAME, vec![10], vec![10]);
+    fork.put(IDX_NAME, vec![20], vec![20]);
+    fork.put(IDX_NAME, vec![30], vec![30]);
+    fork.put("other_idx", vec![40], vec![40]);
+    db.merge(fork.into_patch()).unwrap();
+
+    let mut fork = db.fork();
+    fork.put(IDX_NAME, vec![5], vec![5]);
+    fork.put(IDX_NAME, vec![20], vec![21]);
+    fork.put(IDX_NAME, vec![35], vec![35]);
+    fork.remove(IDX_NAME, vec![30]);
+    fork.remove(IDX_NAME, vec![15]);
+
+    fn collect(mut iter: ::storage::Iter) -> Vec<(u8, u8)> {
+        let mut values = Vec::new();
+        while let Some((k, v)) = iter.next() {
+            values.push((k[0], v[0]));
+        }
+        values
+    }
+
+    let snapshot = db.snapshot();
+    assert_eq!(
+        collect(snapshot.iter_rev(IDX_NAME, Unbounded)),
+        vec![(30, 30), (20, 20), (10, 10)]
+    );
+    assert_eq!(
+        collect(snapshot.iter_rev(IDX_NAME, Included(&[20]))),
+        vec![(20, 20), (10, 10)]
+    );
+    assert_eq!(
+        collect(snapshot.iter_rev(IDX_NAME, Excluded(&[20]))),
+        vec![(10, 10)]
+    );
+    assert_eq!(
+        collect(snapshot.iter_rev(IDX_NAME, Excluded(&[100]))),
+        vec![(30, 30), (20, 20), (10, 10)]
+    );
+    assert_eq!(collect(snapshot.iter_rev(IDX_NAME, Excluded(&[10]))), vec![]);
+    assert_eq!(collect(snapshot.iter_rev("absent_idx", Unbounded)), vec![]);
+    assert_eq!(
+        collect(snapshot.range(IDX_NAME, &[10], &[30])),
+        vec![(10, 10), (20, 20)]
+    );
+    assert_eq!(
+        collect(snapshot.range_rev(IDX_NAME, &[15], &[35])),
+        vec![(30, 30), (20, 20)]
+    );
+
+    assert_eq!(
+        collect(fork.iter_rev(IDX_NAME, Unbounded)),
+        vec![(35, 35), (20, 21), (10, 10), (5, 5)]
+    );
+    assert_eq!(
+        collect(fork.iter_rev(IDX_NAME, Included(&[30]))),
+        vec![(20, 21), (10, 10), (5, 5)]
+    );
+    assert_eq!(
+        collect(fork.iter_rev(IDX_NAME, Excluded(&[10]))),
+        vec![(5, 5)]
+    );
+    assert_eq!(
+        collect(fork.range(IDX_NAME, &[6], &[35])),
+        vec![(10, 10), (20, 21)]
+    );
+    assert_eq!(
+        collect(fork.range_rev(IDX_NAME, &[5], &[20])),
+        vec![(10, 10), (5, 5)]
+    );
+}
+
+fn released_savepoint<T: Database>(db: T) {
+    let mut fork = db.fork();
+    let savepoint = fork.checkpoint();
+    fork.release(savepoint);
+    fork.checkpoint();
+    fork.rollback_to(savepoint);
+}
+
+fn drop_index<T: Database>(db: T) {
+    let mut fork = db.fork();
+    {
+        let mut map = MapIndex::new("map", &mut fork);
+        map.put(&1u8, 1u8);
+        map.put(&2u8, 2u8);
+        MapIndex::new_in_family("family", &1u8, &mut fork).put(&1u8, 1u8);
+        MapIndex::new_in_family("family", &2u8, &mut fork).put(&1u8, 2u8);
+    }
+    db.merge(fork.into_patch()).unwrap();
+
+    let mut fork = db.fork();
+    MapIndex::new("map", &mut fork).put(&3u8, 3u8);
+    fork.drop_index("map");
+    fork.drop_index("family");
+    assert!(index_info(&fork, "map").is_none());
+    assert!(index_info(&fork, "family").is_none());
+    assert!(fork.iter("map", &[]).next().is_none());
+    assert!(fork.iter("family", &[]).next().is_none());
+
+    // The names can be reused for indexes of other types.
+    ListIndex::new("map", &mut fork).push(10u8);
+    ListIndex::new("family", &mut fork).push(20u8);
+    db.merge(fork.into_patch()).unwrap();
+
+    let snapshot = db.snapshot();
+    let list: ListIndex<_, u8> = ListIndex::new("map", &snapshot);
+    assert_eq!(list.iter().collect::<Vec<_>>(), vec![10]);
+    let list: ListIndex<_, u8> = ListIndex::new("family", &snapshot);
+    assert_eq!(list.iter().collect::<Vec<_>>(), vec![20]);
+}
+
+fn rename_index<T: Database>(db: T) {
+    let mut fork = db.fork();
+    {
+        MapIndex::new("old", &mut fork).put(&1u8, 1u8);
+        MapIndex::new_in_family("old_family", &1u8, &mut fork).put(&1u8, 1u8);
+        MapIndex::new_in_family("old_family", &2u8, &mut fork).put(&1u8, 2u8);
+    }
+    db.merge(fork.into_patch()).unwrap();
+
+    let mut fork = db.fork();
+    MapIndex::new("old", &mut fork).put(&2u8, 2u8);
+    fork.rename_index("old", "new");
+    fork.rename_index("old_family", "new_family");
+    assert!(index_info(&fork, "old").is_none());
+    assert!(index_info(&fork, "old_family").is_none());
+    // The old names can be reused for indexes of other types.
+    ProofMapIndex::new("old", &mut fork).put(&[0; 32], 0u8);
+    db.merge(fork.into_patch()).unwrap();
+
+    let snapshot = db.snapshot();
+    let map: MapIndex<_, u8, u8> = MapIndex::new("new", &snapshot);
+    assert_eq!(map.iter().collect::<Vec<_>>(), vec![(1, 1), (2, 2)]);
+    let map: MapIndex<_, u8, u8> = MapIndex::new_in_family("new_family", &1u8, &snapshot);
+    assert_eq!(map.iter().collect::<Vec<_>>(), vec![(1, 1)]);
+    let map: MapIndex<_, u8, u8> = MapIndex::new_in_family("new_family", &2u8, &snapshot);
+    assert_eq!(map.iter().collect::<Vec<_>>(), vec![(1, 2)]);
+    assert!(index_info(&*snapshot, "new_family").unwrap().is_family);
+    let map: ProofMapIndex<_, [u8; 32], u8> = ProofMapIndex::new("old", &snapshot);
+    assert_eq!(map.get(&[0; 32]), Some(0));
+}
+
+fn rollback_rename_index<T: Database>(db: T) {
+    let mut fork = db.fork();
+    MapIndex::new("old", &mut fork).put(&1u8, 1u8);
+    db.merge(fork.into_patch()).unwrap();
+
+    let mut fork = db.fork();
+    fork.checkpoint();
+    fork.rename_index("old", "new");
+    fork.drop_index("new");
+    fork.rollback();
+    assert!(index_info(&fork, "new").is_none());
+
+    let map: MapIndex<_, u8, u8> = MapIndex::new("old", &fork);
+    assert_eq!(map.iter().collect::<Vec<_>>(), vec![(1, 1)]);
+    assert!(index_info(&fork, "old").is_some());
+}
+
+fn rename_to_existing_index<T: Database>(db: T) {
+    let mut fork = db.fork();
+    MapIndex::new("old", &mut fork).put(&1u8, 1u8);
+    ListIndex::new("new", &mut fork).push(1u8);
+    fork.rename_index("old", "new");
+}
+
+fn rename_index_to_invalid_name<T: Database>(db: T) {
+    let mut fork = db.fork();
+    MapIndex::new("old", &mut fork).put(&1u8, 1u8);
+    fork.rename_index("old", "invalid-name");
+}
+
+fn operations_limit<T: Database>(db: T) {
+    let mut fork = db.fork();
+    fork.put(IDX_NAME, vec![1], vec![1]);
+
+    fork.set_operations_limit(Some(3));
+    let result = fork.catch_unwind(|fork| {
+        assert!(fork.contains(IDX_NAME, &[1]));
+        fork.put(IDX_NAME, vec![2], vec![2]);
+        assert_eq!(fork.get(IDX_NAME, &[2]), Some(vec![2]));
+    });
+    assert!(result.is_ok());
+
+    fork.set_operations_limit(Some(1));
+    let result = fork.catch_unwind(|fork| {
+        fork.remove(IDX_NAME, vec![1]);
+        fork.iter(IDX_NAME, &[]);
+    });
+    assert!(result.unwrap_err().is::<OperationsLimitExceeded>());
+
+    fork.set_operations_limit(None);
+    assert!(fork.contains(IDX_NAME, &[1]));
+    assert!(fork.contains(IDX_NAME, &[2]));
+
+    // Each entry yielded by an iterator and each removed key are counted as well.
+    fork.put(IDX_NAME, vec![3], vec![3]);
+    fork.set_operations_limit(Some(4));
+    let result = fork.catch_unwind(|fork| {
+        let mut iter = fork.iter(IDX_NAME, &[]);
+        while iter.next().is_some() {}
+    });
+    assert!(result.is_ok());
+
+    fork.set_operations_limit(Some(3));
+    let result = fork.catch_unwind(|fork| {
+        let mut iter = fork.iter(IDX_NAME, &[]);
+        while iter.next().is_some() {}
+    });
+    assert!(result.unwrap_err().is::<OperationsLimitExceeded>());
+
+    fork.set_operations_limit(Some(3));
+    let result = fork.catch_unwind(|fork| fork.remove_by_prefix(IDX_NAME, None));
+    assert!(result.unwrap_err().is::<OperationsLimitExceeded>());
+
+    fork.set_operations_limit(None);
+    assert!(fork.contains(IDX_NAME, &[3]));
+}
+
+fn time_limit<T: Database>(db: T) {
+    let mut fork = db.fork();
+
+    fork.set_time_limit(Some(Duration::from_secs(0)));
+    let result = fork.catch_unwind(|fork| fork.put(IDX_NAME, vec![1], vec![1]));
+    assert!(result.unwrap_err().is::<TimeLimitExceeded>());
+
+    fork.set_time_limit(Some(Duration::from_secs(60)));
+    fork.put(IDX_NAME, vec![2], vec![2]);
+
+    fork.set_time_limit(None);
+    assert!(!fork.contains(IDX_NAME, &[1]));
+    assert!(fork.contains(IDX_NAME, &[2]));
+}
 
 mod memorydb_tests {
     use super::super::MemoryDB;
@@ -189,12 +508,129 @@ mod memorydb_tests {
     fn test_memory_changelog() {
         super::changelog(memorydb_database());
     }
+
+    #[test]
+    fn test_memory_fork_iter_rev() {
+        super::fork_iter_rev(memorydb_database());
+    }
+
+    #[test]
+    fn test_memory_nested_savepoints() {
+        super::nested_savepoints(memorydb_database());
+    }
+
+    #[test]
+    fn test_memory_rollback_remove_by_prefix() {
+        super::rollback_remove_by_prefix(memorydb_database());
+    }
+
+    #[test]
+    fn test_memory_operations_limit() {
+        super::operations_limit(memorydb_database());
+    }
+
+    #[test]
+    fn test_memory_time_limit() {
+        super::time_limit(memorydb_database());
+    }
+
+    #[test]
+    #[should_panic(expected = "is already rolled back or released")]
+    fn test_memory_released_savepoint() {
+        super::released_savepoint(memorydb_database());
+    }
+
+    #[test]
+    fn test_memory_drop_index() {
+        super::drop_index(memorydb_database());
+    }
+
+    #[test]
+    fn test_memory_rename_index() {
+        super::rename_index(memorydb_database());
+    }
+
+    #[test]
+    fn test_memory_rollback_rename_index() {
+        super::rollback_rename_index(memorydb_database());
+    }
+
+    #[test]
+    #[should_panic(expected = "to an existing index")]
+    fn test_memory_rename_to_existing_index() {
+        super::rename_to_existing_index(memorydb_database());
+    }
+
+    #[test]
+    #[should_panic(expected = "Wrong characters using in name")]
+    fn test_memory_rename_index_to_invalid_name() {
+        super::rename_index_to_invalid_name(memorydb_database());
+    }
+
+    #[test]
+    #[should_panic(expected = "Attempt to access an internal storage infrastructure")]
+    fn test_memory_drop_internal_index() {
+        use storage::Database;
+        memorydb_database().fork().drop_index("__INDEXES_METADATA__");
+    }
+}
+
+mod filedb_tests {
+    use std::path::Path;
+    use tempdir::TempDir;
+    use super::super::{DbOptions, FileDB};
+
+    fn filedb_database(path: &Path) -> FileDB {
+        FileDB::open(path, &DbOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_filedb_fork_iter() {
+        let dir = TempDir::new("exonum_filedb1").unwrap();
+        super::fork_iter(filedb_database(dir.path()));
+    }
+
+    #[test]
+    fn test_filedb_changelog() {
+        let dir = TempDir::new("exonum_filedb2").unwrap();
+        super::changelog(filedb_database(dir.path()));
+    }
+
+    #[test]
+    fn test_filedb_fork_iter_rev() {
+        let dir = TempDir::new("exonum_filedb3").unwrap();
+        super::fork_iter_rev(filedb_database(dir.path()));
+    }
+
+    #[test]
+    fn test_filedb_nested_savepoints() {
+        let dir = TempDir::new("exonum_filedb4").unwrap();
+        super::nested_savepoints(filedb_database(dir.path()));
+    }
+
+    #[test]
+    fn test_filedb_rollback_remove_by_prefix() {
+        let dir = TempDir::new("exonum_filedb5").unwrap();
+        super::rollback_remove_by_prefix(filedb_database(dir.path()));
+    }
+
+    #[test]
+    fn test_filedb_drop_index() {
+        let dir = TempDir::new("exonum_filedb6").unwrap();
+        super::drop_index(filedb_database(dir.path()));
+    }
+
+    #[test]
+    fn test_filedb_rename_index() {
+        let dir = TempDir::new("exonum_filedb7").unwrap();
+        super::rename_index(filedb_database(dir.path()));
+    }
 }
 
 mod rocksdb_tests {
     use std::path::Path;
     use tempdir::TempDir;
-    use super::super::{RocksDB, DbOptions};
+    use super::super::{CompressionType, RocksDB, DbOptions};
 
     fn rocksdb_database(path: &Path) -> RocksDB {
         let options = DbOptions::default();
@@ -214,4 +650,443 @@ mod rocksdb_tests {
         let path = dir.path();
         super::changelog(rocksdb_database(path));
     }
+
+    #[test]
+    fn test_rocksdb_fork_iter_rev() {
+        let dir = TempDir::new("exonum_rocksdb6").unwrap();
+        let path = dir.path();
+        super::fork_iter_rev(rocksdb_database(path));
+    }
+
+    #[test]
+    fn test_rocksdb_nested_savepoints() {
+        let dir = TempDir::new("exonum_rocksdb3").unwrap();
+        let path = dir.path();
+        super::nested_savepoints(rocksdb_database(path));
+    }
+
+    #[test]
+    fn test_rocksdb_rollback_remove_by_prefix() {
+        let dir = TempDir::new("exonum_rocksdb4").unwrap();
+        let path = dir.path();
+        super::rollback_remove_by_prefix(rocksdb_database(path));
+    }
+
+    #[test]
+    #[should_panic(expected = "is already rolled back or released")]
+    fn test_rocksdb_released_savepoint() {
+        let dir = TempDir::new("exonum_rocksdb5").unwrap();
+        let path = dir.path();
+        super::released_savepoint(rocksdb_database(path));
+    }
+
+    #[test]
+    fn test_rocksdb_drop_index() {
+        let dir = TempDir::new("exonum_rocksdb7").unwrap();
+        let path = dir.path();
+        super::drop_index(rocksdb_database(path));
+    }
+
+    #[test]
+    fn test_rocksdb_rename_index() {
+        let dir = TempDir::new("exonum_rocksdb8").unwrap();
+        let path = dir.path();
+        super::rename_index(rocksdb_database(path));
+    }
+
+    #[test]
+    fn test_rocksdb_rollback_rename_index() {
+        let dir = TempDir::new("exonum_rocksdb9").unwrap();
+        let path = dir.path();
+        super::rollback_rename_index(rocksdb_database(path));
+    }
+
+    #[test]
+    #[should_panic(expected = "to an existing index")]
+    fn test_rocksdb_rename_to_existing_index() {
+        let dir = TempDir::new("exonum_rocksdb10").unwrap();
+        let path = dir.path();
+        super::rename_to_existing_index(rocksdb_database(path));
+    }
+
+    #[test]
+    #[should_panic(expected = "Wrong characters using in name")]
+    fn test_rocksdb_rename_index_to_invalid_name() {
+        let dir = TempDir::new("exonum_rocksdb14").unwrap();
+        let path = dir.path();
+        super::rename_index_to_invalid_name(rocksdb_database(path));
+    }
+
+    #[test]
+    fn test_rocksdb_tuned_options() {
+        let dir = TempDir::new("exonum_rocksdb11").unwrap();
+        let options = DbOptions {
+            compression_type: Some(CompressionType::None),
+            block_cache_size: Some(1024 * 1024),
+            write_buffer_size: Some(4 * 1024 * 1024),
+            bloom_filter_bits_per_key: Some(10),
+            wal_sync: true,
+            ..DbOptions::default()
+        };
+        super::changelog(RocksDB::open(dir.path(), &options).unwrap());
+    }
+
+    #[test]
+    fn test_rocksdb_read_only() {
+        use std::collections::Bound::*;
+        use storage::{Database, ReadOnlyRocksDB};
+
+        fn collect(mut iter: ::storage::Iter) -> Vec<(u8, u8)> {
+            let mut values = Vec::new();
+            while let Some((k, v)) = iter.next() {
+                values.push((k[0], v[0]));
+            }
+            values
+        }
+
+        let dir = TempDir::new("exonum_rocksdb12").unwrap();
+        let path = dir.path();
+        let db = rocksdb_database(path);
+        let mut fork = db.fork();
+        for &i in &[10, 20, 30] {
+            fork.put(super::IDX_NAME, vec![i], vec![i]);
+        }
+        fork.put("other_idx", vec![40], vec![40]);
+        db.merge(fork.into_patch()).unwrap();
+
+        // The database is opened while it is still used by the read-write instance.
+        let read_only = ReadOnlyRocksDB::open(path, &DbOptions::default()).unwrap();
+        let mut fork = db.fork();
+        fork.put(super::IDX_NAME, vec![50], vec![50]);
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = read_only.snapshot();
+        assert_eq!(snapshot.get(super::IDX_NAME, &[20]), Some(vec![20]));
+        assert_eq!(snapshot.get(super::IDX_NAME, &[50]), None);
+        assert_eq!(snapshot.get("absent_idx", &[20]), None);
+        assert_eq!(
+            collect(snapshot.iter(super::IDX_NAME, &[15])),
+            vec![(20, 20), (30, 30)]
+        );
+        assert_eq!(collect(snapshot.iter("absent_idx", &[])), vec![]);
+        assert_eq!(
+            collect(snapshot.iter_rev(super::IDX_NAME, Unbounded)),
+            vec![(30, 30), (20, 20), (10, 10)]
+        );
+        assert_eq!(
+            collect(snapshot.iter_rev(super::IDX_NAME, Excluded(&[20]))),
+            vec![(10, 10)]
+        );
+        assert_eq!(
+            collect(snapshot.iter_rev(super::IDX_NAME, Included(&[100]))),
+            vec![(30, 30), (20, 20), (10, 10)]
+        );
+
+        let mut iter = snapshot.iter(super::IDX_NAME, &[]);
+        assert_eq!(iter.peek(), Some((&[10][..], &[10][..])));
+        assert_eq!(iter.next(), Some((&[10][..], &[10][..])));
+        assert_eq!(iter.peek(), Some((&[20][..], &[20][..])));
+        assert_eq!(iter.peek(), Some((&[20][..], &[20][..])));
+        drop(iter);
+
+        let mut fork = read_only.fork();
+        fork.put(super::IDX_NAME, vec![60], vec![60]);
+        assert!(read_only.merge(fork.into_patch()).is_err());
+        assert!(read_only.merge_sync(read_only.fork().into_patch()).is_err());
+
+        // The later changes are visible after reopening.
+        let read_only = ReadOnlyRocksDB::open(path, &DbOptions::default()).unwrap();
+        assert_eq!(read_only.snapshot().get(super::IDX_NAME, &[50]), Some(vec![50]));
+    }
+
+    #[test]
+    fn test_rocksdb_read_only_missing_database() {
+        use storage::ReadOnlyRocksDB;
+
+        let dir = TempDir::new("exonum_rocksdb13").unwrap();
+        let path = dir.path().join("missing");
+        assert!(ReadOnlyRocksDB::open(&path, &DbOptions::default()).is_err());
+        assert!(!path.exists());
+    }
+}
+
+mod unwind_safety_tests {
+    use std::collections::BTreeMap;
+
+    use crypto::{hash, Hash};
+    use storage::db::PANIC_AFTER_CHANGES;
+    use storage::{Change, Database, Entry, Fork, HashedProofMapIndex, KeySetIndex, ListIndex,
+                  MapIndex, MemoryDB, ProofKeySetIndex, ProofListIndex, ProofMapIndex,
+                  SparseListIndex, ValueSetIndex};
+
+    // All the changes in the fork, which are visible to the readers.
+    fn fork_changes(fork: &Fork) -> BTreeMap<(String, Vec<u8>), Option<Vec<u8>>> {
+        let mut result = BTreeMap::new();
+        for (name, changes) in fork.patch().iter() {
+            for (key, change) in changes.iter() {
+                let value = match *change {
+                    Change::Put(ref value) => Some(value.clone()),
+                    Change::Delete => None,
+                };
+                result.insert((name.clone(), key.clone()), value);
+            }
+        }
+        result
+    }
+
+    // Runs the operation with a panic injected before each of the changes it makes to the fork,
+    // and checks that the fork remains in the state before the operation and can be used
+    // to perform the operation once more.
+    fn check_unwind_safety<I, F>(init: I, operation: F)
+    where
+        I: Fn(&mut Fork),
+        F: Fn(&mut Fork),
+    {
+        let db = MemoryDB::new();
+        let mut fork = db.fork();
+        init(&mut fork);
+        db.merge(fork.into_patch()).unwrap();
+
+        let prepare = || {
+            let mut fork = db.fork();
+            fork.put("unrelated", vec![1], vec![1]);
+            fork
+        };
+        let initial = fork_changes(&prepare());
+        let expected = {
+            let mut fork = prepare();
+            operation(&mut fork);
+            fork_changes(&fork)
+        };
+        assert_ne!(initial, expected);
+
+        for changes in 0.. {
+            let mut fork = prepare();
+            PANIC_AFTER_CHANGES.with(|limit| limit.set(Some(changes)));
+            let result = fork.catch_unwind(|fork| operation(fork));
+            PANIC_AFTER_CHANGES.with(|limit| limit.set(None));
+            if result.is_ok() {
+                assert!(changes > 0);
+                assert_eq!(fork_changes(&fork), expected);
+                break;
+            }
+
+            assert_eq!(fork_changes(&fork), initial);
+            operation(&mut fork);
+            assert_eq!(fork_changes(&fork), expected);
+        }
+    }
+
+    #[test]
+    fn test_entry_unwind_safety() {
+        check_unwind_safety(
+            |fork| Entry::new("entry", fork).set(1u64),
+            |fork| {
+                let mut entry = Entry::new("entry", fork);
+                entry.set(2u64);
+                entry.remove();
+            },
+        );
+    }
+
+    #[test]
+    fn test_list_index_unwind_safety() {
+        check_unwind_safety(
+            |fork| ListIndex::new("list", fork).extend(0..5u64),
+            |fork| {
+                let mut list = ListIndex::new("list", fork);
+                list.extend(5..10u64);
+                list.set(1, 100);
+                list.pop();
+                list.truncate(3);
+            },
+        );
+    }
+
+    #[test]
+    fn test_sparse_list_index_unwind_safety() {
+        check_unwind_safety(
+            |fork| {
+                let mut list = SparseListIndex::new("sparse_list", fork);
+                for i in 0..5u64 {
+                    list.push(i);
+                }
+            },
+            |fork| {
+                let mut list = SparseListIndex::new("sparse_list", fork);
+                list.push(5u64);
+                list.remove(1);
+                list.set(2, 100);
+                list.pop();
+            },
+        );
+    }
+
+    #[test]
+    fn test_map_index_unwind_safety() {
+        check_unwind_safety(
+            |fork| {
+                let mut map = MapIndex::new("map", fork);
+                for i in 0..5u8 {
+                    map.put(&i, u64::from(i));
+                }
+            },
+            |fork| {
+                let mut map = MapIndex::new("map", fork);
+                map.put(&1u8, 100u64);
+                map.put(&10u8, 10);
+                map.remove(&2);
+            },
+        );
+    }
+
+    #[test]
+    fn test_index_family_unwind_safety() {
+        check_unwind_safety(
+            |fork| MapIndex::new_in_family("family", &1u8, fork).put(&1u8, 1u8),
+            |fork| {
+                MapIndex::new_in_family("family", &2u8, &mut *fork).put(&1u8, 2u8);
+                MapIndex::<_, u8, u8>::new_in_family("family", &1u8, fork).clear();
+            },
+        );
+    }
+
+    #[test]
+    fn test_key_set_index_unwind_safety() {
+        check_unwind_safety(
+            |fork| KeySetIndex::new("key_set", fork).insert(1u8),
+            |fork| {
+                let mut set = KeySetIndex::new("key_set", fork);
+                set.insert(2u8);
+                set.insert(3);
+                set.remove(&1);
+            },
+        );
+    }
+
+    #[test]
+    fn test_value_set_index_unwind_safety() {
+        check_unwind_safety(
+            |fork| ValueSetIndex::new("value_set", fork).insert(1u64),
+            |fork| {
+                let mut set = ValueSetIndex::new("value_set", fork);
+                set.insert(2u64);
+                set.insert(3);
+                set.remove(&1);
+            },
+        );
+    }
+
+    #[test]
+    fn test_proof_list_index_unwind_safety() {
+        check_unwind_safety(
+            |fork| ProofListIndex::new("proof_list", fork).extend(0..5u64),
+            |fork| {
+                let mut list = ProofListIndex::new("proof_list", fork);
+                list.extend(5..9u64);
+                list.set(2, 100);
+            },
+        );
+    }
+
+    #[test]
+    fn test_proof_list_index_clear_unwind_safety() {
+        check_unwind_safety(
+            |fork| ProofListIndex::new("proof_list", fork).extend(0..5u64),
+            |fork| ProofListIndex::<_, u64>::new("proof_list", fork).clear(),
+        );
+    }
+
+    #[test]
+    fn test_proof_map_index_unwind_safety() {
+        check_unwind_safety(
+            |fork| {
+                let mut map = ProofMapIndex::new("proof_map", fork);
+                for i in 0..8u8 {
+                    map.put(&hash(&[i]), u64::from(i));
+                }
+            },
+            |fork| {
+                let mut map = ProofMapIndex::new("proof_map", fork);
+                map.put(&hash(&[1]), 100u64);
+                map.put(&hash(&[100]), 100);
+                map.remove(&hash(&[2]));
+            },
+        );
+    }
+
+    #[test]
+    fn test_deferred_proof_map_index_unwind_safety() {
+        check_unwind_safety(
+            |fork| {
+                let mut map = ProofMapIndex::new("proof_map", fork);
+                for i in 0..8u8 {
+                    map.put(&hash(&[i]), u64::from(i));
+                }
+            },
+            |fork| {
+                let mut map = ProofMapIndex::new("proof_map", fork);
+                let mut deferred = map.deferred();
+                for i in 5..12u8 {
+                    deferred.put(&hash(&[i]), 100u64);
+                }
+                deferred.remove(&hash(&[2]));
+            },
+        );
+    }
+
+    #[test]
+    fn test_hashed_proof_map_index_unwind_safety() {
+        check_unwind_safety(
+            |fork| {
+                let mut map = HashedProofMapIndex::new("hashed_proof_map", fork);
+                for i in 0..5u8 {
+                    map.put(&i, u64::from(i));
+                }
+            },
+            |fork| {
+                let mut map = HashedProofMapIndex::new("hashed_proof_map", fork);
+                map.put(&1u8, 100u64);
+                map.put(&10u8, 10);
+                map.remove(&2);
+            },
+        );
+    }
+
+    #[test]
+    fn test_proof_key_set_index_unwind_safety() {
+        check_unwind_safety(
+            |fork| {
+                let mut set = ProofKeySetIndex::new("proof_key_set", fork);
+                for i in 0..5u8 {
+                    set.insert(hash(&[i]));
+                }
+            },
+            |fork| {
+                let mut set: ProofKeySetIndex<_, Hash> =
+                    ProofKeySetIndex::new("proof_key_set", fork);
+                set.insert(hash(&[10]));
+                set.remove(&hash(&[1]));
+            },
+        );
+    }
+
+    #[test]
+    fn test_drop_and_rename_index_unwind_safety() {
+        check_unwind_safety(
+            |fork| {
+                {
+                    let mut map = MapIndex::new("map", &mut *fork);
+                    for i in 0..5u8 {
+                        map.put(&i, u64::from(i));
+                    }
+                }
+                ListIndex::new("list", fork).extend(0..5u64);
+            },
+            |fork| {
+                fork.drop_index("list");
+                fork.rename_index("map", "new_map");
+            },
+        );
+    }
 }
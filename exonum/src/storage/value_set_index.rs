@@ -210,6 +210,52 @@ where
         ValueSetIndexIter { base_iter: self.base.iter_from(&(), from) }
     }
 
+    /// An iterator visiting all elements in descending order of their hashes. The iterator
+    /// element type is (Hash, V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ValueSetIndex};
+    ///
+    /// let db = MemoryDB::new();
+    /// let name  = "name";
+    /// let snapshot = db.snapshot();
+    /// let index: ValueSetIndex<_, u8> = ValueSetIndex::new(name, &snapshot);
+    ///
+    /// for val in index.iter_rev() {
+    ///     println!("{:?}", val);
+    /// }
+    /// ```
+    pub fn iter_rev(&self) -> ValueSetIndexIter<V> {
+        ValueSetIndexIter { base_iter: self.base.iter_rev(&()) }
+    }
+
+    /// An iterator visiting the elements with hashes in the range `[from, to)` in ascending
+    /// order. The iterator element type is (Hash, V).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum::storage::{MemoryDB, Database, ValueSetIndex};
+    /// use exonum::crypto::Hash;
+    ///
+    /// let db = MemoryDB::new();
+    /// let name  = "name";
+    /// let snapshot = db.snapshot();
+    /// let index: ValueSetIndex<_, u8> = ValueSetIndex::new(name, &snapshot);
+    ///
+    /// let from = Hash::new([0; 32]);
+    /// let to = Hash::new([0x80; 32]);
+    ///
+    /// for val in index.range(&from, &to) {
+    ///     println!("{:?}", val);
+    /// }
+    /// ```
+    pub fn range(&self, from: &Hash, to: &Hash) -> ValueSetIndexIter<V> {
+        ValueSetIndexIter { base_iter: self.base.range(&(), from, to) }
+    }
+
     /// An iterator visiting hashes of all elements in ascending order. The iterator element type
     /// is [Hash](../../crypto/struct.Hash.html).
     ///
@@ -18,7 +18,8 @@ use exonum::messages::{Message, RawTransaction};
 use exonum::encoding::Error as MessageError;
 use exonum::crypto::{PublicKey, SecretKey, Hash, gen_keypair};
 use exonum::storage::{Fork, Snapshot};
-use exonum::blockchain::{Service, Transaction, TransactionSet, ExecutionResult};
+use exonum::blockchain::{Service, Transaction, TransactionSet, ExecutionResult, TxExpiration};
+use exonum::helpers::Height;
 
 pub const TIMESTAMPING_SERVICE: u16 = 129;
 pub const TIMESTAMPING_TRANSACTION_MESSAGE_ID: u16 = 128;
@@ -31,6 +32,12 @@ transactions! {
             pub_key: &PublicKey,
             data: &[u8],
         }
+
+        struct ExpiringTimestampTx {
+            pub_key: &PublicKey,
+            data: &[u8],
+            max_height: Height,
+        }
     }
 }
 
@@ -44,6 +51,19 @@ impl Transaction for TimestampTx {
     }
 }
 
+impl Transaction for ExpiringTimestampTx {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.pub_key())
+    }
+
+    fn execute(&self, _: &mut Fork) -> ExecutionResult {
+        Ok(())
+    }
+
+    fn expiration(&self) -> Option<TxExpiration> {
+        Some(TxExpiration::MaxHeight(self.max_height()))
+    }
+}
 
 #[derive(Default)]
 pub struct TimestampingService {}
@@ -37,7 +37,8 @@ use exonum::node::state::{PREVOTES_REQUEST_TIMEOUT, PROPOSE_REQUEST_TIMEOUT,
                           TRANSACTIONS_REQUEST_TIMEOUT};
 use exonum::helpers::{Height, Round, user_agent};
 
-use sandbox::timestamping::{TimestampTx, TimestampingTxGenerator, TIMESTAMPING_SERVICE};
+use sandbox::timestamping::{ExpiringTimestampTx, TimestampTx, TimestampingTxGenerator,
+                            TIMESTAMPING_SERVICE};
 use sandbox::timestamping_sandbox;
 use sandbox::sandbox::sandbox_with_services_uninitialized;
 use sandbox::sandbox_tests_helper::*;
@@ -2947,6 +2948,142 @@ fn handle_tx_ignore_existing_tx_in_blockchain() {
     sandbox.add_time(Duration::from_millis(0));
 }
 
+/// - ignore expired transaction
+/// - idea of test is to receive expired and valid txs
+/// - only valid tx is added to the pool
+#[test]
+fn handle_tx_ignore_expired_tx() {
+    let sandbox = timestamping_sandbox();
+    let (pub_key, sec_key) = gen_keypair();
+
+    let expired_tx = ExpiringTimestampTx::new(&pub_key, &[1], HEIGHT_ZERO, &sec_key);
+    let tx = ExpiringTimestampTx::new(&pub_key, &[2], HEIGHT_ONE, &sec_key);
+    sandbox.recv(&expired_tx);
+    sandbox.recv(&tx);
+
+    assert_eq!(sandbox.transactions_hashes(), vec![tx.hash()]);
+}
+
+/// - ignore propose with expired transaction
+/// - idea of test is to receive propose with unknown tx
+/// - receive that tx, which turns out to be expired
+/// - prevote is not broadcast and the tx is not requested anymore
+#[test]
+fn ignore_propose_with_expired_tx() {
+    let sandbox = timestamping_sandbox();
+    let (pub_key, sec_key) = gen_keypair();
+    let tx = ExpiringTimestampTx::new(&pub_key, &[1], HEIGHT_ZERO, &sec_key);
+
+    let propose = ProposeBuilder::new(&sandbox)
+        .with_duration_since_sandbox_time(sandbox.propose_timeout())
+        .with_tx_hashes(&[tx.hash()])
+        .build();
+    sandbox.recv(&propose);
+    sandbox.recv(&tx);
+
+    // Neither a prevote for the invalid propose nor the request timeout
+    // trigger a request of the expired transaction.
+    sandbox.recv(&Prevote::new(
+        VALIDATOR_3,
+        HEIGHT_ONE,
+        ROUND_ONE,
+        &propose.hash(),
+        Round::zero(),
+        sandbox.s(VALIDATOR_3),
+    ));
+    sandbox.add_time(Duration::from_millis(TRANSACTIONS_REQUEST_TIMEOUT));
+
+    sandbox.assert_lock(LOCK_ZERO, None);
+    assert!(sandbox.transactions_hashes().is_empty());
+    sandbox.add_time(Duration::from_millis(0));
+}
+
+/// - remove expired transactions from the pool after commit
+/// - idea of test is to receive tx, which expires at the current height
+/// - commit block without this tx
+/// - the tx is removed from the pool
+#[test]
+fn remove_expired_tx_from_pool_after_commit() {
+    let sandbox = timestamping_sandbox();
+    let (pub_key, sec_key) = gen_keypair();
+    let expiring_tx = ExpiringTimestampTx::new(&pub_key, &[1], HEIGHT_ONE, &sec_key);
+    let tx = gen_timestamping_tx();
+
+    let propose = ProposeBuilder::new(&sandbox)
+        .with_duration_since_sandbox_time(sandbox.propose_timeout())
+        .with_tx_hashes(&[tx.hash()])
+        .build();
+    let block = BlockBuilder::new(&sandbox)
+        .with_duration_since_sandbox_time(sandbox.propose_timeout())
+        .with_tx_hash(&tx.hash())
+        .with_state_hash(&sandbox.compute_state_hash(&[tx.raw().clone()]))
+        .build();
+
+    let precommit_1 = Precommit::new(
+        VALIDATOR_1,
+        HEIGHT_ONE,
+        ROUND_ONE,
+        &propose.hash(),
+        &block.hash(),
+        sandbox.time(),
+        sandbox.s(VALIDATOR_1),
+    );
+    let precommit_2 = Precommit::new(
+        VALIDATOR_2,
+        HEIGHT_ONE,
+        ROUND_ONE,
+        &propose.hash(),
+        &block.hash(),
+        sandbox.time(),
+        sandbox.s(VALIDATOR_2),
+    );
+    let precommit_3 = Precommit::new(
+        VALIDATOR_3,
+        HEIGHT_ONE,
+        ROUND_ONE,
+        &propose.hash(),
+        &block.hash(),
+        sandbox.time(),
+        sandbox.s(VALIDATOR_3),
+    );
+
+    sandbox.recv(&expiring_tx);
+    assert_eq!(sandbox.transactions_hashes(), vec![expiring_tx.hash()]);
+
+    sandbox.recv(&precommit_1);
+    sandbox.add_time(Duration::from_millis(PROPOSE_REQUEST_TIMEOUT));
+    sandbox.send(
+        sandbox.a(VALIDATOR_1),
+        &make_request_propose_from_precommit(&sandbox, &precommit_1),
+    );
+    sandbox.send(
+        sandbox.a(VALIDATOR_1),
+        &make_request_prevote_from_precommit(&sandbox, &precommit_1),
+    );
+
+    sandbox.recv(&precommit_2);
+    sandbox.add_time(Duration::from_millis(PROPOSE_REQUEST_TIMEOUT));
+    sandbox.send(
+        sandbox.a(VALIDATOR_2),
+        &make_request_propose_from_precommit(&sandbox, &precommit_2),
+    );
+    sandbox.send(
+        sandbox.a(VALIDATOR_2),
+        &make_request_prevote_from_precommit(&sandbox, &precommit_2),
+    );
+    sandbox.recv(&propose);
+    sandbox.recv(&tx);
+    sandbox.broadcast(&make_prevote_from_propose(&sandbox, &propose));
+
+    sandbox.recv(&precommit_3);
+    sandbox.assert_state(HEIGHT_TWO, ROUND_ONE);
+    sandbox.check_broadcast_status(HEIGHT_TWO, &block.hash());
+
+    // The transaction cannot be included into the block at the new height.
+    assert!(sandbox.transactions_hashes().is_empty());
+    sandbox.add_time(Duration::from_millis(0));
+}
+
 /// HANDLE ROUND TIMEOUT:
 /// - Ignore if height and round are not the same
 /// scenario:
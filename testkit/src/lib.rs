@@ -145,7 +145,7 @@ use exonum::blockchain::{Blockchain, Schema as CoreSchema, Service, StoredConfig
 use exonum::crypto;
 use exonum::helpers::{Height, ValidatorId};
 use exonum::node::{ApiSender, ExternalMessage, State as NodeState, TxPool, NodeApiConfig};
-use exonum::storage::{MemoryDB, Patch, Snapshot};
+use exonum::storage::{MemoryDB, MeteredDB, Patch, Snapshot};
 
 #[macro_use]
 mod macros;
@@ -326,7 +326,7 @@ impl TestKit {
         let db_handler = db.handler();
 
         let mut blockchain = Blockchain::new(
-            db,
+            MeteredDB::new(Box::new(db)),
             services,
             *network.us().service_keypair().0,
             network.us().service_keypair().1.clone(),
@@ -18,8 +18,8 @@ extern crate bodyparser;
 extern crate iron;
 extern crate router;
 
-use exonum::blockchain::{ApiContext, Blockchain, Service, Transaction, TransactionSet,
-                         ExecutionError, ExecutionResult};
+use exonum::blockchain::{ApiContext, Blockchain, Event, Schema, Service, Transaction,
+                         TransactionSet, ExecutionError, ExecutionResult};
 use exonum::messages::{Message, RawTransaction};
 use exonum::node::{ApiSender, TransactionSend};
 use exonum::storage::{Entry, Fork, Snapshot};
@@ -72,6 +72,19 @@ impl<'a> CounterSchema<&'a mut Fork> {
     }
 }
 
+// // // // Events // // // //
+
+#[derive(Serialize)]
+pub struct CounterIncremented {
+    pub count: u64,
+}
+
+impl Event for CounterIncremented {
+    fn event_name(&self) -> &str {
+        "incremented"
+    }
+}
+
 // // // // Transactions // // // //
 
 transactions! {
@@ -86,6 +99,16 @@ transactions! {
         struct TxReset {
             author: &PublicKey,
         }
+
+        struct TxIncrementWithEvent {
+            author: &PublicKey,
+            by: u64,
+        }
+
+        struct TxFailWithPayload {
+            author: &PublicKey,
+            code: u8,
+        }
     }
 }
 
@@ -110,6 +133,30 @@ impl Transaction for TxIncrement {
     }
 }
 
+impl Transaction for TxIncrementWithEvent {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.author())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let count = CounterSchema::new(&mut *fork).inc_count(self.by());
+        Schema::new(fork).emit_event(&CounterIncremented { count });
+        Ok(())
+    }
+}
+
+impl Transaction for TxFailWithPayload {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.author())
+    }
+
+    fn execute(&self, _: &mut Fork) -> ExecutionResult {
+        let mut error = ExecutionError::with_payload(self.code(), &json!({ "code": self.code() }));
+        error.set_description("Failed on purpose".to_string());
+        Err(error)
+    }
+}
+
 impl TxReset {
     pub fn verify_author(&self) -> bool {
         use exonum::encoding::serialize::FromHex;
@@ -28,13 +28,14 @@ use exonum::blockchain::Transaction;
 use exonum::crypto::{self, PublicKey, CryptoHash};
 use exonum::helpers::Height;
 use exonum::messages::Message;
-use exonum::encoding::serialize::FromHex;
+use exonum::encoding::serialize::{FromHex, encode_hex};
 use exonum::encoding::serialize::json::ExonumJson;
 use exonum_testkit::{ApiKind, ComparableSnapshot, TestKit, TestKitApi, TestKitBuilder};
 use serde_json::Value;
 
 mod counter;
-use counter::{CounterSchema, CounterService, TransactionResponse, TxIncrement, TxReset, ADMIN_KEY};
+use counter::{CounterSchema, CounterService, TransactionResponse, TxFailWithPayload, TxIncrement,
+              TxIncrementWithEvent, TxReset, ADMIN_KEY};
 
 fn init_testkit() -> (TestKit, TestKitApi) {
     let testkit = TestKit::for_service(CounterService);
@@ -596,6 +597,34 @@ fn test_explorer_transaction() {
     }
 }
 
+#[test]
+fn test_explorer_transaction_events() {
+    let (mut testkit, api) = init_testkit();
+    let (pubkey, key) = crypto::gen_keypair();
+    let tx = TxIncrementWithEvent::new(&pubkey, 5, &key);
+    let other_tx = TxIncrement::new(&pubkey, 3, &key);
+    testkit.create_block_with_transactions(txvec![tx.clone(), other_tx.clone()]);
+
+    let info: Value = api.get(
+        ApiKind::Explorer,
+        &format!("v1/transactions/{}", &tx.hash().to_string()),
+    );
+    assert_eq!(
+        info["events"],
+        json!([{
+            "service_id": 1,
+            "name": "incremented",
+            "payload": { "count": 5 },
+        }])
+    );
+
+    let info: Value = api.get(
+        ApiKind::Explorer,
+        &format!("v1/transactions/{}", &other_tx.hash().to_string()),
+    );
+    assert_eq!(info["events"], json!([]));
+}
+
 #[test]
 fn test_explorer_transaction_statuses() {
     fn assert_status(api: &TestKitApi, tx: &Transaction, expected_status: &Value) {
@@ -625,24 +654,77 @@ fn test_explorer_transaction_statuses() {
         let (pubkey, key) = crypto::gen_keypair();
         TxIncrement::new(&pubkey, u64::max_value() - 3, &key)
     };
+    let payload_error_tx = {
+        let (pubkey, key) = crypto::gen_keypair();
+        TxFailWithPayload::new(&pubkey, 2, &key)
+    };
 
     testkit.create_block_with_transactions(txvec![
         tx.clone(),
         error_tx.clone(),
         panicking_tx.clone(),
+        payload_error_tx.clone(),
     ]);
 
     assert_status(&api, &tx, &json!({ "type": "success" }));
     assert_status(
         &api,
         &error_tx,
-        &json!({ "type": "error", "code": 0, "description": "Adding zero does nothing!" }),
+        &json!({
+            "type": "error",
+            "code": 0,
+            "description": "Adding zero does nothing!",
+            "service_id": 1,
+        }),
     );
     assert_status(
         &api,
         &panicking_tx,
         &json!({ "type": "panic", "description": "attempt to add with overflow" }),
     );
+    assert_status(
+        &api,
+        &payload_error_tx,
+        &json!({
+            "type": "error",
+            "code": 2,
+            "description": "Failed on purpose",
+            "service_id": 1,
+            "payload": { "code": 2 },
+        }),
+    );
+}
+
+#[test]
+fn test_explorer_simulate_transaction() {
+    fn simulate(api: &TestKitApi, tx: &Transaction, max_operations: u64) -> Value {
+        let request = json!({
+            "tx_body": encode_hex(tx.raw()),
+            "max_operations": max_operations,
+            "touched_keys": true,
+        });
+        api.post(ApiKind::Explorer, "v1/transactions/simulate", &request)
+    }
+
+    let (mut testkit, api) = init_testkit();
+    let (pubkey, key) = crypto::gen_keypair();
+    testkit.create_block_with_transaction(TxIncrement::new(&pubkey, 5, &key));
+
+    let info = simulate(&api, &TxIncrement::new(&pubkey, 3, &key), 1000);
+    assert_eq!(info["status"], json!({ "type": "success" }));
+    assert_eq!(info["touched_keys"]["counter.count"], json!([""]));
+    let info = simulate(&api, &TxIncrement::new(&pubkey, 0, &key), 1000);
+    assert_eq!(info["status"]["code"], 0);
+    assert_eq!(info["touched_keys"], json!({}));
+    // The changes are discarded.
+    let counter: u64 = api.get(ApiKind::Service("counter"), "count");
+    assert_eq!(counter, 5);
+
+    let info = simulate(&api, &TxIncrement::new(&pubkey, 3, &key), 1);
+    assert_eq!(
+        info["description"],
+        "Bad request: Execution budget of 1 storage operations is exceeded"
+    );
 }
 
 // Make sure that boxed transaction can be used in the `TestKitApi::send`.
@@ -20,6 +20,7 @@ extern crate pretty_assertions;
 use exonum_testkit::{ApiKind, TestKitBuilder};
 use exonum::api::public::HealthCheckInfo;
 use exonum::helpers::user_agent;
+use exonum::storage::{IndexDetails, IndexInfo, IndexType, StorageStats};
 
 #[test]
 fn test_healthcheck_connectivity_false() {
@@ -38,3 +39,43 @@ fn test_user_agent_info() {
     let expected = user_agent::get();
     assert_eq!(info, expected);
 }
+
+#[test]
+fn test_storage_indexes() {
+    let mut testkit = TestKitBuilder::validator().with_validators(2).create();
+    testkit.create_blocks_until(exonum::helpers::Height(3));
+    let api = testkit.api();
+
+    let indexes: Vec<IndexInfo> = api.get_private(ApiKind::System, "v1/storage/indexes");
+    let blocks = indexes
+        .iter()
+        .find(|info| info.name == "core.blocks")
+        .expect("No blocks index");
+    assert_eq!(blocks.index_type, IndexType::Map);
+    assert!(!blocks.is_family);
+    assert!(indexes.iter().any(|info| {
+        info.name == "core.precommits" && info.is_family
+    }));
+
+    let details: IndexDetails =
+        api.get_private(ApiKind::System, "v1/storage/indexes/core.blocks");
+    assert_eq!(details.name, blocks.name);
+    assert_eq!(details.index_type, IndexType::Map);
+    assert!(!details.is_family);
+    // The genesis block and 3 more blocks.
+    assert_eq!(details.entries, 4);
+}
+
+#[test]
+fn test_storage_stats() {
+    let mut testkit = TestKitBuilder::validator().with_validators(2).create();
+    testkit.create_blocks_until(exonum::helpers::Height(3));
+    let api = testkit.api();
+
+    let stats: StorageStats = api.get_private(ApiKind::System, "v1/storage/stats");
+    // Each block adds a single block record.
+    let blocks = stats.last_patch.get("core.blocks").expect("No blocks index");
+    assert_eq!(blocks.writes, 1);
+    assert!(stats.patches >= 3);
+    assert!(stats.total.get("core.blocks").unwrap().writes >= 3);
+}